@@ -0,0 +1,86 @@
+//! Print a report of how the objects Nano sends differ from what this crate expects.
+//!
+//! `cargo run --example schema_drift [cassette.json]`
+//!
+//! With a cassette, the responses recorded in it are checked. Without one, a session is recorded
+//! from the live API, logging in with `NANO_USERNAME` and `NANO_PASSWORD`, and saved to
+//! `schema_drift.json` before it's checked. Requests that fail, including ones that fail because
+//! of drift, are still checked, and the report is printed as JSON to stdout.
+
+use nanowrimo::{Error, NanoClient, NanoKind, ObjectInfo};
+use nanowrimo::cassette::{Cassette, Recorder};
+use nanowrimo::credentials::EnvCredentials;
+use nanowrimo::schema::DriftDetector;
+
+use std::env;
+use std::fmt::Debug;
+use std::process;
+
+fn check<T: Debug>(what: &str, res: Result<T, Error>) -> Option<T> {
+    res.map_err(|err| eprintln!("Couldn't get {}: {}", what, err)).ok()
+}
+
+async fn record() -> Result<Cassette, Error> {
+    let recorder = Recorder::new();
+    let client = NanoClient::builder()
+        .credentials(EnvCredentials::default())
+        .middleware(recorder.clone())
+        .build()?;
+    client.login().await?;
+
+    let user = check("current user", client.current_user_include(&[
+        NanoKind::Project, NanoKind::FavoriteAuthor, NanoKind::FavoriteBook, NanoKind::Genre,
+        NanoKind::GroupUser, NanoKind::Group, NanoKind::UserBadge, NanoKind::StopWatch, NanoKind::Timer,
+        NanoKind::WritingLocation, NanoKind::WritingMethod,
+    ]).await);
+
+    let projects = match &user {
+        Some(user) => check("projects", client.get_all_filtered(NanoKind::Project, &[("user_id", user.data.id())]).await),
+        None => None
+    };
+    for project in projects.iter().flat_map(|projects| &projects.data) {
+        let project = check("project", client.get_id_include(project.kind(), project.id(), &[
+            NanoKind::ProjectChallenge, NanoKind::ProjectSession, NanoKind::Challenge,
+        ]).await);
+
+        let included = project.iter().flat_map(|project| project.included.iter().flatten());
+        for pc in included.filter(|obj| obj.kind() == NanoKind::ProjectChallenge) {
+            check("daily aggregates", client.daily_aggregates(pc.id()).await);
+        }
+    }
+
+    for kind in &[NanoKind::Badge, NanoKind::Challenge, NanoKind::WritingLocation, NanoKind::WritingMethod] {
+        check(kind.api_name(), client.get_all(*kind).await);
+    }
+    check("groups", client.get_all_include(NanoKind::Group, &[NanoKind::GroupExternalLink, NanoKind::Location]).await);
+    check("location groups", client.get_all_include(NanoKind::LocationGroup, &[NanoKind::Location]).await);
+    check("nanomessages", client.get_all(NanoKind::NanoMessage).await);
+    check("notifications", client.notifications().await);
+    check("pages", client.pages("about-nano").await);
+    check("offers", client.offers().await);
+
+    let cassette = recorder.cassette();
+    cassette.save("schema_drift.json")?;
+    Ok(cassette)
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Error> {
+    let cassette = match env::args().nth(1) {
+        Some(path) => Cassette::load(path)?,
+        None => record().await?
+    };
+
+    let mut detector = DriftDetector::new();
+    detector.observe_cassette(&cassette);
+    let report = detector.report();
+
+    println!("{}", serde_json::to_string_pretty(&report)?);
+
+    if report.is_breaking() {
+        process::exit(2);
+    } else if report.has_drift() {
+        process::exit(1);
+    }
+    Ok(())
+}
@@ -0,0 +1,23 @@
+//! Calculations over data that's already been fetched from Nano, such as progress towards a
+//! challenge goal. Nothing here sends requests, so results can be recomputed as often as needed.
+//!
+//! Dates are the writing days Nano reports, and "today" is always passed in, rather than taken
+//! from the clock, so results don't depend on the time zone the code runs in. Where only a
+//! timestamp is known, such as the end of a session, it's put on a day in the writer's time zone.
+
+mod badges;
+mod history;
+mod productivity;
+mod progress;
+mod series;
+mod streak;
+
+#[cfg(test)]
+mod tests;
+
+pub use badges::{BadgePrediction, BadgeRule, BadgeRules};
+pub use history::{HistoryDiscrepancy, HistoryEntry, HistoryEvent, WritingHistory};
+pub use productivity::{Conditions, Productivity, SessionStats};
+pub use progress::Progress;
+pub use series::{DailySeries, WeekdayStats};
+pub use streak::{Streak, StreakCounter, StreakRules, Streaks};
@@ -0,0 +1,200 @@
+use super::DailySeries;
+use crate::data::UserBadgeData;
+use crate::enums::UnitType;
+use crate::error::Error;
+use crate::quantity::Quantity;
+
+use std::collections::BTreeMap;
+
+use chrono::{Duration, NaiveDate};
+use serde::{Deserialize, Serialize};
+
+/// What has to be done to earn a badge
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq, Eq)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+pub enum BadgeRule {
+    /// Reach a total count
+    Total { count: u64 },
+    /// Reach the goal of the challenge, whatever it is
+    Goal,
+    /// Write at least a count in a single day
+    Day { count: u64 },
+    /// Write on a number of days in a row
+    Streak { days: u64 },
+}
+
+/// The rules for earning badges, keyed by the ID of the Badge they're for. Usually loaded from
+/// JSON, such as `{"12": {"type": "total", "count": 25000}, "13": {"type": "goal"}}`, so they can
+/// change with Nano's badges without changing code
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Default)]
+#[serde(transparent)]
+pub struct BadgeRules {
+    rules: BTreeMap<u64, BadgeRule>,
+}
+
+/// Where a ProjectChallenge stands on a single badge
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct BadgePrediction {
+    pub badge_id: u64,
+    pub rule: BadgeRule,
+    /// The day the rule was met, if it has been
+    pub earned_on: Option<NaiveDate>,
+    /// Whether Nano has already awarded the badge
+    pub awarded: bool,
+    /// The unit of the series the prediction was made from, which counts in the rule are in
+    pub unit_type: UnitType,
+    /// How far from meeting the rule: days for a streak, the count still to write today for a
+    /// single day, or the count still to write for anything else. Zero once the rule has been met
+    pub remaining: u64,
+    /// When the rule will be met at the current pace. None once it has been, for rules that
+    /// don't depend on pace, or if it won't be met before the challenge ends
+    pub expected: Option<NaiveDate>,
+}
+
+impl BadgePrediction {
+    /// Whether the badge has been earned, either by meeting its rule or by being awarded
+    pub fn is_earned(&self) -> bool {
+        self.awarded || self.earned_on.is_some()
+    }
+
+    /// Whether the badge hasn't been earned yet, but is expected to be by a day
+    pub fn is_expected_by(&self, day: NaiveDate) -> bool {
+        !self.is_earned() && self.expected.is_some_and(|expected| expected <= day)
+    }
+
+    /// Describe how close the badge is, such as "2,500 words away from the 25k badge", given
+    /// the badge's title
+    pub fn describe(&self, title: &str) -> String {
+        if self.is_earned() {
+            return format!("Earned the {} badge", title)
+        }
+
+        match self.rule {
+            BadgeRule::Total { .. } | BadgeRule::Goal => {
+                let remaining = Quantity::from_count(self.unit_type, self.remaining as i64);
+                format!("{} away from the {} badge", remaining, title)
+            },
+            BadgeRule::Day { count } => {
+                let count = Quantity::from_count(self.unit_type, count as i64);
+                format!("{} in one day to earn the {} badge", count, title)
+            },
+            BadgeRule::Streak { .. } => {
+                format!("{} more days in a row to earn the {} badge", self.remaining, title)
+            },
+        }
+    }
+}
+
+impl BadgeRules {
+    /// Create an empty set of rules
+    pub fn new() -> BadgeRules {
+        BadgeRules::default()
+    }
+
+    /// Set the rule for a badge, replacing any it already had
+    pub fn insert(&mut self, badge_id: u64, rule: BadgeRule) {
+        self.rules.insert(badge_id, rule);
+    }
+
+    /// Get the rule for a badge, if it has one
+    pub fn get(&self, badge_id: u64) -> Option<BadgeRule> {
+        self.rules.get(&badge_id).copied()
+    }
+
+    /// Predict every badge with a rule, from a ProjectChallenge's daily series and goal, as of a
+    /// day. Days after `today` are ignored, and badges in `awarded` are counted as earned even
+    /// if the series doesn't show it. Predictions are in order of badge ID. Fails if the goal
+    /// isn't in the unit of the series
+    pub fn predict(&self, series: &DailySeries, goal: Quantity, today: NaiveDate, awarded: &[UserBadgeData]) -> Result<Vec<BadgePrediction>, Error> {
+        if goal.unit_type() != series.unit_type() {
+            return Err(Error::ValidationError(format!(
+                "Can't predict badges for a goal in {:?} from a series in {:?}", goal.unit_type(), series.unit_type()
+            )));
+        }
+        let goal = goal.count().max(0) as u64;
+
+        let days = series.iter()
+            .filter(|(day, _)| *day <= today)
+            .collect::<Vec<_>>();
+        let total = days.iter().map(|(_, count)| count).sum::<u64>();
+        let pace = if days.is_empty() { 0.0 } else { total as f64 / days.len() as f64 };
+
+        // The streak as of today, which isn't broken yet if nothing has been written today
+        let streak = days.iter()
+            .rev()
+            .skip_while(|(day, count)| *day == today && *count == 0)
+            .take_while(|(_, count)| *count > 0)
+            .count() as u64;
+        let wrote_today = days.last().is_some_and(|(day, count)| *day == today && *count > 0);
+
+        Ok(self.rules.iter()
+            .map(|(badge_id, rule)| {
+                let earned_on = BadgeRules::earned_on(*rule, &days, goal);
+                let (remaining, expected) = match (*rule, earned_on) {
+                    (_, Some(_)) => (0, None),
+                    (BadgeRule::Total { count }, None) => {
+                        let remaining = count.saturating_sub(total);
+                        (remaining, BadgeRules::project(remaining, pace, today, series.end()))
+                    },
+                    (BadgeRule::Goal, None) => {
+                        let remaining = goal.saturating_sub(total);
+                        (remaining, BadgeRules::project(remaining, pace, today, series.end()))
+                    },
+                    (BadgeRule::Day { count }, None) => {
+                        let written = days.last().filter(|(day, _)| *day == today).map_or(0, |(_, count)| *count);
+                        (count.saturating_sub(written), None)
+                    },
+                    (BadgeRule::Streak { days }, None) => {
+                        let remaining = days.saturating_sub(streak);
+                        let first = if wrote_today { 1 } else { 0 };
+                        let expected = today + Duration::days(remaining as i64 - 1 + first);
+                        (remaining, Some(expected).filter(|expected| *expected <= series.end()))
+                    },
+                };
+
+                BadgePrediction {
+                    badge_id: *badge_id,
+                    rule: *rule,
+                    earned_on,
+                    awarded: awarded.iter().any(|user_badge| user_badge.badge_id == *badge_id),
+                    unit_type: series.unit_type(),
+                    remaining,
+                    expected,
+                }
+            })
+            .collect())
+    }
+
+    /// The first day a rule was met, if it was
+    fn earned_on(rule: BadgeRule, days: &[(NaiveDate, u64)], goal: u64) -> Option<NaiveDate> {
+        let mut total = 0;
+        let mut streak = 0;
+
+        for (day, count) in days {
+            total += count;
+            streak = if *count > 0 { streak + 1 } else { 0 };
+
+            let met = match rule {
+                BadgeRule::Total { count } => total >= count,
+                BadgeRule::Goal => total >= goal,
+                BadgeRule::Day { count: needed } => *count >= needed,
+                BadgeRule::Streak { days } => streak >= days,
+            };
+            if met {
+                return Some(*day);
+            }
+        }
+
+        None
+    }
+
+    /// The day `remaining` will be written at `pace` per day, if it's no later than `end`
+    fn project(remaining: u64, pace: f64, today: NaiveDate, end: NaiveDate) -> Option<NaiveDate> {
+        if pace <= 0.0 {
+            return None;
+        }
+
+        let days = (remaining as f64 / pace).ceil() as i64;
+        Some(today + Duration::days(days)).filter(|expected| *expected <= end)
+    }
+}
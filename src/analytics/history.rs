@@ -0,0 +1,150 @@
+use crate::data::{ChallengeObject, ObjectInfo, ProjectChallengeObject, ProjectObject, UserObject};
+use crate::enums::{EventType, UnitType};
+
+use std::rc::Rc;
+
+use chrono::Datelike;
+
+/// A single ProjectChallenge in a writer's history, with the Challenge and Project it links.
+/// Challenges and Projects are shared between all the entries that link them
+#[derive(Debug)]
+pub struct HistoryEntry {
+    pub project_challenge: ProjectChallengeObject,
+    pub challenge: Rc<ChallengeObject>,
+    pub project: Rc<ProjectObject>,
+}
+
+impl HistoryEntry {
+    /// The year the challenge started in
+    pub fn year(&self) -> i32 {
+        self.project_challenge.data.starts_at.year()
+    }
+
+    /// The count at the end of the challenge, or so far if it hasn't ended
+    pub fn final_count(&self) -> u64 {
+        self.project_challenge.data.current_count
+    }
+
+    /// Whether the count reached the goal
+    pub fn won(&self) -> bool {
+        self.project_challenge.data.current_count >= self.project_challenge.data.goal
+    }
+}
+
+/// Every ProjectChallenge in a single Challenge, such as NaNoWriMo 2020 or the April 2021 Camp
+#[derive(Debug)]
+pub struct HistoryEvent {
+    pub challenge: Rc<ChallengeObject>,
+    pub event_type: EventType,
+    /// The year the challenge started in
+    pub year: i32,
+    /// The ProjectChallenges, ordered by when they started
+    pub entries: Vec<HistoryEntry>,
+}
+
+impl HistoryEvent {
+    /// Whether any ProjectChallenge in the event reached its goal
+    pub fn won(&self) -> bool {
+        self.entries.iter().any(HistoryEntry::won)
+    }
+
+    /// The total words written in the event. ProjectChallenges counting hours aren't included
+    pub fn word_count(&self) -> u64 {
+        self.entries.iter()
+            .filter(|entry| entry.project_challenge.data.unit_type == UnitType::Words)
+            .map(HistoryEntry::final_count)
+            .sum()
+    }
+}
+
+/// A disagreement between a total worked out from a writer's history, and the same total in
+/// their profile stats
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HistoryDiscrepancy {
+    /// The name of the stat, as Nano sends it, such as `stats-years-won`
+    pub stat: &'static str,
+    /// The value in the profile stats
+    pub reported: u64,
+    /// The value worked out from the history
+    pub computed: u64,
+}
+
+/// Everything a writer has taken part in, grouped by Challenge
+#[derive(Debug)]
+pub struct WritingHistory {
+    pub user: UserObject,
+    /// Each Challenge the writer took part in, ordered by when they first started
+    pub events: Vec<HistoryEvent>,
+    /// Totals that don't match the writer's profile stats. Nano sometimes counts things the
+    /// history can't see, such as deleted projects, so these are worth a look rather than errors
+    pub discrepancies: Vec<HistoryDiscrepancy>,
+}
+
+impl WritingHistory {
+    /// Group ProjectChallenges by their Challenge, and check them against the user's stats
+    pub fn new(user: UserObject, mut entries: Vec<HistoryEntry>, projects: u64) -> WritingHistory {
+        entries.sort_by_key(|entry| (entry.project_challenge.data.starts_at, entry.project_challenge.id()));
+
+        let mut events: Vec<HistoryEvent> = Vec::new();
+        for entry in entries {
+            let challenge_id = entry.project_challenge.data.challenge_id;
+
+            match events.iter_mut().find(|event| event.challenge.id() == challenge_id) {
+                Some(event) => event.entries.push(entry),
+                None => events.push(HistoryEvent {
+                    challenge: Rc::clone(&entry.challenge),
+                    event_type: entry.project_challenge.data.event_type,
+                    year: entry.year(),
+                    entries: vec![entry],
+                })
+            }
+        }
+
+        let mut history = WritingHistory { user, events, discrepancies: Vec::new() };
+        history.discrepancies = history.check(projects);
+        history
+    }
+
+    fn check(&self, projects: u64) -> Vec<HistoryDiscrepancy> {
+        let stats = &self.user.data.stats;
+        let nano_years = self.events_of(EventType::NanoWrimo).collect::<Vec<_>>();
+        let wordiest = self.entries()
+            .filter(|entry| entry.project_challenge.data.unit_type == UnitType::Words)
+            .map(HistoryEntry::final_count)
+            .max()
+            .unwrap_or(0);
+
+        let checks = [
+            ("stats-projects", Some(stats.projects), projects),
+            ("stats-word-count", Some(stats.word_count), self.word_count()),
+            ("stats-wordiest", Some(stats.wordiest), wordiest),
+            ("stats-years-done", stats.years_done, nano_years.len() as u64),
+            ("stats-years-won", stats.years_won, nano_years.iter().filter(|event| event.won()).count() as u64),
+        ];
+
+        checks.iter()
+            .filter_map(|(stat, reported, computed)| match reported {
+                Some(reported) if reported != computed => {
+                    Some(HistoryDiscrepancy { stat, reported: *reported, computed: *computed })
+                },
+                _ => None
+            })
+            .collect()
+    }
+
+    /// Every ProjectChallenge in the history, in the same order as `events`
+    pub fn entries(&self) -> impl Iterator<Item = &HistoryEntry> {
+        self.events.iter().flat_map(|event| event.entries.iter())
+    }
+
+    /// The events of a single type, oldest first
+    pub fn events_of(&self, event_type: EventType) -> impl Iterator<Item = &HistoryEvent> {
+        self.events.iter().filter(move |event| event.event_type == event_type)
+    }
+
+    /// The total words written in every challenge. ProjectChallenges counting hours aren't
+    /// included
+    pub fn word_count(&self) -> u64 {
+        self.events.iter().map(HistoryEvent::word_count).sum()
+    }
+}
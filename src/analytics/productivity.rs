@@ -0,0 +1,234 @@
+use crate::data::ProjectSessionData;
+use crate::enums::{Feeling, How, UnitType, Where};
+use crate::quantity::Quantity;
+
+use std::collections::HashMap;
+use std::hash::Hash;
+
+use chrono::{Duration, TimeZone, Timelike};
+
+/// Totals over a group of writing sessions. Only sessions with a start time have a known length,
+/// so rates are worked out from those alone
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct SessionStats {
+    unit_type: UnitType,
+    sessions: u64,
+    count: i64,
+    timed_sessions: u64,
+    timed_count: i64,
+    time: Duration,
+}
+
+impl SessionStats {
+    /// No sessions at all, in a unit
+    pub fn empty(unit_type: UnitType) -> SessionStats {
+        SessionStats {
+            unit_type,
+            sessions: 0,
+            count: 0,
+            timed_sessions: 0,
+            timed_count: 0,
+            time: Duration::zero(),
+        }
+    }
+
+    fn add(&mut self, session: &ProjectSessionData) {
+        self.sessions += 1;
+        self.count += session.count;
+
+        if let Some(start) = session.start {
+            self.timed_sessions += 1;
+            self.timed_count += session.count;
+            self.time += session.end - start;
+        }
+    }
+
+    fn merge(&mut self, other: &SessionStats) {
+        self.sessions += other.sessions;
+        self.count += other.count;
+        self.timed_sessions += other.timed_sessions;
+        self.timed_count += other.timed_count;
+        self.time += other.time;
+    }
+
+    /// How many sessions there were
+    pub fn sessions(&self) -> u64 {
+        self.sessions
+    }
+
+    /// The total written in all sessions
+    pub fn count(&self) -> Quantity {
+        Quantity::from_count(self.unit_type, self.count)
+    }
+
+    /// How many sessions had a start time
+    pub fn timed_sessions(&self) -> u64 {
+        self.timed_sessions
+    }
+
+    /// The total written in sessions with a start time
+    pub fn timed_count(&self) -> Quantity {
+        Quantity::from_count(self.unit_type, self.timed_count)
+    }
+
+    /// The total time spent in sessions with a start time
+    pub fn time(&self) -> Duration {
+        self.time
+    }
+
+    /// The average written per session
+    pub fn per_session(&self) -> f64 {
+        if self.sessions == 0 {
+            0.0
+        } else {
+            self.count as f64 / self.sessions as f64
+        }
+    }
+
+    /// The rate of writing per hour, over sessions with a start time. None if no time was spent
+    pub fn per_hour(&self) -> Option<f64> {
+        let seconds = self.time.num_seconds();
+        if seconds <= 0 {
+            None
+        } else {
+            Some(self.timed_count as f64 * 3600.0 / seconds as f64)
+        }
+    }
+}
+
+/// How a session was written: the writer's mood, what they wrote with, and where they were.
+/// Each is None for sessions where the writer didn't say
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Conditions {
+    /// How the writer felt
+    pub feeling: Option<Feeling>,
+    /// What the writer wrote with
+    pub how: Option<How>,
+    /// Where the writer was
+    pub r#where: Option<Where>,
+}
+
+/// Writing sessions grouped by the conditions they were written in and by hour of day, to find
+/// when and where a writer is most productive. Hours are in a given time zone, and sessions in a
+/// different unit than the one given are ignored
+#[derive(Debug, Clone)]
+pub struct Productivity<Tz: TimeZone> {
+    tz: Tz,
+    unit_type: UnitType,
+    by_conditions: HashMap<Conditions, SessionStats>,
+    by_hour: [SessionStats; 24],
+}
+
+impl<Tz: TimeZone> Productivity<Tz> {
+    /// Create an empty analysis of sessions in a unit, with hours in a time zone
+    pub fn new(tz: Tz, unit_type: UnitType) -> Productivity<Tz> {
+        Productivity {
+            tz,
+            unit_type,
+            by_conditions: HashMap::new(),
+            by_hour: [SessionStats::empty(unit_type); 24],
+        }
+    }
+
+    /// Add writing sessions, from any project. A session counts towards the hour it started in,
+    /// or the hour it ended in if it has no start time
+    pub fn add_sessions<'a, I>(&mut self, sessions: I)
+        where
+            I: IntoIterator<Item = &'a ProjectSessionData>
+    {
+        let unit_type = self.unit_type;
+        for session in sessions.into_iter().filter(|session| session.unit_type == unit_type) {
+            let conditions = Conditions {
+                feeling: session.feeling,
+                how: session.how,
+                r#where: session.r#where,
+            };
+            self.by_conditions.entry(conditions).or_insert_with(|| SessionStats::empty(unit_type)).add(session);
+
+            let hour = session.start.unwrap_or(session.end).with_timezone(&self.tz).hour();
+            self.by_hour[hour as usize].add(session);
+        }
+    }
+
+    /// The unit of the sessions counted
+    pub fn unit_type(&self) -> UnitType {
+        self.unit_type
+    }
+
+    /// Totals over every session
+    pub fn overall(&self) -> SessionStats {
+        let mut out = SessionStats::empty(self.unit_type);
+        for stats in self.by_conditions.values() {
+            out.merge(stats);
+        }
+        out
+    }
+
+    fn group_by<K, F>(&self, key: F) -> HashMap<K, SessionStats>
+        where
+            K: Eq + Hash,
+            F: Fn(&Conditions) -> K
+    {
+        let mut out = HashMap::new();
+        for (conditions, stats) in &self.by_conditions {
+            out.entry(key(conditions))
+                .or_insert_with(|| SessionStats::empty(self.unit_type))
+                .merge(stats);
+        }
+        out
+    }
+
+    /// Totals for each feeling the writer reported
+    pub fn by_feeling(&self) -> HashMap<Option<Feeling>, SessionStats> {
+        self.group_by(|conditions| conditions.feeling)
+    }
+
+    /// Totals for each method the writer used
+    pub fn by_how(&self) -> HashMap<Option<How>, SessionStats> {
+        self.group_by(|conditions| conditions.how)
+    }
+
+    /// Totals for each place the writer wrote
+    pub fn by_where(&self) -> HashMap<Option<Where>, SessionStats> {
+        self.group_by(|conditions| conditions.r#where)
+    }
+
+    /// Totals for each combination of feeling, method and place
+    pub fn by_conditions(&self) -> &HashMap<Conditions, SessionStats> {
+        &self.by_conditions
+    }
+
+    /// Totals for each hour of the day, from midnight to 11 PM
+    pub fn by_hour(&self) -> &[SessionStats; 24] {
+        &self.by_hour
+    }
+
+    /// The combination of feeling, method and place with the highest rate of writing per hour,
+    /// out of those with at least `min_sessions` timed sessions. Requiring a few sessions keeps a
+    /// single good session from winning. The combination with more sessions wins ties
+    pub fn most_productive(&self, min_sessions: u64) -> Option<(Conditions, SessionStats)> {
+        self.by_conditions.iter()
+            .filter(|(_, stats)| stats.timed_sessions >= min_sessions.max(1))
+            .filter_map(|(conditions, stats)| stats.per_hour().map(|rate| (rate, *conditions, *stats)))
+            .max_by(|(a_rate, _, a), (b_rate, _, b)| {
+                a_rate.partial_cmp(b_rate)
+                    .unwrap_or(std::cmp::Ordering::Equal)
+                    .then(a.timed_sessions.cmp(&b.timed_sessions))
+            })
+            .map(|(_, conditions, stats)| (conditions, stats))
+    }
+
+    /// The hour of the day with the highest rate of writing per hour, out of those with at least
+    /// `min_sessions` timed sessions. The earliest hour wins ties
+    pub fn most_productive_hour(&self, min_sessions: u64) -> Option<(u32, SessionStats)> {
+        self.by_hour.iter()
+            .enumerate()
+            .filter(|(_, stats)| stats.timed_sessions >= min_sessions.max(1))
+            .filter_map(|(hour, stats)| stats.per_hour().map(|rate| (rate, hour as u32, *stats)))
+            .fold(None, |best: Option<(f64, u32, SessionStats)>, next| match best {
+                Some(best) if best.0 >= next.0 => Some(best),
+                _ => Some(next)
+            })
+            .map(|(_, hour, stats)| (hour, stats))
+    }
+}
@@ -0,0 +1,227 @@
+use crate::data::{ChallengeData, DailyAggregateData, ProjectChallengeData};
+use crate::enums::UnitType;
+use crate::quantity::Quantity;
+
+use std::collections::BTreeMap;
+
+use chrono::{Duration, NaiveDate};
+
+/// How many days the trailing pace is averaged over
+const TRAILING_DAYS: i64 = 7;
+
+/// Progress of a ProjectChallenge towards its goal, as of a given day. Counts are in the unit of
+/// the challenge, which is usually words
+#[derive(Debug, Clone, PartialEq)]
+pub struct Progress {
+    goal: u64,
+    count: u64,
+    unit_type: UnitType,
+    starts_at: NaiveDate,
+    ends_at: NaiveDate,
+    today: NaiveDate,
+    win_allowed_at: Option<NaiveDate>,
+    daily: BTreeMap<NaiveDate, u64>,
+}
+
+impl Progress {
+    /// Calculate the progress of a ProjectChallenge, from the daily aggregates of its project.
+    /// Aggregates outside the challenge, or in a different unit, are ignored
+    pub fn new<'a, I>(challenge: &ProjectChallengeData, history: I, today: NaiveDate) -> Progress
+        where
+            I: IntoIterator<Item = &'a DailyAggregateData>
+    {
+        let mut daily = BTreeMap::new();
+
+        let history = history.into_iter()
+            .filter(|agg| agg.unit_type == challenge.unit_type)
+            .filter(|agg| agg.day >= challenge.starts_at && agg.day <= challenge.ends_at);
+        for agg in history {
+            *daily.entry(agg.day).or_insert(0) += agg.count;
+        }
+
+        Progress {
+            goal: challenge.goal,
+            count: challenge.current_count,
+            unit_type: challenge.unit_type,
+            starts_at: challenge.starts_at,
+            ends_at: challenge.ends_at,
+            today,
+            win_allowed_at: None,
+            daily,
+        }
+    }
+
+    /// Take when winning is allowed from the Challenge the ProjectChallenge is for
+    pub fn with_challenge(mut self, challenge: &ChallengeData) -> Progress {
+        self.win_allowed_at = challenge.win_allowed_at;
+        self
+    }
+
+    fn in_unit(&self, count: i64) -> Quantity {
+        Quantity::from_count(self.unit_type, count)
+    }
+
+    /// The goal of the challenge
+    pub fn goal(&self) -> Quantity {
+        self.in_unit(self.goal as i64)
+    }
+
+    /// The count so far
+    pub fn count(&self) -> Quantity {
+        self.in_unit(self.count as i64)
+    }
+
+    /// The unit the goal and counts are in
+    pub fn unit_type(&self) -> UnitType {
+        self.unit_type
+    }
+
+    /// The day progress is calculated as of
+    pub fn today(&self) -> NaiveDate {
+        self.today
+    }
+
+    /// How many days the challenge lasts, counting both the first and last day
+    pub fn total_days(&self) -> u64 {
+        (self.ends_at - self.starts_at).num_days().max(0) as u64 + 1
+    }
+
+    /// How many days of the challenge have passed by the end of a day, counting that day
+    pub fn days_elapsed_on(&self, day: NaiveDate) -> u64 {
+        ((day - self.starts_at).num_days() + 1).max(0).min(self.total_days() as i64) as u64
+    }
+
+    /// How many days of the challenge have passed, counting today
+    pub fn days_elapsed(&self) -> u64 {
+        self.days_elapsed_on(self.today)
+    }
+
+    /// How many days are left in the challenge, counting today
+    pub fn days_left(&self) -> u64 {
+        if self.today < self.starts_at {
+            self.total_days()
+        } else {
+            self.total_days() - self.days_elapsed() + u64::from(self.today <= self.ends_at)
+        }
+    }
+
+    /// The count expected by the end of a day, to reach the goal writing the same amount every
+    /// day. This is what Nano's graphs call par
+    pub fn par_on(&self, day: NaiveDate) -> Quantity {
+        self.in_unit(self.par_count(day) as i64)
+    }
+
+    fn par_count(&self, day: NaiveDate) -> u64 {
+        self.goal * self.days_elapsed_on(day) / self.total_days()
+    }
+
+    /// The count expected by the end of today. See [`Self::par_on`]
+    pub fn par(&self) -> Quantity {
+        self.par_on(self.today)
+    }
+
+    /// How far ahead of par the count is. Negative if it's behind
+    pub fn ahead_by(&self) -> Quantity {
+        self.in_unit(self.count as i64 - self.par_count(self.today) as i64)
+    }
+
+    fn remaining_count(&self) -> u64 {
+        self.goal.saturating_sub(self.count)
+    }
+
+    /// How much is left to reach the goal
+    pub fn remaining(&self) -> Quantity {
+        self.in_unit(self.remaining_count() as i64)
+    }
+
+    /// Whether the goal has been reached
+    pub fn is_complete(&self) -> bool {
+        self.count >= self.goal
+    }
+
+    /// How much needs to be written each day, including today, to reach the goal by the end of
+    /// the challenge. Zero if the goal has been reached, and None if the challenge is over without
+    /// reaching it
+    pub fn required_per_day(&self) -> Option<Quantity> {
+        if self.is_complete() {
+            return Some(Quantity::zero(self.unit_type));
+        }
+
+        match self.days_left() {
+            0 => None,
+            days => Some(self.in_unit(self.remaining_count().div_ceil(days) as i64))
+        }
+    }
+
+    /// The average written per day so far, over every day of the challenge that has passed
+    pub fn pace(&self) -> f64 {
+        match self.days_elapsed() {
+            0 => 0.0,
+            days => self.count as f64 / days as f64
+        }
+    }
+
+    /// The average written per day over the last seven days of the challenge, counting today, or
+    /// fewer if the challenge hasn't been going that long. Taken from the daily aggregates
+    pub fn trailing_pace(&self) -> f64 {
+        let days = self.days_elapsed().min(TRAILING_DAYS as u64);
+        if days == 0 {
+            return 0.0;
+        }
+
+        let end = self.today.min(self.ends_at);
+        let start = end - Duration::days(days as i64 - 1);
+        let written = self.daily.range(start..=end).map(|(_, count)| count).sum::<u64>();
+
+        written as f64 / days as f64
+    }
+
+    fn projected_at(&self, pace: f64) -> Option<NaiveDate> {
+        if self.is_complete() {
+            return Some(self.completed_on().unwrap_or(self.today));
+        }
+        if pace <= 0.0 {
+            return None;
+        }
+
+        let days = (self.remaining_count() as f64 / pace).ceil() as i64;
+        Some(self.today.max(self.starts_at - Duration::days(1)) + Duration::days(days))
+    }
+
+    /// The day the goal will be reached, if writing continues at the average pace so far. If the
+    /// goal has been reached, this is the day it was. None if nothing has been written
+    pub fn projected_finish(&self) -> Option<NaiveDate> {
+        self.projected_at(self.pace())
+    }
+
+    /// The day the goal will be reached, if writing continues at the pace of the last seven
+    /// days. See [`Self::projected_finish`] and [`Self::trailing_pace`]
+    pub fn projected_finish_trailing(&self) -> Option<NaiveDate> {
+        self.projected_at(self.trailing_pace())
+    }
+
+    /// The first day the daily aggregates add up to the goal, if they do
+    pub fn completed_on(&self) -> Option<NaiveDate> {
+        let mut total = 0;
+        self.daily.iter().find_map(|(day, count)| {
+            total += count;
+            if total >= self.goal { Some(*day) } else { None }
+        })
+    }
+
+    /// The first day a win can be validated, if it's known. See [`Self::with_challenge`]
+    pub fn win_allowed_at(&self) -> Option<NaiveDate> {
+        self.win_allowed_at
+    }
+
+    /// Whether the day a win can be validated has been reached, or None if it isn't known
+    pub fn win_allowed(&self) -> Option<bool> {
+        self.win_allowed_at.map(|day| self.today >= day)
+    }
+
+    /// Whether the challenge can be won today: the goal has been reached, and validating wins is
+    /// allowed, or isn't known to be restricted
+    pub fn can_win(&self) -> bool {
+        self.is_complete() && self.win_allowed().unwrap_or(true)
+    }
+}
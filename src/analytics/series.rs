@@ -0,0 +1,249 @@
+use crate::data::{DailyAggregateData, ProjectChallengeData};
+use crate::enums::UnitType;
+use crate::error::Error;
+use crate::quantity::Quantity;
+
+use chrono::{Datelike, Duration, NaiveDate, Weekday};
+
+/// The days of the week, in the order weekday breakdowns are given
+const WEEKDAYS: [Weekday; 7] = [
+    Weekday::Mon, Weekday::Tue, Weekday::Wed, Weekday::Thu, Weekday::Fri, Weekday::Sat, Weekday::Sun
+];
+
+/// The counts for every day in a range, including days with no writing. Counts are in the unit
+/// of the series, which is usually words
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DailySeries {
+    unit_type: UnitType,
+    start: NaiveDate,
+    counts: Vec<u64>,
+}
+
+/// The writing done on one day of the week, across a series
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct WeekdayStats {
+    /// The day of the week
+    pub weekday: Weekday,
+    /// How many of that day the series covers
+    pub days: u64,
+    /// How many of those days had any writing
+    pub active_days: u64,
+    /// The total written on that day of the week
+    pub total: u64,
+}
+
+impl WeekdayStats {
+    /// The average written on that day of the week, counting days with no writing
+    pub fn average(&self) -> f64 {
+        if self.days == 0 {
+            0.0
+        } else {
+            self.total as f64 / self.days as f64
+        }
+    }
+}
+
+impl DailySeries {
+    /// Create a series of zeroes, from `start` to `end` inclusive. If `end` is before `start`,
+    /// the series is empty
+    pub fn empty(unit_type: UnitType, start: NaiveDate, end: NaiveDate) -> DailySeries {
+        let len = (end - start).num_days() + 1;
+        DailySeries { unit_type, start, counts: vec![0; len.max(0) as usize] }
+    }
+
+    /// Create the series for a ProjectChallenge, from the daily aggregates of its project. Covers
+    /// every day of the challenge, and aggregates outside it, or in a different unit, are ignored
+    pub fn new<'a, I>(challenge: &ProjectChallengeData, history: I) -> DailySeries
+        where
+            I: IntoIterator<Item = &'a DailyAggregateData>
+    {
+        let mut series = DailySeries::empty(challenge.unit_type, challenge.starts_at, challenge.ends_at);
+
+        for agg in history.into_iter().filter(|agg| agg.unit_type == challenge.unit_type) {
+            series.add(agg.day, agg.count);
+        }
+
+        series
+    }
+
+    fn index(&self, day: NaiveDate) -> Option<usize> {
+        let offset = (day - self.start).num_days();
+        if offset >= 0 && (offset as usize) < self.counts.len() {
+            Some(offset as usize)
+        } else {
+            None
+        }
+    }
+
+    /// Add to the count of a day. Returns false, and adds nothing, if the day isn't in the series
+    pub fn add(&mut self, day: NaiveDate, count: u64) -> bool {
+        match self.index(day) {
+            Some(idx) => {
+                self.counts[idx] += count;
+                true
+            },
+            None => false
+        }
+    }
+
+    /// The unit the counts are in
+    pub fn unit_type(&self) -> UnitType {
+        self.unit_type
+    }
+
+    /// The first day of the series
+    pub fn start(&self) -> NaiveDate {
+        self.start
+    }
+
+    /// The last day of the series. For an empty series, this is the day before the start
+    pub fn end(&self) -> NaiveDate {
+        self.start + Duration::days(self.counts.len() as i64 - 1)
+    }
+
+    /// How many days the series covers
+    pub fn len(&self) -> usize {
+        self.counts.len()
+    }
+
+    /// Whether the series covers no days at all
+    pub fn is_empty(&self) -> bool {
+        self.counts.is_empty()
+    }
+
+    /// The count for a day, or None if the day isn't in the series
+    pub fn get(&self, day: NaiveDate) -> Option<u64> {
+        self.index(day).map(|idx| self.counts[idx])
+    }
+
+    /// Every day in the series and its count, in order
+    pub fn iter(&self) -> impl Iterator<Item = (NaiveDate, u64)> + '_ {
+        let start = self.start;
+        self.counts.iter()
+            .enumerate()
+            .map(move |(idx, count)| (start + Duration::days(idx as i64), *count))
+    }
+
+    /// The total of every day in the series
+    pub fn total(&self) -> Quantity {
+        Quantity::from_count(self.unit_type, self.counts.iter().sum::<u64>() as i64)
+    }
+
+    /// The running total at the end of every day in the series, in order
+    pub fn cumulative(&self) -> Vec<(NaiveDate, u64)> {
+        self.iter()
+            .scan(0, |total, (day, count)| {
+                *total += count;
+                Some((day, *total))
+            })
+            .collect()
+    }
+
+    /// The running total at the end of a day. Days before the series have a total of zero, and
+    /// days after it the total of the whole series
+    pub fn cumulative_on(&self, day: NaiveDate) -> u64 {
+        if day < self.start {
+            return 0;
+        }
+
+        let days = ((day - self.start).num_days() + 1) as usize;
+        self.counts.iter().take(days).sum()
+    }
+
+    /// The average of every day and the days before it, up to `window` days in total, in order.
+    /// Near the start of the series, fewer days are averaged. A window of zero is treated as one
+    pub fn rolling_average(&self, window: usize) -> Vec<(NaiveDate, f64)> {
+        let window = window.max(1);
+        let mut sum = 0;
+
+        self.iter()
+            .enumerate()
+            .map(|(idx, (day, count))| {
+                sum += count;
+                if idx >= window {
+                    sum -= self.counts[idx - window];
+                }
+                (day, sum as f64 / (idx + 1).min(window) as f64)
+            })
+            .collect()
+    }
+
+    /// The day with the highest count, and the count. The earliest wins ties, and there is no
+    /// best day if nothing was written
+    pub fn best_day(&self) -> Option<(NaiveDate, u64)> {
+        self.iter()
+            .filter(|(_, count)| *count > 0)
+            .fold(None, |best, (day, count)| match best {
+                Some((_, best_count)) if best_count >= count => best,
+                _ => Some((day, count))
+            })
+    }
+
+    /// The writing done on each day of the week, from Monday to Sunday
+    pub fn by_weekday(&self) -> Vec<WeekdayStats> {
+        let mut stats = WEEKDAYS.iter()
+            .map(|weekday| WeekdayStats { weekday: *weekday, days: 0, active_days: 0, total: 0 })
+            .collect::<Vec<_>>();
+
+        for (day, count) in self.iter() {
+            let stat = &mut stats[day.weekday().num_days_from_monday() as usize];
+            stat.days += 1;
+            stat.total += count;
+            if count > 0 {
+                stat.active_days += 1;
+            }
+        }
+
+        stats
+    }
+
+    /// Add another series into this one, such as one for another ProjectChallenge. The series
+    /// grows to cover the days of both, and the counts of days in both are added together. Fails
+    /// if the series are in different units
+    pub fn merge(&mut self, other: &DailySeries) -> Result<(), Error> {
+        if self.unit_type != other.unit_type {
+            return Err(Error::ValidationError(format!(
+                "Can't merge a series in {:?} into one in {:?}", other.unit_type, self.unit_type
+            )));
+        }
+        if other.is_empty() {
+            return Ok(());
+        }
+
+        if self.is_empty() {
+            self.start = other.start;
+            self.counts = other.counts.clone();
+            return Ok(());
+        }
+
+        let start = self.start.min(other.start);
+        let end = self.end().max(other.end());
+        let mut merged = DailySeries::empty(self.unit_type, start, end);
+
+        for (day, count) in self.iter().chain(other.iter()) {
+            merged.add(day, count);
+        }
+
+        *self = merged;
+        Ok(())
+    }
+
+    /// Merge several series into one, such as the series for every ProjectChallenge in a year.
+    /// Returns None if there are no series, and fails if they aren't all in the same unit
+    pub fn merged<'a, I>(series: I) -> Result<Option<DailySeries>, Error>
+        where
+            I: IntoIterator<Item = &'a DailySeries>
+    {
+        let mut series = series.into_iter();
+        let mut out = match series.next() {
+            Some(first) => first.clone(),
+            None => return Ok(None)
+        };
+
+        for other in series {
+            out.merge(other)?;
+        }
+
+        Ok(Some(out))
+    }
+}
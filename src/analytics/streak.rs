@@ -0,0 +1,149 @@
+use crate::data::{DailyAggregateData, ProjectSessionData};
+use crate::enums::UnitType;
+use crate::quantity::Quantity;
+
+use std::collections::BTreeMap;
+
+use chrono::{NaiveDate, TimeZone};
+
+/// The rules for which days count towards a streak
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct StreakRules {
+    /// The least that has to be written in a day for it to count. Only writing in the same unit
+    /// counts towards it, so streaks for hours-based challenges need a minimum in hours
+    pub min_per_day: Quantity,
+    /// How many days in a row can be missed without breaking a streak. Missed days don't add to
+    /// the length of the streak
+    pub grace_days: u32,
+}
+
+impl StreakRules {
+    /// Any writing in a unit counts, and missing a day breaks the streak
+    pub fn any_writing(unit_type: UnitType) -> StreakRules {
+        StreakRules { min_per_day: Quantity::from_count(unit_type, 1), grace_days: 0 }
+    }
+}
+
+impl Default for StreakRules {
+    /// Any writing in words counts, and missing a day breaks the streak
+    fn default() -> StreakRules {
+        StreakRules::any_writing(UnitType::Words)
+    }
+}
+
+/// A run of writing days, none more than the allowed grace days apart
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Streak {
+    /// The first writing day of the streak
+    pub start: NaiveDate,
+    /// The last writing day of the streak
+    pub end: NaiveDate,
+    /// How many writing days the streak has, not counting any missed days
+    pub days: u64,
+}
+
+/// The current and longest streaks as of a given day
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub struct Streaks {
+    /// The streak that's still going, if there is one. Not having written yet today doesn't end
+    /// a streak, since the day isn't over
+    pub current: Option<Streak>,
+    /// The longest streak, which may be the current one. The earliest wins ties
+    pub longest: Option<Streak>,
+}
+
+/// Counts writing streaks across any number of projects, by writing day in a given time zone.
+/// Sessions are put on the day they ended, in the time zone, rather than in UTC, so writers far
+/// from UTC don't have sessions land on the wrong day
+#[derive(Debug, Clone)]
+pub struct StreakCounter<Tz: TimeZone> {
+    tz: Tz,
+    rules: StreakRules,
+    days: BTreeMap<NaiveDate, [i64; 2]>,
+}
+
+fn unit_index(unit_type: UnitType) -> usize {
+    match unit_type {
+        UnitType::Words => 0,
+        UnitType::Hours => 1,
+    }
+}
+
+impl<Tz: TimeZone> StreakCounter<Tz> {
+    /// Create a counter for writing days in a time zone, with the default rules
+    pub fn new(tz: Tz) -> StreakCounter<Tz> {
+        StreakCounter::with_rules(tz, StreakRules::default())
+    }
+
+    /// Create a counter for writing days in a time zone, with the given rules
+    pub fn with_rules(tz: Tz, rules: StreakRules) -> StreakCounter<Tz> {
+        StreakCounter { tz, rules, days: BTreeMap::new() }
+    }
+
+    fn add(&mut self, day: NaiveDate, unit_type: UnitType, count: i64) {
+        self.days.entry(day).or_insert([0; 2])[unit_index(unit_type)] += count;
+    }
+
+    /// Count writing sessions, from any project. Sessions that removed words count against their
+    /// day
+    pub fn add_sessions<'a, I>(&mut self, sessions: I)
+        where
+            I: IntoIterator<Item = &'a ProjectSessionData>
+    {
+        for session in sessions {
+            let day = session.end.with_timezone(&self.tz).date_naive();
+            self.add(day, session.unit_type, session.count);
+        }
+    }
+
+    /// Count daily aggregates, from any project. Nano has already put these on a day, so the
+    /// time zone isn't used. Sessions and aggregates for the same project shouldn't both be
+    /// added, or that project's writing is counted twice
+    pub fn add_aggregates<'a, I>(&mut self, aggregates: I)
+        where
+            I: IntoIterator<Item = &'a DailyAggregateData>
+    {
+        for agg in aggregates {
+            self.add(agg.day, agg.unit_type, agg.count as i64);
+        }
+    }
+
+    /// Every day that counts towards a streak under this counter's rules, in order
+    pub fn writing_days(&self) -> Vec<NaiveDate> {
+        let min = self.rules.min_per_day;
+        let idx = unit_index(min.unit_type());
+
+        self.days.iter()
+            .filter(|(_, counts)| Quantity::from_count(min.unit_type(), counts[idx]) >= min)
+            .map(|(day, _)| *day)
+            .collect()
+    }
+
+    /// Find the current and longest streaks as of a day. Days after it are ignored
+    pub fn streaks(&self, today: NaiveDate) -> Streaks {
+        let max_gap = i64::from(self.rules.grace_days) + 1;
+        let mut streaks = Vec::<Streak>::new();
+
+        for day in self.writing_days().into_iter().filter(|day| *day <= today) {
+            match streaks.last_mut() {
+                Some(streak) if (day - streak.end).num_days() <= max_gap => {
+                    streak.end = day;
+                    streak.days += 1;
+                },
+                _ => streaks.push(Streak { start: day, end: day, days: 1 })
+            }
+        }
+
+        // Today isn't over, so it can't have been missed yet
+        let current = streaks.last()
+            .filter(|streak| (today - streak.end).num_days() <= max_gap)
+            .copied();
+        let longest = streaks.iter()
+            .fold(None, |longest: Option<Streak>, streak| match longest {
+                Some(longest) if longest.days >= streak.days => Some(longest),
+                _ => Some(*streak)
+            });
+
+        Streaks { current, longest }
+    }
+}
@@ -0,0 +1,438 @@
+use super::*;
+use crate::data::{ChallengeData, DailyAggregateData, ProjectChallengeData, ProjectSessionData, UserBadgeData};
+use crate::enums::{Feeling, How, UnitType, Where};
+use crate::quantity::Quantity;
+
+use chrono::{DateTime, Duration, FixedOffset, NaiveDate, TimeZone, Utc, Weekday};
+use serde_json::json;
+
+fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+    NaiveDate::from_ymd_opt(year, month, day).unwrap()
+}
+
+fn nov(day: u32) -> NaiveDate {
+    date(2020, 11, day)
+}
+
+fn project_challenge(goal: u64, count: u64) -> ProjectChallengeData {
+    serde_json::from_value(json!({
+        "challenge-id": 3,
+        "current-count": count,
+        "ends-at": "2020-11-30",
+        "event-type": 0,
+        "feeling": null,
+        "goal": goal,
+        "how": null,
+        "last-recompute": null,
+        "name": "NaNoWriMo 2020",
+        "project-id": 5012,
+        "speed": null,
+        "start-count": 0,
+        "starts-at": "2020-11-01",
+        "streak": null,
+        "unit-type": 0,
+        "user-id": 74,
+        "when": null,
+        "writing-location": null,
+        "writing-type": 0
+    })).unwrap()
+}
+
+fn aggregate(day: NaiveDate, count: u64) -> DailyAggregateData {
+    DailyAggregateData { count, day, project_id: 5012, unit_type: UnitType::Words, user_id: None }
+}
+
+fn session(end: DateTime<Utc>, count: i64) -> ProjectSessionData {
+    ProjectSessionData {
+        count,
+        created_at: end,
+        end,
+        feeling: None,
+        how: None,
+        project_challenge_id: 1,
+        project_id: 5012,
+        session_date: end.date_naive(),
+        start: None,
+        unit_type: UnitType::Words,
+        r#where: None,
+    }
+}
+
+fn challenge(win_allowed_at: Option<NaiveDate>) -> ChallengeData {
+    ChallengeData {
+        default_goal: 50000,
+        ends_at: nov(30),
+        event_type: None,
+        flexible_goal: None,
+        name: "NaNoWriMo 2020".to_string(),
+        prep_starts_at: None,
+        starts_at: nov(1),
+        unit_type: UnitType::Words,
+        user_id: 0,
+        win_allowed_at,
+        writing_type: crate::enums::WritingType::Novel,
+    }
+}
+
+/// Slow for three days, then faster for a week, for 19,000 words by the 10th
+fn november_history() -> Vec<DailyAggregateData> {
+    (1..=3).map(|day| aggregate(nov(day), 500))
+        .chain((4..=10).map(|day| aggregate(nov(day), 2500)))
+        .collect()
+}
+
+#[test]
+fn test_progress() {
+    let mut history = november_history();
+    // Days outside the challenge, or in other units, don't count towards it
+    history.push(aggregate(date(2020, 10, 31), 999));
+    history.push(DailyAggregateData { unit_type: UnitType::Hours, ..aggregate(nov(9), 3) });
+
+    let progress = Progress::new(&project_challenge(50000, 19000), &history, nov(10));
+
+    assert_eq!(progress.total_days(), 30);
+    assert_eq!(progress.days_elapsed(), 10);
+    assert_eq!(progress.days_left(), 21);
+    assert_eq!((progress.goal(), progress.count()), (Quantity::words(50000), Quantity::words(19000)));
+    assert_eq!(progress.par(), Quantity::words(16666));
+    assert_eq!(progress.par_on(nov(30)), Quantity::words(50000));
+    assert_eq!(progress.ahead_by(), Quantity::words(2334));
+    assert_eq!(progress.remaining(), Quantity::words(31000));
+    assert_eq!(progress.required_per_day(), Some(Quantity::words(1477)));
+
+    assert_eq!(progress.pace(), 1900.0);
+    assert_eq!(progress.trailing_pace(), 2500.0);
+    assert_eq!(progress.projected_finish(), Some(nov(27)));
+    assert_eq!(progress.projected_finish_trailing(), Some(nov(23)));
+
+    assert!(!progress.is_complete());
+    assert_eq!(progress.win_allowed(), None);
+    assert!(!progress.can_win());
+
+    let behind = Progress::new(&project_challenge(50000, 10000), &history, nov(10));
+    assert_eq!(behind.ahead_by(), Quantity::words(-6666));
+}
+
+#[test]
+fn test_progress_edges() {
+    // Before the challenge starts, nothing is expected yet
+    let early = Progress::new(&project_challenge(50000, 0), &[], date(2020, 10, 25));
+    assert_eq!((early.days_elapsed(), early.days_left(), early.par()), (0, 30, Quantity::words(0)));
+    assert_eq!(early.required_per_day(), Some(Quantity::words(1667)));
+    assert_eq!(early.trailing_pace(), 0.0);
+    assert_eq!(early.projected_finish(), None);
+
+    // On the first day, the trailing pace only covers the day so far
+    let first = Progress::new(&project_challenge(50000, 2000), &[aggregate(nov(1), 2000)], nov(1));
+    assert_eq!(first.trailing_pace(), 2000.0);
+    assert_eq!(first.projected_finish(), Some(nov(25)));
+
+    // After the challenge ends without reaching the goal, it can't be reached any more
+    let late = Progress::new(&project_challenge(50000, 40000), &november_history(), date(2020, 12, 2));
+    assert_eq!((late.days_elapsed(), late.days_left(), late.par()), (30, 0, Quantity::words(50000)));
+    assert_eq!(late.required_per_day(), None);
+}
+
+#[test]
+fn test_progress_win() {
+    let mut history = november_history();
+    history.extend((11..=22).map(|day| aggregate(nov(day), 2700)));
+
+    let progress = Progress::new(&project_challenge(50000, 51400), &history, nov(22));
+    assert!(progress.is_complete());
+    assert_eq!(progress.remaining(), Quantity::words(0));
+    assert_eq!(progress.required_per_day(), Some(Quantity::words(0)));
+    assert_eq!(progress.completed_on(), Some(nov(22)));
+    assert_eq!(progress.projected_finish(), Some(nov(22)));
+
+    let progress = progress.with_challenge(&challenge(Some(nov(20))));
+    assert_eq!(progress.win_allowed(), Some(true));
+    assert!(progress.can_win());
+
+    let early = Progress::new(&project_challenge(50000, 51400), &history, nov(18))
+        .with_challenge(&challenge(Some(nov(20))));
+    assert_eq!(early.win_allowed(), Some(false));
+    assert!(!early.can_win());
+}
+
+#[test]
+fn test_streaks() {
+    let mut counter = StreakCounter::new(Utc);
+    // Two projects on the same days, then a gap, then four days ending yesterday
+    counter.add_aggregates(&[aggregate(nov(1), 300), aggregate(nov(2), 200), aggregate(nov(3), 100)]);
+    counter.add_aggregates(&[
+        DailyAggregateData { project_id: 6000, ..aggregate(nov(2), 1000) },
+        DailyAggregateData { unit_type: UnitType::Hours, ..aggregate(nov(5), 2) },
+    ]);
+    counter.add_aggregates(&(7..=10).map(|day| aggregate(nov(day), 50)).collect::<Vec<_>>());
+
+    let streaks = counter.streaks(nov(11));
+    assert_eq!(streaks.current, Some(Streak { start: nov(7), end: nov(10), days: 4 }));
+    assert_eq!(streaks.longest, streaks.current);
+
+    // Missing a whole day ends the streak, and days after today don't count
+    assert_eq!(counter.streaks(nov(12)).current, None);
+    assert_eq!(counter.streaks(nov(4)).current, Some(Streak { start: nov(1), end: nov(3), days: 3 }));
+    assert_eq!(counter.streaks(date(2020, 10, 30)), Streaks::default());
+
+    // Raising the minimum drops the smaller days, and grace days bridge the gaps
+    let rules = StreakRules { min_per_day: Quantity::words(100), grace_days: 1 };
+    let mut counter = StreakCounter::with_rules(Utc, rules);
+    counter.add_aggregates(&[aggregate(nov(1), 300), aggregate(nov(3), 100), aggregate(nov(4), 99)]);
+    counter.add_aggregates(&[aggregate(nov(6), 500), aggregate(nov(7), 500)]);
+
+    let streaks = counter.streaks(nov(9));
+    assert_eq!(streaks.current, Some(Streak { start: nov(6), end: nov(7), days: 2 }));
+    assert_eq!(streaks.longest, Some(Streak { start: nov(1), end: nov(3), days: 2 }));
+    assert_eq!(counter.streaks(nov(10)).current, None);
+
+    // Only writing in the unit of the minimum counts
+    let mut counter = StreakCounter::with_rules(Utc, StreakRules::any_writing(UnitType::Hours));
+    counter.add_aggregates(&[aggregate(nov(1), 300), DailyAggregateData { unit_type: UnitType::Hours, ..aggregate(nov(2), 2) }]);
+    assert_eq!(counter.writing_days(), vec![nov(2)]);
+}
+
+#[test]
+fn test_streaks_time_zone() {
+    // Evening sessions in New Zealand, which end the next morning in UTC terms, and the same
+    // writing bucketed in UTC
+    let auckland = FixedOffset::east_opt(13 * 3600).unwrap();
+    let sessions: Vec<_> = (1..=5)
+        .map(|day| session(Utc.with_ymd_and_hms(2020, 11, day, 9, 0, 0).unwrap(), 500))
+        // Late at night, and again just after midnight, local time
+        .chain(vec![
+            session(Utc.with_ymd_and_hms(2020, 11, 5, 10, 30, 0).unwrap(), 500),
+            session(Utc.with_ymd_and_hms(2020, 11, 5, 11, 30, 0).unwrap(), 500),
+        ])
+        .collect();
+
+    let mut local = StreakCounter::new(auckland);
+    local.add_sessions(&sessions);
+    assert_eq!(local.writing_days(), (1..=6).map(nov).collect::<Vec<_>>());
+    assert_eq!(local.streaks(nov(6)).current, Some(Streak { start: nov(1), end: nov(6), days: 6 }));
+
+    let mut utc = StreakCounter::new(Utc);
+    utc.add_sessions(&sessions);
+    assert_eq!(utc.writing_days(), (1..=5).map(nov).collect::<Vec<_>>());
+
+    // Deleting words can take a day back under the minimum
+    local.add_sessions(&[session(Utc.with_ymd_and_hms(2020, 11, 3, 8, 0, 0).unwrap(), -500)]);
+    let streaks = local.streaks(nov(6));
+    assert_eq!(streaks.current, Some(Streak { start: nov(4), end: nov(6), days: 3 }));
+    assert_eq!(streaks.longest, streaks.current);
+}
+
+#[test]
+fn test_daily_series() {
+    let mut history = november_history();
+    history.retain(|agg| agg.day != nov(5));
+    history.push(aggregate(nov(2), 100));
+    history.push(aggregate(date(2020, 12, 1), 999));
+    history.push(DailyAggregateData { unit_type: UnitType::Hours, ..aggregate(nov(9), 3) });
+
+    let series = DailySeries::new(&project_challenge(50000, 16500), &history);
+    assert_eq!((series.start(), series.end(), series.len()), (nov(1), nov(30), 30));
+    assert_eq!(series.unit_type(), UnitType::Words);
+    assert_eq!(series.get(nov(2)), Some(600));
+    assert_eq!(series.get(nov(5)), Some(0));
+    assert_eq!(series.get(nov(30)), Some(0));
+    assert_eq!(series.get(date(2020, 12, 1)), None);
+    assert_eq!(series.total(), Quantity::words(16600));
+
+    let cumulative = series.cumulative();
+    assert_eq!(cumulative.len(), 30);
+    assert_eq!(cumulative[4], (nov(5), 4100));
+    assert_eq!(cumulative[29], (nov(30), 16600));
+    assert_eq!(series.cumulative_on(nov(5)), 4100);
+    assert_eq!(series.cumulative_on(date(2020, 10, 1)), 0);
+    assert_eq!(series.cumulative_on(date(2021, 1, 1)), 16600);
+
+    let rolling = series.rolling_average(3);
+    assert_eq!(rolling[0], (nov(1), 500.0));
+    assert_eq!(rolling[1], (nov(2), 550.0));
+    assert_eq!(rolling[5], (nov(6), 5000.0 / 3.0));
+    assert_eq!(rolling[12], (nov(13), 0.0));
+
+    assert_eq!(series.best_day(), Some((nov(4), 2500)));
+    assert_eq!(DailySeries::new(&project_challenge(50000, 0), &[]).best_day(), None);
+
+    // November 2020 started on a Sunday, so there are five Sundays and Mondays
+    let weekdays = series.by_weekday();
+    assert_eq!(weekdays.iter().map(|day| day.weekday).collect::<Vec<_>>(), vec![
+        Weekday::Mon, Weekday::Tue, Weekday::Wed, Weekday::Thu, Weekday::Fri, Weekday::Sat, Weekday::Sun
+    ]);
+    let sunday = weekdays[6];
+    assert_eq!((sunday.days, sunday.active_days, sunday.total), (5, 2, 3000));
+    assert_eq!(sunday.average(), 600.0);
+    assert_eq!(weekdays[3].total, 0);
+}
+
+#[test]
+fn test_daily_series_merge() {
+    let mut first = DailySeries::empty(UnitType::Words, nov(1), nov(3));
+    first.add(nov(2), 100);
+    let mut second = DailySeries::empty(UnitType::Words, nov(3), nov(6));
+    assert!(second.add(nov(3), 50));
+    assert!(!second.add(nov(7), 50));
+
+    let merged = DailySeries::merged(&[first.clone(), second]).unwrap().unwrap();
+    assert_eq!((merged.start(), merged.end()), (nov(1), nov(6)));
+    assert_eq!(merged.iter().map(|(_, count)| count).collect::<Vec<_>>(), vec![0, 100, 50, 0, 0, 0]);
+
+    // Gaps between the series are filled in
+    let later = DailySeries::empty(UnitType::Words, nov(10), nov(10));
+    first.merge(&later).unwrap();
+    assert_eq!((first.len(), first.total()), (10, Quantity::words(100)));
+
+    let empty = DailySeries::empty(UnitType::Words, nov(5), nov(4));
+    assert!(empty.is_empty());
+    let mut merged = empty.clone();
+    merged.merge(&later).unwrap();
+    assert_eq!(merged, later);
+
+    let hours = DailySeries::empty(UnitType::Hours, nov(1), nov(30));
+    assert!(matches!(first.merge(&hours), Err(crate::Error::ValidationError(_))));
+    assert_eq!(DailySeries::merged(&[]).unwrap(), None);
+}
+
+/// A session in a Pacific time zone, written in the given conditions
+fn timed_session(day: u32, hour: u32, minutes: i64, count: i64, conditions: Conditions) -> ProjectSessionData {
+    let start = Utc.with_ymd_and_hms(2020, 11, day, 8, 0, 0).unwrap() + Duration::hours(hour as i64);
+    ProjectSessionData {
+        start: Some(start),
+        feeling: conditions.feeling,
+        how: conditions.how,
+        r#where: conditions.r#where,
+        ..session(start + Duration::minutes(minutes), count)
+    }
+}
+
+#[test]
+fn test_productivity() {
+    let pacific = FixedOffset::west_opt(8 * 3600).unwrap();
+    let cafe = Conditions { feeling: Some(Feeling::Great), how: Some(How::Laptop), r#where: Some(Where::Cafe) };
+    let home = Conditions { feeling: Some(Feeling::Okay), how: Some(How::ByHand), r#where: Some(Where::Home) };
+
+    let sessions = vec![
+        // Fast mornings at the cafe
+        timed_session(1, 7, 60, 1500, cafe),
+        timed_session(2, 7, 30, 600, cafe),
+        // Slow evenings at home
+        timed_session(1, 21, 120, 1000, home),
+        timed_session(2, 21, 60, 400, home),
+        // One very fast session isn't enough to count as the best
+        timed_session(3, 13, 10, 1000, Conditions { r#where: Some(Where::Library), ..cafe }),
+        // Untimed sessions only count towards totals, in the hour they ended
+        ProjectSessionData { feeling: home.feeling, how: home.how, r#where: home.r#where, ..session(Utc.with_ymd_and_hms(2020, 11, 3, 6, 30, 0).unwrap(), 300) },
+        ProjectSessionData { unit_type: UnitType::Hours, ..timed_session(4, 7, 60, 1, cafe) },
+    ];
+
+    let mut productivity = Productivity::new(pacific, UnitType::Words);
+    productivity.add_sessions(&sessions);
+
+    let overall = productivity.overall();
+    assert_eq!((overall.sessions(), overall.count(), overall.timed_sessions(), overall.timed_count()), (6, Quantity::words(4800), 5, Quantity::words(4500)));
+    assert_eq!(overall.time(), Duration::minutes(280));
+    assert_eq!(overall.per_session(), 800.0);
+
+    let by_where = productivity.by_where();
+    let cafe_stats = by_where[&Some(Where::Cafe)];
+    assert_eq!((cafe_stats.sessions(), cafe_stats.count()), (2, Quantity::words(2100)));
+    assert_eq!(cafe_stats.per_hour(), Some(1400.0));
+    let home_stats = by_where[&Some(Where::Home)];
+    assert_eq!((home_stats.sessions(), home_stats.timed_sessions(), home_stats.count()), (3, 2, Quantity::words(1700)));
+    assert_eq!(home_stats.per_hour(), Some(1400.0 / 3.0));
+    assert_eq!(productivity.by_feeling()[&Some(Feeling::Great)].count(), Quantity::words(3100));
+    assert_eq!(productivity.by_how().len(), 2);
+    assert_eq!(productivity.by_conditions().len(), 3);
+
+    assert_eq!(productivity.most_productive(1).unwrap().0.r#where, Some(Where::Library));
+    let (best, stats) = productivity.most_productive(2).unwrap();
+    assert_eq!(best, cafe);
+    assert_eq!(stats.sessions(), 2);
+    assert_eq!(productivity.most_productive(3), None);
+
+    let hours = productivity.by_hour();
+    assert_eq!((hours[7].sessions(), hours[21].sessions(), hours[22].sessions()), (2, 2, 1));
+    assert_eq!(hours[13].count(), Quantity::words(1000));
+    assert_eq!(productivity.most_productive_hour(2).unwrap().0, 7);
+
+    let empty = Productivity::new(Utc, UnitType::Words);
+    assert_eq!(empty.overall(), SessionStats::empty(UnitType::Words));
+    assert_eq!(empty.overall().per_hour(), None);
+    assert_eq!(empty.most_productive_hour(1), None);
+}
+
+#[test]
+fn test_badge_predictions() {
+    let rules: BadgeRules = serde_json::from_value(json!({
+        "1": { "type": "total", "count": 10000 },
+        "2": { "type": "total", "count": 25000 },
+        "3": { "type": "goal" },
+        "4": { "type": "day", "count": 2500 },
+        "5": { "type": "day", "count": 3000 },
+        "6": { "type": "streak", "days": 7 },
+        "7": { "type": "streak", "days": 14 },
+        "8": { "type": "total", "count": 100000 }
+    })).unwrap();
+    assert_eq!(rules.get(3), Some(BadgeRule::Goal));
+    assert_eq!(rules.get(9), None);
+
+    let series = DailySeries::new(&project_challenge(50000, 19000), &november_history());
+    let predictions = rules.predict(&series, Quantity::words(50000), nov(10), &[]).unwrap();
+    let summary = predictions.iter()
+        .map(|pred| (pred.badge_id, pred.earned_on, pred.remaining, pred.expected))
+        .collect::<Vec<_>>();
+    assert_eq!(summary, vec![
+        (1, Some(nov(7)), 0, None),
+        (2, None, 6000, Some(nov(14))),
+        (3, None, 31000, Some(nov(27))),
+        (4, Some(nov(4)), 0, None),
+        (5, None, 500, None),
+        (6, Some(nov(7)), 0, None),
+        (7, None, 4, Some(nov(14))),
+        (8, None, 81000, None),
+    ]);
+
+    assert_eq!(predictions[1].describe("25k"), "6,000 words away from the 25k badge");
+    assert_eq!(predictions[4].describe("Big Day"), "3,000 words in one day to earn the Big Day badge");
+    assert_eq!(predictions[6].describe("Fortnight"), "4 more days in a row to earn the Fortnight badge");
+    assert_eq!(predictions[0].describe("10k"), "Earned the 10k badge");
+    assert_eq!(series.total().to_string(), "19,000 words");
+    assert!(predictions[1].is_expected_by(nov(14)));
+    assert!(!predictions[1].is_expected_by(nov(13)));
+    assert!(!predictions[0].is_expected_by(nov(30)));
+
+    // Badges Nano has awarded count as earned, and a streak not yet continued today isn't broken
+    let awarded = UserBadgeData {
+        badge_id: 8,
+        created_at: Utc.with_ymd_and_hms(2020, 11, 10, 0, 0, 0).unwrap(),
+        project_challenge_id: 1,
+        user_id: 74,
+    };
+    let predictions = rules.predict(&series, Quantity::words(50000), nov(11), &[awarded]).unwrap();
+    assert!(predictions[7].is_earned());
+    assert_eq!((predictions[6].remaining, predictions[6].expected), (4, Some(nov(14))));
+    assert_eq!(predictions[4].remaining, 3000);
+}
+
+#[test]
+fn test_badge_predictions_hours() {
+    let rules: BadgeRules = serde_json::from_value(json!({
+        "1": { "type": "goal" },
+        "2": { "type": "day", "count": 3 }
+    })).unwrap();
+
+    let challenge = ProjectChallengeData { unit_type: UnitType::Hours, ..project_challenge(20, 4) };
+    let history = [(1, 1), (2, 2), (3, 1)].iter()
+        .map(|(day, count)| DailyAggregateData { unit_type: UnitType::Hours, ..aggregate(nov(*day), *count) })
+        .collect::<Vec<_>>();
+    let series = DailySeries::new(&challenge, &history);
+
+    // Counts are described in the unit of the series, and a goal in another unit is refused
+    let predictions = rules.predict(&series, Quantity::hours(20), nov(3), &[]).unwrap();
+    assert_eq!(predictions[0].describe("Camp"), "16 hours away from the Camp badge");
+    assert_eq!(predictions[1].describe("Marathon"), "3 hours in one day to earn the Marathon badge");
+    let res = rules.predict(&series, Quantity::words(50000), nov(3), &[]);
+    assert!(matches!(res, Err(crate::Error::ValidationError(_))));
+}
@@ -0,0 +1,252 @@
+//! A blocking version of [`NanoClient`](crate::NanoClient), for use outside of async code.
+//! Requires the `blocking` feature.
+//!
+//! The blocking client wraps an async client, and runs each call to completion on its own
+//! single-threaded runtime, so it shares all the data types, errors, retries and middleware of
+//! the async client. It must not be used from inside another runtime, where it will panic.
+//!
+//! Helpers that take an async client, such as [`Leaderboard`] and [`RegionScan`], are driven
+//! through methods on the blocking client instead, like [`NanoClient::refresh_leaderboard`].
+//!
+//! ```no_run
+//! # fn example() -> Result<(), nanowrimo::Error> {
+//! use nanowrimo::blocking::NanoClient;
+//!
+//! let client = NanoClient::new_user("username", "password")?;
+//! let user = client.current_user()?;
+//! println!("Word count: {}", user.data.data.stats.word_count);
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::analytics::WritingHistory;
+use crate::client::NanoClientBuilder;
+use crate::credentials::CredentialProvider;
+use crate::data::*;
+use crate::error::Error;
+use crate::kind::NanoKind;
+use crate::leaderboard::Leaderboard;
+use crate::quantity::Quantity;
+use crate::region::RegionScan;
+use crate::session::{Session, SessionState, SessionStore};
+
+use std::cell::RefCell;
+use std::fmt;
+use std::future::Future;
+
+use chrono::NaiveDate;
+use serde::Serialize;
+use tokio::runtime::{Builder, Runtime};
+
+/// A blocking client for the Nano API. Every method matches the method of the same name on the
+/// async [`NanoClient`](crate::NanoClient), but waits for the response instead of returning a
+/// future
+pub struct NanoClient {
+    inner: crate::NanoClient,
+    runtime: RefCell<Runtime>,
+}
+
+impl fmt::Debug for NanoClient {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("NanoClient")
+            .field("inner", &self.inner)
+            .finish()
+    }
+}
+
+impl NanoClient {
+    /// Wrap an async client, such as one built with [`NanoClientBuilder`]. Fails if the runtime
+    /// for the client can't be started
+    pub fn from_async(inner: crate::NanoClient) -> Result<NanoClient, Error> {
+        let runtime = Builder::new()
+            .basic_scheduler()
+            .enable_all()
+            .build()?;
+
+        Ok(NanoClient { inner, runtime: RefCell::new(runtime) })
+    }
+
+    fn wrap(inner: crate::NanoClient) -> NanoClient {
+        NanoClient::from_async(inner).expect("Couldn't start the runtime for a blocking NanoClient")
+    }
+
+    fn block_on<F: Future>(&self, fut: F) -> F::Output {
+        self.runtime.borrow_mut().block_on(fut)
+    }
+
+    /// Get the async client this wraps
+    pub fn into_async(self) -> crate::NanoClient {
+        self.inner
+    }
+
+    /// Create a builder, to configure a client beyond what the other constructors allow. Finish
+    /// it with [`NanoClientBuilder::build_blocking`]
+    pub fn builder() -> NanoClientBuilder {
+        NanoClientBuilder::new()
+    }
+
+    /// Create a new client with the 'anonymous' or 'guest' user, not logged in. Panics if the
+    /// runtime for the client can't be started
+    pub fn new_anon() -> NanoClient {
+        NanoClient::wrap(crate::NanoClient::new_anon())
+    }
+
+    /// Create a new client that is automatically logged in as a specific user
+    pub fn new_user(user: &str, pass: &str) -> Result<NanoClient, Error> {
+        let client = NanoClient::from_async(crate::NanoClient::builder().user(user, pass).build()?)?;
+        client.login()?;
+        Ok(client)
+    }
+
+    /// Create a new client that gets its credentials from a provider. Panics if the runtime for
+    /// the client can't be started. See [`crate::NanoClient::with_credentials`]
+    pub fn with_credentials<P: CredentialProvider + 'static>(provider: P) -> NanoClient {
+        NanoClient::wrap(crate::NanoClient::with_credentials(provider))
+    }
+
+    /// Create a new client authenticated by the token of an existing session. Panics if the
+    /// runtime for the client can't be started. See [`crate::NanoClient::with_token`]
+    pub fn with_token(token: &str) -> NanoClient {
+        NanoClient::wrap(crate::NanoClient::with_token(token))
+    }
+
+    /// Create a new client that restores its session from a store. See
+    /// [`crate::NanoClient::with_store`]
+    pub fn with_store<S: SessionStore + 'static>(store: S) -> Result<NanoClient, Error> {
+        NanoClient::from_async(crate::NanoClient::with_store(store)?)
+    }
+
+    /// Create a new client for a specific user that restores its session from a store, and only
+    /// logs in if the store has no session. See [`crate::NanoClient::new_user_stored`]
+    pub fn new_user_stored<S: SessionStore + 'static>(user: &str, pass: &str, store: S) -> Result<NanoClient, Error> {
+        let client = NanoClient::from_async(
+            crate::NanoClient::builder().user(user, pass).session_store(store).build()?
+        )?;
+
+        if !client.is_logged_in() {
+            client.login()?;
+        }
+
+        Ok(client)
+    }
+
+    /// Get the current session of this client, if it's authenticated
+    pub fn session(&self) -> Option<Session> {
+        self.inner.session()
+    }
+
+    /// Check whether this client is currently logged in
+    pub fn is_logged_in(&self) -> bool {
+        self.inner.is_logged_in()
+    }
+
+    /// Get the current log-in state of this client
+    pub fn session_state(&self) -> SessionState {
+        self.inner.session_state()
+    }
+
+    /// Change the current user of the client. See [`crate::NanoClient::change_user`]
+    pub fn change_user(&mut self, user: Option<&str>, pass: Option<&str>) -> Result<(), Error> {
+        let NanoClient { inner, runtime } = self;
+        runtime.get_mut().block_on(inner.change_user(user, pass))
+    }
+
+    /// Fetch the members of a leaderboard's group again. See [`Leaderboard::reload_members`]
+    pub fn reload_leaderboard_members(&self, leaderboard: &mut Leaderboard) -> Result<(), Error> {
+        self.block_on(leaderboard.reload_members(&self.inner))
+    }
+
+    /// Fetch the latest counts for a leaderboard, and rank them again. See
+    /// [`Leaderboard::refresh`]
+    pub fn refresh_leaderboard(&self, leaderboard: &mut Leaderboard, today: NaiveDate) -> Result<(), Error> {
+        self.block_on(leaderboard.refresh(&self.inner, today))
+    }
+
+    /// Fetch the next page of members for a region scan. See [`RegionScan::next_page`]
+    pub fn next_region_page(&self, scan: &mut RegionScan) -> Result<(), Error> {
+        self.block_on(scan.next_page(&self.inner))
+    }
+
+    /// Fetch every remaining page of members for a region scan. See [`RegionScan::run`]
+    pub fn run_region_scan(&self, scan: &mut RegionScan) -> Result<(), Error> {
+        self.block_on(scan.run(&self.inner))
+    }
+}
+
+macro_rules! blocking_methods {
+    ($( fn $name:ident $(<$gen:ident: $bound:path>)? ($($arg:ident: $ty:ty),*) -> $ret:ty; )+) => {
+        impl NanoClient {
+            $(
+            #[doc = concat!("See [`crate::NanoClient::", stringify!($name), "`]")]
+            pub fn $name $(<$gen: $bound>)? (&self $(, $arg: $ty)*) -> Result<$ret, Error> {
+                self.block_on(self.inner.$name($($arg),*))
+            }
+            )+
+        }
+    }
+}
+
+blocking_methods! {
+    fn login() -> ();
+    fn logout() -> ();
+
+    fn fundometer() -> Fundometer;
+    fn search(name: &str) -> CollectionResponse<UserObject>;
+    fn random_offer() -> ItemResponse<PostObject>;
+    fn store_items() -> Vec<StoreItem>;
+    fn offers() -> Vec<ItemResponse<PostObject>>;
+    fn current_user_include(include: &[NanoKind]) -> ItemResponse<UserObject>;
+    fn current_user() -> ItemResponse<UserObject>;
+    fn pages(page: &str) -> ItemResponse<PageObject>;
+    fn notifications() -> CollectionResponse<NotificationObject>;
+    fn available_challenges() -> CollectionResponse<ChallengeObject>;
+    fn daily_aggregates(id: u64) -> CollectionResponse<DailyAggregateObject>;
+
+    fn create<D: Serialize>(ty: NanoKind, data: &D) -> ItemResponse;
+    fn update<D: Serialize>(ty: NanoKind, id: u64, data: &D) -> ItemResponse;
+    fn delete(ty: NanoKind, id: u64) -> ();
+    fn add_related(ty: NanoKind, id: u64, rel: NanoKind, items: &[ObjectRef]) -> ();
+    fn remove_related(ty: NanoKind, id: u64, rel: NanoKind, items: &[ObjectRef]) -> ();
+    fn attach_genre(project_id: u64, genre_id: u64) -> ();
+    fn detach_genre(project_id: u64, genre_id: u64) -> ();
+
+    fn enroll(project_id: u64, challenge_id: u64, goal: Quantity) -> ItemResponse<ProjectChallengeObject>;
+    fn update_goal(project_challenge_id: u64, goal: Quantity) -> ItemResponse<ProjectChallengeObject>;
+    fn unenroll(project_challenge_id: u64) -> ();
+    fn create_custom_challenge(project_id: u64, challenge: &CustomChallenge) -> ItemResponse<ChallengeObject>;
+    fn award_badge(project_challenge_id: u64, badge_id: u64) -> ItemResponse<UserBadgeObject>;
+    fn revoke_badge(user_badge_id: u64) -> ();
+    fn badges_for(project_challenge_id: u64) -> ChallengeBadges;
+    fn history(user_id: u64) -> WritingHistory;
+
+    fn upload_avatar(user_id: u64, data: &[u8], mime: &str) -> ItemResponse<UserObject>;
+    fn upload_project_cover(project_id: u64, data: &[u8], mime: &str) -> ItemResponse<ProjectObject>;
+    fn upload_group_avatar(group_id: u64, data: &[u8], mime: &str) -> ItemResponse<GroupObject>;
+
+    fn get_all_include_filtered(ty: NanoKind, include: &[NanoKind], filter: &[(&str, u64)]) -> CollectionResponse;
+    fn get_all_filtered(ty: NanoKind, filter: &[(&str, u64)]) -> CollectionResponse;
+    fn get_all_include(ty: NanoKind, include: &[NanoKind]) -> CollectionResponse;
+    fn get_all(ty: NanoKind) -> CollectionResponse;
+    fn get_id_include(ty: NanoKind, id: u64, include: &[NanoKind]) -> ItemResponse;
+    fn get_id(ty: NanoKind, id: u64) -> ItemResponse;
+    fn get_slug_include(ty: NanoKind, slug: &str, include: &[NanoKind]) -> ItemResponse;
+    fn get_slug(ty: NanoKind, slug: &str) -> ItemResponse;
+    fn get_all_related(rel: &RelationLink) -> CollectionResponse;
+    fn get_unique_related(rel: &RelationLink) -> ItemResponse;
+
+    fn create_favorite_author(data: &FavoriteAuthorData) -> ItemResponse<FavoriteAuthorObject>;
+    fn update_favorite_author(id: u64, data: &FavoriteAuthorData) -> ItemResponse<FavoriteAuthorObject>;
+    fn delete_favorite_author(id: u64) -> ();
+    fn create_favorite_book(data: &FavoriteBookData) -> ItemResponse<FavoriteBookObject>;
+    fn update_favorite_book(id: u64, data: &FavoriteBookData) -> ItemResponse<FavoriteBookObject>;
+    fn delete_favorite_book(id: u64) -> ();
+    fn create_genre(data: &GenreData) -> ItemResponse<GenreObject>;
+    fn update_genre(id: u64, data: &GenreData) -> ItemResponse<GenreObject>;
+    fn delete_genre(id: u64) -> ();
+    fn create_writing_location(data: &WritingLocationData) -> ItemResponse<WritingLocationObject>;
+    fn update_writing_location(id: u64, data: &WritingLocationData) -> ItemResponse<WritingLocationObject>;
+    fn delete_writing_location(id: u64) -> ();
+    fn create_writing_method(data: &WritingMethodData) -> ItemResponse<WritingMethodObject>;
+    fn update_writing_method(id: u64, data: &WritingMethodData) -> ItemResponse<WritingMethodObject>;
+    fn delete_writing_method(id: u64) -> ();
+}
@@ -0,0 +1,230 @@
+//! Recording responses from Nano to JSON files, and replaying them later without network access.
+//!
+//! A [`Recorder`] is a [`Middleware`] that saves every request a client makes, along with the
+//! response, into a [`Cassette`]. Passwords, tokens and emails are scrubbed before anything is
+//! saved, and headers aren't saved at all. A [`Replayer`] answers requests from a cassette instead
+//! of sending them, so tests built on recorded responses are fast and deterministic.
+//!
+//! ```no_run
+//! # async fn example() -> Result<(), nanowrimo::Error> {
+//! use nanowrimo::NanoClient;
+//! use nanowrimo::cassette::{Cassette, Recorder, Replayer};
+//!
+//! let recorder = Recorder::new();
+//! let client = NanoClient::builder().middleware(recorder.clone()).build()?;
+//! client.store_items().await?;
+//! recorder.cassette().save("store_items.json")?;
+//!
+//! let client = NanoClient::builder()
+//!     .middleware(Replayer::new(Cassette::load("store_items.json")?))
+//!     .build()?;
+//! client.store_items().await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::error::Error;
+use crate::middleware::{Middleware, RawResponse, RequestInfo};
+
+use std::cell::RefCell;
+use std::fs;
+use std::path::Path;
+use std::rc::Rc;
+
+use reqwest::StatusCode;
+use reqwest::header::HeaderMap;
+use serde::{Serialize, Deserialize};
+use serde_json::Value;
+
+const SCRUBBED: &str = "<scrubbed>";
+
+/// A recorded request, identified by its method and path
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct RecordedRequest {
+    /// The method of the request, such as `GET`
+    pub method: String,
+    /// The path of the request, relative to the base URL, including the query
+    pub path: String,
+    /// The JSON body of the request, if it had one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub body: Option<Value>,
+}
+
+/// A recorded response to a request
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct RecordedResponse {
+    /// The status code of the response
+    pub status: u16,
+    /// The JSON body of the response, or None if it was empty or not JSON
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub body: Option<Value>,
+}
+
+/// A single request and the response to it
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct Interaction {
+    /// The request that was sent
+    pub request: RecordedRequest,
+    /// The response that was received
+    pub response: RecordedResponse,
+}
+
+/// A list of recorded interactions, in the order they happened
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct Cassette {
+    /// The recorded interactions
+    pub interactions: Vec<Interaction>,
+}
+
+impl Cassette {
+    /// Create a new, empty, cassette
+    pub fn new() -> Cassette {
+        Cassette::default()
+    }
+
+    /// Load a cassette from a JSON file
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Cassette, Error> {
+        Ok(serde_json::from_slice(&fs::read(path)?)?)
+    }
+
+    /// Save this cassette to a JSON file, replacing it if it exists
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), Error> {
+        fs::write(path, serde_json::to_vec_pretty(self)?)?;
+        Ok(())
+    }
+}
+
+fn request_path(req: &RequestInfo) -> String {
+    let path = req.url.path().trim_start_matches('/');
+
+    match req.url.query() {
+        Some(query) => format!("{}?{}", path, query),
+        None => path.to_string()
+    }
+}
+
+fn scrub(val: &mut Value, fields: &[String]) {
+    match val {
+        Value::Object(map) => {
+            for (key, val) in map.iter_mut() {
+                if fields.iter().any(|field| field == key) && !val.is_null() {
+                    *val = Value::String(SCRUBBED.to_string());
+                } else {
+                    scrub(val, fields);
+                }
+            }
+        },
+        Value::Array(vals) => vals.iter_mut().for_each(|val| scrub(val, fields)),
+        _ => ()
+    }
+}
+
+/// A [`Middleware`] that records every request and response into a [`Cassette`]. Clones share
+/// the same cassette, so a clone can be kept to save the cassette after the client is done
+#[derive(Debug, Clone)]
+pub struct Recorder {
+    cassette: Rc<RefCell<Cassette>>,
+    scrub_fields: Vec<String>,
+}
+
+impl Recorder {
+    /// Create a new recorder, with an empty cassette. The `password`, `auth_token` and `email`
+    /// fields are scrubbed from all bodies
+    pub fn new() -> Recorder {
+        Recorder {
+            cassette: Rc::new(RefCell::new(Cassette::new())),
+            scrub_fields: vec!["password".to_string(), "auth_token".to_string(), "email".to_string()],
+        }
+    }
+
+    /// Also scrub a field with a given name from all bodies, wherever it appears
+    pub fn scrub_field(mut self, field: &str) -> Recorder {
+        self.scrub_fields.push(field.to_string());
+        self
+    }
+
+    /// Get a copy of everything recorded so far
+    pub fn cassette(&self) -> Cassette {
+        self.cassette.borrow().clone()
+    }
+}
+
+impl Default for Recorder {
+    fn default() -> Recorder {
+        Recorder::new()
+    }
+}
+
+impl Middleware for Recorder {
+    fn on_response(&self, req: &RequestInfo, resp: &mut RawResponse) -> Result<(), Error> {
+        let parse = |body: &[u8]| -> Option<Value> {
+            let mut val = serde_json::from_slice(body).ok()?;
+            scrub(&mut val, &self.scrub_fields);
+            Some(val)
+        };
+
+        let request = RecordedRequest {
+            method: req.method.to_string(),
+            path: request_path(req),
+            body: req.body.as_deref().and_then(parse),
+        };
+
+        let response = RecordedResponse {
+            status: resp.status.as_u16(),
+            body: parse(&resp.body),
+        };
+
+        self.cassette.borrow_mut().interactions.push(Interaction { request, response });
+        Ok(())
+    }
+}
+
+/// A [`Middleware`] that answers requests from a [`Cassette`], without sending them. Each request
+/// is answered with the first unused interaction with the same method and path, so repeated
+/// requests are answered in the order they were recorded. Requests with no matching interaction
+/// fail with a [`Error::CassetteError`]
+#[derive(Debug)]
+pub struct Replayer {
+    remaining: RefCell<Vec<Interaction>>,
+}
+
+impl Replayer {
+    /// Create a new replayer for a cassette
+    pub fn new(cassette: Cassette) -> Replayer {
+        Replayer { remaining: RefCell::new(cassette.interactions) }
+    }
+
+    /// Get the interactions that haven't been replayed yet
+    pub fn remaining(&self) -> Vec<Interaction> {
+        self.remaining.borrow().clone()
+    }
+}
+
+impl Middleware for Replayer {
+    fn intercept(&self, req: &RequestInfo) -> Option<Result<RawResponse, Error>> {
+        let method = req.method.to_string();
+        let path = request_path(req);
+
+        let mut remaining = self.remaining.borrow_mut();
+        let idx = match remaining.iter().position(|int| int.request.method == method && int.request.path == path) {
+            Some(idx) => idx,
+            None => return Some(Err(Error::CassetteError(format!("No recorded response for {} {}", method, path))))
+        };
+
+        let resp = remaining.remove(idx).response;
+        let status = match StatusCode::from_u16(resp.status) {
+            Ok(status) => status,
+            Err(err) => return Some(Err(Error::CassetteError(format!("Invalid recorded status: {}", err))))
+        };
+
+        Some(Ok(RawResponse {
+            status,
+            headers: HeaderMap::new(),
+            body: resp.body.map(|body| body.to_string().into_bytes()).unwrap_or_default(),
+        }))
+    }
+}
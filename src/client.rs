@@ -1,17 +1,102 @@
 use super::data::*;
 use super::error::Error;
 use super::kind::NanoKind;
-
-use std::collections::HashMap;
+use super::enums::{AdheresTo, BadgeType, EventType, UnitType};
+use super::session::{Session, SessionState, SessionStore};
+use super::credentials::{CredentialProvider, Credentials};
+use super::middleware::{Middleware, RawResponse, RequestInfo};
+use super::transport::{HttpTransport, ReqwestTransport};
+use super::quantity::Quantity;
+use super::analytics::{HistoryEntry, WritingHistory};
+
+use std::collections::{HashMap, HashSet};
+use std::collections::hash_map::RandomState;
 use std::cell::RefCell;
-
-use reqwest::{Client, Method, StatusCode};
+use std::rc::Rc;
+use std::future::Future;
+use std::hash::{BuildHasher, Hasher};
+
+use chrono::Utc;
+use futures_util::stream::{self, StreamExt};
+use paste::paste;
+use reqwest::{Client, Method, RequestBuilder, StatusCode};
+use reqwest::header::{HeaderMap, HeaderValue, AUTHORIZATION, CONTENT_TYPE, RETRY_AFTER};
 use serde::Serialize;
 use serde::de::DeserializeOwned;
 
+mod builder;
+mod governor;
+mod trace;
+
 #[cfg(test)]
 mod tests;
 
+pub use builder::NanoClientBuilder;
+pub use governor::{Backoff, RateLimit, set_global_rate_limit};
+
+use governor::Governor;
+use trace::CallTrace;
+
+struct ImageUpload<'a> {
+    field: &'a str,
+    data: &'a [u8],
+    mime: &'a str,
+    extension: &'a str,
+}
+
+impl ImageUpload<'_> {
+    /// Encode the upload as a multipart form with a single part, returning the content type and
+    /// body. The body is built up front, rather than streamed, so every transport can send it
+    fn multipart(&self) -> (String, Vec<u8>) {
+        let boundary = loop {
+            let random = RandomState::new().build_hasher().finish();
+            let boundary = format!("nanowrimo-{:016x}", random);
+            if !self.data.windows(boundary.len()).any(|window| window == boundary.as_bytes()) {
+                break boundary;
+            }
+        };
+
+        let mut body = format!(
+            "--{}\r\nContent-Disposition: form-data; name=\"{}\"; filename=\"{}.{}\"\r\nContent-Type: {}\r\n\r\n",
+            boundary, self.field, self.field, self.extension, self.mime
+        ).into_bytes();
+        body.extend_from_slice(self.data);
+        body.extend_from_slice(format!("\r\n--{}--\r\n", boundary).as_bytes());
+
+        (format!("multipart/form-data; boundary={}", boundary), body)
+    }
+}
+
+/// The outcome of sending a request once: the status of the response, if there was one, and the
+/// parsed result
+type Attempt<U> = (Option<StatusCode>, Result<U, Error>);
+
+/// How many related objects are fetched at once, when a call needs several of them
+const FETCH_CONCURRENCY: usize = 4;
+
+/// Run a fetch for every item, up to `concurrency` at a time, and get every result, in the order
+/// they finished
+pub(crate) async fn fetch_each<T, F, Fut, U>(items: Vec<T>, concurrency: usize, fetch: F) -> Vec<Result<U, Error>>
+    where
+        F: Fn(T) -> Fut,
+        Fut: Future<Output = Result<U, Error>>
+{
+    stream::iter(items)
+        .map(fetch)
+        .buffer_unordered(concurrency)
+        .collect()
+        .await
+}
+
+/// Run a fetch for every item, up to `concurrency` at a time, failing if any of them do
+pub(crate) async fn fetch_all<T, F, Fut, U>(items: Vec<T>, concurrency: usize, fetch: F) -> Result<Vec<U>, Error>
+    where
+        F: Fn(T) -> Fut,
+        Fut: Future<Output = Result<U, Error>>
+{
+    fetch_each(items, concurrency, fetch).await.into_iter().collect()
+}
+
 fn add_included(data: &mut Vec<(String, String)>, include: &[NanoKind]) {
     if !include.is_empty() {
         data.push(
@@ -21,26 +106,53 @@ fn add_included(data: &mut Vec<(String, String)>, include: &[NanoKind]) {
 }
 
 /// A client with which to connect to the Nano site. Can be used with or without login.
+/// Credentials and tokens are redacted from its `Debug` output.
 #[derive(Debug)]
 pub struct NanoClient {
     client: Client,
-    username: String,
-    password: String,
-    token: RefCell<Option<String>>,
+    base_url: String,
+    credentials: Option<Box<dyn CredentialProvider>>,
+    state: RefCell<SessionState>,
+    store: Option<Box<dyn SessionStore>>,
+    governor: Governor,
+    middleware: Vec<Box<dyn Middleware>>,
+    transport: Box<dyn HttpTransport>,
 }
 
 impl NanoClient {
     const BASE_URL: &'static str = "https://api.nanowrimo.org/";
 
     fn new(user: &str, pass: &str) -> NanoClient {
+        NanoClient::new_url(NanoClient::BASE_URL, user, pass)
+    }
+
+    pub(crate) fn new_url(base_url: &str, user: &str, pass: &str) -> NanoClient {
+        let credentials: Option<Box<dyn CredentialProvider>> = if user.is_empty() {
+            None
+        } else {
+            Some(Box::new(Credentials::new(user, pass)))
+        };
+
+        let client = Client::new();
+
         NanoClient {
-            client: Client::new(),
-            username: user.to_string(),
-            password: pass.to_string(),
-            token: RefCell::new(None),
+            transport: Box::new(ReqwestTransport::from_client(client.clone())),
+            client,
+            base_url: base_url.to_string(),
+            credentials,
+            state: RefCell::new(SessionState::Anonymous),
+            store: None,
+            governor: Governor::new(None, Backoff::default()),
+            middleware: Vec::new(),
         }
     }
 
+    /// Create a builder, to configure a client beyond what the other constructors allow, such as
+    /// rate limiting
+    pub fn builder() -> NanoClientBuilder {
+        NanoClientBuilder::new()
+    }
+
     /// Create a new client with the 'anonymous' or 'guest' user, not logged in
     pub fn new_anon() -> NanoClient {
         NanoClient::new("", "")
@@ -53,7 +165,70 @@ impl NanoClient {
         Ok(client)
     }
 
-    async fn make_request<T, U>(&self, path: &str, method: Method, data: &T) -> Result<U, Error>
+    /// Create a new client that gets its credentials from a provider, such as
+    /// [`EnvCredentials`](crate::credentials::EnvCredentials). The provider is only asked for
+    /// credentials when the client logs in, which it does automatically the first time Nano
+    /// requires it, or manually with [`Self::login`]
+    pub fn with_credentials<P: CredentialProvider + 'static>(provider: P) -> NanoClient {
+        let mut client = NanoClient::new("", "");
+        client.credentials = Some(Box::new(provider));
+        client
+    }
+
+    /// Create a new client authenticated by the token of an existing session, such as one from
+    /// [`Self::session`]. The client has no username or password, so it can't log in again if
+    /// the token expires
+    pub fn with_token(token: &str) -> NanoClient {
+        let client = NanoClient::new("", "");
+        client.state.replace(SessionState::authenticated(token.to_string()));
+        client
+    }
+
+    /// Create a new client that restores its session from a store, if the store has one, and
+    /// otherwise stays the 'anonymous' or 'guest' user. The client saves its session to the store
+    /// whenever it logs in or out
+    pub fn with_store<S: SessionStore + 'static>(store: S) -> Result<NanoClient, Error> {
+        let mut client = NanoClient::new("", "");
+        client.set_store(Box::new(store))?;
+        Ok(client)
+    }
+
+    /// Create a new client for a specific user that restores its session from a store, and only
+    /// logs in if the store has no session. If the restored session has expired, the client logs
+    /// in again the first time Nano rejects it. The client saves its session to the store
+    /// whenever it logs in or out
+    pub async fn new_user_stored<S: SessionStore + 'static>(user: &str, pass: &str, store: S) -> Result<NanoClient, Error> {
+        let mut client = NanoClient::new(user, pass);
+        client.set_store(Box::new(store))?;
+
+        if !client.is_logged_in() {
+            client.login().await?;
+        }
+
+        Ok(client)
+    }
+
+    fn set_store(&mut self, store: Box<dyn SessionStore>) -> Result<(), Error> {
+        if let Some(session) = store.load()? {
+            self.state.replace(SessionState::authenticated(session.auth_token));
+        }
+        self.store = Some(store);
+        Ok(())
+    }
+
+    /// Get the current session of this client, if it's authenticated. The session can be saved
+    /// and later used with [`Self::with_token`] to authenticate without logging in
+    pub fn session(&self) -> Option<Session> {
+        self.state.borrow()
+            .token()
+            .map(|token| Session { auth_token: token.expose().to_string() })
+    }
+
+    fn has_credentials(&self) -> bool {
+        self.credentials.is_some()
+    }
+
+    async fn make_request<T, U>(&self, path: &str, method: Method, data: &T) -> Attempt<U>
         where
             T: Serialize + ?Sized,
             U: DeserializeOwned + std::fmt::Debug
@@ -66,11 +241,7 @@ impl NanoClient {
             _ => json = Some(data)
         }
 
-        let mut req  = self.client.request(method, &format!("{}{}", NanoClient::BASE_URL, path));
-
-        if let Some(token) = &*self.token.borrow() {
-            req = req.header("Authorization", token)
-        }
+        let mut req = self.request(path, method);
 
         if let Some(query) = query {
             req = req.query(query);
@@ -80,10 +251,68 @@ impl NanoClient {
             req = req.json(json)
         }
 
-        let resp = req.send()
-            .await?;
+        self.send_request(req, path).await
+    }
+
+    async fn make_upload<U>(&self, path: &str, upload: &ImageUpload<'_>) -> Attempt<U>
+        where
+            U: DeserializeOwned + std::fmt::Debug
+    {
+        let (content_type, body) = upload.multipart();
+
+        let req = self.request(path, Method::POST)
+            .header(CONTENT_TYPE, content_type)
+            .body(body);
+
+        self.send_request(req, path).await
+    }
+
+    fn request(&self, path: &str, method: Method) -> RequestBuilder {
+        let mut req = self.client.request(method, &format!("{}{}", self.base_url, path));
+
+        if let Some(token) = self.state.borrow().token() {
+            // RequestBuilder::header always clears the sensitive flag, so that Debug output shows
+            // the value, but headers passed as a map are kept as-is
+            if let Ok(mut val) = HeaderValue::from_str(token.expose()) {
+                val.set_sensitive(true);
+                let mut headers = HeaderMap::new();
+                headers.insert(AUTHORIZATION, val);
+                req = req.headers(headers)
+            }
+        }
+
+        req
+    }
 
-        let status = resp.status();
+    /// Send a request once, returning the status of the response along with the result, if
+    /// there was a response
+    async fn send_request<U>(&self, req: RequestBuilder, path: &str) -> Attempt<U>
+        where
+            U: DeserializeOwned + std::fmt::Debug
+    {
+        self.governor.acquire(&*self.transport).await;
+
+        let resp = match self.execute(req).await {
+            Ok(resp) => resp,
+            Err(err) => return (None, Err(err))
+        };
+        let status = resp.status;
+        (Some(status), NanoClient::read_response(resp, path))
+    }
+
+    fn read_response<U>(resp: RawResponse, path: &str) -> Result<U, Error>
+        where
+            U: DeserializeOwned + std::fmt::Debug
+    {
+        let status = resp.status;
+
+        if governor::is_throttled(status) {
+            let retry_after = resp.headers
+                .get(RETRY_AFTER)
+                .and_then(|val| val.to_str().ok())
+                .and_then(governor::parse_retry_after);
+            return Err(Error::Throttled(status, retry_after));
+        }
 
         match status {
             StatusCode::INTERNAL_SERVER_ERROR => return Err(
@@ -92,12 +321,15 @@ impl NanoClient {
             StatusCode::NOT_FOUND => return Err(
                 Error::SimpleNanoError(status, "Page Not Found".to_string())
             ),
+            StatusCode::NO_CONTENT => return Ok(serde_json::from_value(serde_json::Value::Null)?),
+            // Other server errors usually come from a proxy in front of Nano, and aren't JSON
+            _ if status.is_server_error() => return Err(
+                Error::SimpleNanoError(status, status.canonical_reason().unwrap_or("Server Error").to_string())
+            ),
             _ => ()
         }
 
-        let nano_resp = resp
-            .json()
-            .await?;
+        let nano_resp = serde_json::from_slice(&resp.body)?;
 
         match nano_resp {
             NanoResponse::Success(val) => Ok(val),
@@ -107,7 +339,93 @@ impl NanoClient {
                     NanoError::ErrorList { errors } => Err(Error::NanoErrors(errors))
                 }
             },
-            NanoResponse::Unknown(val) => panic!("Couldn't parse valid JSON as NanoResponse:\n{}", val)
+            NanoResponse::Unknown(_) => trace::parse_body(&resp.body, trace::path_kind(path))
+        }
+    }
+
+    /// Send a request through the middleware, and read the whole response
+    async fn execute(&self, req: RequestBuilder) -> Result<RawResponse, Error> {
+        let mut info = RequestInfo::from_request(&req.build()?);
+        for middleware in &self.middleware {
+            middleware.on_request(&mut info)?;
+        }
+
+        let intercepted = self.middleware
+            .iter()
+            .enumerate()
+            .find_map(|(idx, middleware)| middleware.intercept(&info).map(|resp| (idx, resp)));
+
+        let (mut resp, seen) = match intercepted {
+            Some((idx, resp)) => (resp?, idx),
+            None => (self.transport.send(&info).await?, self.middleware.len())
+        };
+
+        for middleware in self.middleware[..seen].iter().rev() {
+            middleware.on_response(&info, &mut resp)?;
+        }
+
+        Ok(resp)
+    }
+
+    /// Send a request, logging in again if Nano rejects the session, and retrying with backoff if
+    /// Nano is overloaded or can't be reached. Requests that aren't idempotent are only retried if
+    /// they can't have reached Nano
+    async fn retry<U, F, Fut>(&self, method: &Method, path: &str, request: F) -> Result<U, Error>
+        where
+            F: Fn() -> Fut,
+            Fut: Future<Output = Attempt<U>>
+    {
+        let mut trace = CallTrace::new(method, path);
+        let res = self.retry_traced(method.is_idempotent(), &mut trace, request).await;
+        trace.finish(&res);
+        res
+    }
+
+    async fn retry_traced<U, F, Fut>(&self, idempotent: bool, trace: &mut CallTrace, request: F) -> Result<U, Error>
+        where
+            F: Fn() -> Fut,
+            Fut: Future<Output = Attempt<U>>
+    {
+        let mut attempt = 0;
+        let mut retried_login = false;
+
+        loop {
+            let (status, res) = trace.within(request()).await;
+            trace.attempt(status);
+
+            let err = match &res {
+                Err(err) => err,
+                Ok(_) => return res
+            };
+
+            if let (Error::SimpleNanoError(StatusCode::UNAUTHORIZED, _), false) = (err, retried_login) {
+                if self.is_logged_in() {
+                    self.state.replace(SessionState::Expired);
+                    // The saved session has the same token, so it shouldn't be restored again
+                    if let Some(store) = &self.store {
+                        store.clear()?;
+                    }
+                }
+
+                // Guests have nothing to log in with, so the error is final for them
+                if !self.has_credentials() {
+                    return res;
+                }
+
+                trace.within(self.login()).await?;
+                trace.relogin();
+                retried_login = true;
+                continue;
+            }
+
+            match self.governor.retry_delay(err, attempt, idempotent) {
+                Some(delay) => {
+                    trace.retry(err, delay);
+                    attempt += 1;
+                    self.transport.delay(delay).await;
+                },
+                None => return res
+            }
         }
     }
 
@@ -116,40 +434,51 @@ impl NanoClient {
             T: Serialize + ?Sized,
             U: DeserializeOwned + std::fmt::Debug
     {
-        let res = self.make_request(path, method.clone(), data).await;
-
-        match res {
-            Err(Error::SimpleNanoError(code, _)) if code == StatusCode::UNAUTHORIZED && self.is_logged_in() => {
-                self.login().await?;
-                self.make_request(path, method, data).await
-            },
-            _ => res
-        }
+        self.retry(&method, path, || self.make_request(path, method.clone(), data)).await
     }
 
     /// Check whether this client is currently logged in
     pub fn is_logged_in(&self) -> bool {
-        self.token.borrow().is_none()
+        matches!(*self.state.borrow(), SessionState::Authenticated { .. })
+    }
+
+    /// Get the current log-in state of this client
+    pub fn session_state(&self) -> SessionState {
+        self.state.borrow().clone()
     }
 
-    /// Log in this client, without logging out
+    /// Log in this client, without logging out. Credentials are fetched from the client's
+    /// provider each time this is called
     pub async fn login(&self) -> Result<(), Error> {
+        let credentials = match &self.credentials {
+            Some(provider) => provider.credentials()?,
+            None => return Err(Error::CredentialError("Client has no credentials to log in with".to_string()))
+        };
+
         let mut map = HashMap::new();
-        map.insert("identifier", &self.username);
-        map.insert("password", &self.password);
+        map.insert("identifier", credentials.identifier.as_str());
+        map.insert("password", credentials.password.expose());
 
         let res = self.make_request::<_, LoginResponse>("users/sign_in", Method::POST, &map)
-            .await?;
+            .await
+            .1?;
 
-        self.token.replace(Some(res.auth_token));
+        if let Some(store) = &self.store {
+            store.save(&Session { auth_token: res.auth_token.clone() })?;
+        }
+        self.state.replace(SessionState::authenticated(res.auth_token));
 
         Ok(())
     }
 
     /// Log out this client, without checking if it's logged in
     pub async fn logout(&self) -> Result<(), Error> {
-        self.make_request::<_, ()>("users/logout", Method::POST, &()).await?;
-        self.token.replace(None);
+        self.make_request::<_, ()>("users/logout", Method::POST, &()).await.1?;
+        self.state.replace(SessionState::Anonymous);
+
+        if let Some(store) = &self.store {
+            store.clear()?;
+        }
 
         Ok(())
     }
@@ -159,18 +488,20 @@ impl NanoClient {
     pub async fn change_user(&mut self, user: Option<&str>, pass: Option<&str>) -> Result<(), Error> {
         if self.is_logged_in() {
             self.logout().await?;
+        } else {
+            self.state.replace(SessionState::Anonymous);
         }
 
-        if user.is_some() && pass.is_some() {
-            self.username = user.unwrap().to_string();
-            self.password = pass.unwrap().to_string();
-            self.login().await?;
-        } else if user.is_none() && pass.is_none() {
-            self.username = "".to_string();
-            self.password = "".to_string();
-            self.token.replace(None);
-        } else {
-            panic!("Either both user and pass must be provided, or neither")
+        match (user, pass) {
+            (Some(user), Some(pass)) => {
+                self.credentials = Some(Box::new(Credentials::new(user, pass)));
+                self.login().await?;
+            },
+            (None, None) => {
+                self.credentials = None;
+                self.state.replace(SessionState::Anonymous);
+            },
+            _ => panic!("Either both user and pass must be provided, or neither")
         }
 
         Ok(())
@@ -255,6 +586,409 @@ impl NanoClient {
         self.retry_request(&format!("project-challenges/{}/daily-aggregates", id), Method::GET, &()).await
     }
 
+    // Type modification
+
+    async fn create_typed<D, U>(&self, ty: NanoKind, data: &D) -> Result<ItemResponse<U>, Error>
+        where
+            D: Serialize,
+            U: ObjectInfo + DeserializeOwned + std::fmt::Debug
+    {
+        let body = WriteRequest {
+            data: WriteData { id: None, kind: ty, attributes: data }
+        };
+
+        self.retry_request(ty.api_name(), Method::POST, &body).await
+    }
+
+    async fn update_typed<D, U>(&self, ty: NanoKind, id: u64, data: &D) -> Result<ItemResponse<U>, Error>
+        where
+            D: Serialize,
+            U: ObjectInfo + DeserializeOwned + std::fmt::Debug
+    {
+        let body = WriteRequest {
+            data: WriteData { id: Some(id.to_string()), kind: ty, attributes: data }
+        };
+
+        self.retry_request(&format!("{}/{}", ty.api_name(), id), Method::PATCH, &body).await
+    }
+
+    /// Create a new item of a specific kind, with the given attributes. Returns the newly created
+    /// item, as Nano now sees it.
+    ///
+    /// **Warning**: Most kinds can't be created by normal users, and Nano doesn't document which
+    /// attributes are required. Prefer the typed methods such as [`Self::create_favorite_book`]
+    pub async fn create<D: Serialize>(&self, ty: NanoKind, data: &D) -> Result<ItemResponse, Error> {
+        self.create_typed(ty, data).await
+    }
+
+    /// Update an item of a specific kind and ID with the given attributes. Attributes not included
+    /// in `data` are left as they are.
+    pub async fn update<D: Serialize>(&self, ty: NanoKind, id: u64, data: &D) -> Result<ItemResponse, Error> {
+        self.update_typed(ty, id, data).await
+    }
+
+    /// Delete an item of a specific kind and ID
+    pub async fn delete(&self, ty: NanoKind, id: u64) -> Result<(), Error> {
+        self.retry_request(&format!("{}/{}", ty.api_name(), id), Method::DELETE, &()).await
+    }
+
+    /// Link an item to other items through one of its relationships, without touching any of the
+    /// links it already has
+    pub async fn add_related(&self, ty: NanoKind, id: u64, rel: NanoKind, items: &[ObjectRef]) -> Result<(), Error> {
+        let body = RelationRequest { data: items };
+
+        self.retry_request(
+            &format!("{}/{}/relationships/{}", ty.api_name(), id, rel.api_name()),
+            Method::POST,
+            &body
+        ).await
+    }
+
+    /// Unlink an item from other items in one of its relationships. The other items themselves
+    /// aren't deleted
+    pub async fn remove_related(&self, ty: NanoKind, id: u64, rel: NanoKind, items: &[ObjectRef]) -> Result<(), Error> {
+        let body = RelationRequest { data: items };
+
+        self.retry_request(
+            &format!("{}/{}/relationships/{}", ty.api_name(), id, rel.api_name()),
+            Method::DELETE,
+            &body
+        ).await
+    }
+
+    /// Attach a Genre to a Project, adding it to the genres the Project already has
+    pub async fn attach_genre(&self, project_id: u64, genre_id: u64) -> Result<(), Error> {
+        self.add_related(
+            NanoKind::Project,
+            project_id,
+            NanoKind::Genre,
+            &[ObjectRef { id: genre_id, kind: NanoKind::Genre }]
+        ).await
+    }
+
+    /// Detach a Genre from a Project. The Genre itself still exists afterwards
+    pub async fn detach_genre(&self, project_id: u64, genre_id: u64) -> Result<(), Error> {
+        self.remove_related(
+            NanoKind::Project,
+            project_id,
+            NanoKind::Genre,
+            &[ObjectRef { id: genre_id, kind: NanoKind::Genre }]
+        ).await
+    }
+
+    // Challenges
+
+    pub(crate) async fn get_typed<U>(&self, ty: NanoKind, id: u64) -> Result<ItemResponse<U>, Error>
+        where
+            U: ObjectInfo + DeserializeOwned + std::fmt::Debug
+    {
+        self.retry_request(&format!("{}/{}", ty.api_name(), id), Method::GET, &()).await
+    }
+
+    fn check_goal(goal: &Quantity) -> Result<(), Error> {
+        if goal.count() <= 0 {
+            return Err(Error::ValidationError("Challenge goal must be above zero".to_string()))
+        }
+        if !goal.is_whole() {
+            return Err(Error::ValidationError(format!("Nano only counts whole hours, not {}", goal)))
+        }
+        Ok(())
+    }
+
+    fn check_unit_type(project: &ProjectData, unit_type: UnitType) -> Result<(), Error> {
+        if project.unit_type != unit_type {
+            return Err(Error::ValidationError(format!(
+                "Challenge counts {:?}, but the project counts {:?}", unit_type, project.unit_type
+            )))
+        }
+        Ok(())
+    }
+
+    fn check_goal_unit(goal: &Quantity, unit_type: UnitType) -> Result<(), Error> {
+        if goal.unit_type() != unit_type {
+            return Err(Error::ValidationError(format!(
+                "Challenge counts {:?}, but the goal is {}", unit_type, goal
+            )))
+        }
+        Ok(())
+    }
+
+    /// Enroll a Project in a Challenge with a given goal, creating the ProjectChallenge that links
+    /// them. Fails without contacting Nano if the goal is zero or not a whole number of hours, and
+    /// without creating anything if the Challenge counts a different unit than the goal or Project
+    pub async fn enroll(&self, project_id: u64, challenge_id: u64, goal: Quantity) -> Result<ItemResponse<ProjectChallengeObject>, Error> {
+        NanoClient::check_goal(&goal)?;
+
+        let project = self.get_typed::<ProjectObject>(NanoKind::Project, project_id).await?.data;
+        let challenge = self.get_typed::<ChallengeObject>(NanoKind::Challenge, challenge_id).await?.data;
+
+        NanoClient::check_goal_unit(&goal, challenge.data.unit_type)?;
+        NanoClient::check_unit_type(&project.data, challenge.data.unit_type)?;
+
+        let data = ProjectChallengeData {
+            challenge_id,
+            current_count: 0,
+            ends_at: challenge.data.ends_at,
+            event_type: challenge.data.event_type.unwrap_or(EventType::Custom),
+            feeling: None,
+            goal: goal.count() as u64,
+            how: None,
+            last_recompute: None,
+            name: challenge.data.name,
+            project_id,
+            speed: None,
+            start_count: Some(0),
+            starts_at: challenge.data.starts_at,
+            streak: None,
+            unit_type: challenge.data.unit_type,
+            user_id: project.data.user_id,
+            when: None,
+            writing_location: None,
+            writing_type: Some(challenge.data.writing_type),
+        };
+
+        self.create_typed(NanoKind::ProjectChallenge, &data).await
+    }
+
+    /// Change the goal of a Project in a Challenge, given the ID of the ProjectChallenge linking
+    /// them. Fails as in [`Self::enroll`], without changing anything
+    pub async fn update_goal(&self, project_challenge_id: u64, goal: Quantity) -> Result<ItemResponse<ProjectChallengeObject>, Error> {
+        NanoClient::check_goal(&goal)?;
+
+        let pc = self.get_typed::<ProjectChallengeObject>(NanoKind::ProjectChallenge, project_challenge_id).await?.data;
+        NanoClient::check_goal_unit(&goal, pc.data.unit_type)?;
+
+        let mut data = HashMap::new();
+        data.insert("goal", goal.count() as u64);
+
+        self.update_typed(NanoKind::ProjectChallenge, project_challenge_id, &data).await
+    }
+
+    /// Remove a Project from a Challenge, given the ID of the ProjectChallenge linking them
+    pub async fn unenroll(&self, project_challenge_id: u64) -> Result<(), Error> {
+        self.delete(NanoKind::ProjectChallenge, project_challenge_id).await
+    }
+
+    /// Create a new custom Challenge for use with a given Project. Fails without contacting Nano
+    /// if the challenge doesn't end after it starts, or the goal is zero or not a whole number of
+    /// hours, and without creating anything if the goal's unit doesn't match the Project. The
+    /// Project isn't enrolled, use [`Self::enroll`] with the new Challenge
+    pub async fn create_custom_challenge(&self, project_id: u64, challenge: &CustomChallenge) -> Result<ItemResponse<ChallengeObject>, Error> {
+        if challenge.ends_at <= challenge.starts_at {
+            return Err(Error::ValidationError("Challenge must end after it starts".to_string()))
+        }
+        NanoClient::check_goal(&challenge.default_goal)?;
+
+        let project = self.get_typed::<ProjectObject>(NanoKind::Project, project_id).await?.data;
+
+        NanoClient::check_unit_type(&project.data, challenge.default_goal.unit_type())?;
+
+        let data = ChallengeData {
+            default_goal: challenge.default_goal.count() as u64,
+            ends_at: challenge.ends_at,
+            event_type: Some(EventType::Custom),
+            flexible_goal: None,
+            name: challenge.name.clone(),
+            prep_starts_at: None,
+            starts_at: challenge.starts_at,
+            unit_type: challenge.default_goal.unit_type(),
+            user_id: project.data.user_id,
+            win_allowed_at: None,
+            writing_type: challenge.writing_type,
+        };
+
+        self.create_typed(NanoKind::Challenge, &data).await
+    }
+
+    // Badges
+
+    pub(crate) async fn get_all_typed<U>(&self, ty: NanoKind, filter: &[(&str, u64)]) -> Result<CollectionResponse<U>, Error>
+        where
+            U: ObjectInfo + DeserializeOwned + std::fmt::Debug
+    {
+        let data = filter.iter()
+            .map(|(key, val)| (format!("filter[{}]", key), val.to_string()))
+            .collect::<Vec<_>>();
+
+        self.retry_request(ty.api_name(), Method::GET, &data).await
+    }
+
+    /// Get one page of a filtered collection. Pages are numbered from 1
+    pub(crate) async fn get_page_typed<U>(&self, ty: NanoKind, filter: &[(&str, u64)], page: u64, size: u64) -> Result<CollectionResponse<U>, Error>
+        where
+            U: ObjectInfo + DeserializeOwned + std::fmt::Debug
+    {
+        let mut data = filter.iter()
+            .map(|(key, val)| (format!("filter[{}]", key), val.to_string()))
+            .collect::<Vec<_>>();
+        data.push(("page[number]".to_string(), page.to_string()));
+        data.push(("page[size]".to_string(), size.to_string()));
+
+        self.retry_request(ty.api_name(), Method::GET, &data).await
+    }
+
+    fn check_self_awarded(badge: &BadgeObject) -> Result<(), Error> {
+        if badge.data.badge_type != BadgeType::SelfAwarded {
+            return Err(Error::ValidationError(format!(
+                "Badge '{}' is awarded by Nano, not self-awarded", badge.data.title
+            )))
+        }
+        Ok(())
+    }
+
+    /// Award a self-awarded Badge for a ProjectChallenge. Fails without modifying anything if the
+    /// Badge is a word count or participation badge, as Nano awards those itself
+    pub async fn award_badge(&self, project_challenge_id: u64, badge_id: u64) -> Result<ItemResponse<UserBadgeObject>, Error> {
+        let badge = self.get_typed::<BadgeObject>(NanoKind::Badge, badge_id).await?.data;
+        NanoClient::check_self_awarded(&badge)?;
+
+        let project_challenge = self.get_typed::<ProjectChallengeObject>(NanoKind::ProjectChallenge, project_challenge_id)
+            .await?
+            .data;
+
+        let data = UserBadgeData {
+            badge_id,
+            created_at: Utc::now(),
+            project_challenge_id,
+            user_id: project_challenge.data.user_id,
+        };
+
+        self.create_typed(NanoKind::UserBadge, &data).await
+    }
+
+    /// Revoke a self-awarded Badge, given the ID of the UserBadge that awarded it. Fails without
+    /// modifying anything if the Badge isn't self-awarded
+    pub async fn revoke_badge(&self, user_badge_id: u64) -> Result<(), Error> {
+        let user_badge = self.get_typed::<UserBadgeObject>(NanoKind::UserBadge, user_badge_id).await?.data;
+        let badge = self.get_typed::<BadgeObject>(NanoKind::Badge, user_badge.data.badge_id).await?.data;
+        NanoClient::check_self_awarded(&badge)?;
+
+        self.delete(NanoKind::UserBadge, user_badge_id).await
+    }
+
+    /// Get all the Badges that apply to a ProjectChallenge, split into those earned and those
+    /// not yet earned
+    pub async fn badges_for(&self, project_challenge_id: u64) -> Result<ChallengeBadges, Error> {
+        let badges = self.get_all_typed::<BadgeObject>(NanoKind::Badge, &[]).await?.data;
+        let mut user_badges = self.get_all_typed::<UserBadgeObject>(
+            NanoKind::UserBadge,
+            &[("project_challenge_id", project_challenge_id)]
+        ).await?.data;
+
+        let mut badges = badges.into_iter()
+            .filter(|badge| badge.data.adheres_to == AdheresTo::ProjectChallenge)
+            .collect::<Vec<_>>();
+        badges.sort_by_key(|badge| (badge.data.list_order, badge.data.suborder));
+
+        let mut out = ChallengeBadges { earned: Vec::new(), unearned: Vec::new() };
+        for badge in badges {
+            match user_badges.iter().position(|user_badge| user_badge.data.badge_id == badge.id()) {
+                Some(pos) => out.earned.push((badge, user_badges.swap_remove(pos))),
+                None => out.unearned.push(badge),
+            }
+        }
+
+        Ok(out)
+    }
+
+    // History
+
+    /// Get everything a User has taken part in, with each ProjectChallenge alongside its
+    /// Challenge and Project, grouped by Challenge. The totals are checked against the User's
+    /// profile stats, and any that don't match are listed in the history. Challenges, and any
+    /// Projects not in the User's list, are fetched a few at a time
+    pub async fn history(&self, user_id: u64) -> Result<WritingHistory, Error> {
+        let user = self.get_typed::<UserObject>(NanoKind::User, user_id).await?.data;
+        let filter = [("user_id", user_id)];
+        let project_challenges = self.get_all_typed::<ProjectChallengeObject>(NanoKind::ProjectChallenge, &filter).await?.data;
+        let mut projects = self.get_all_typed::<ProjectObject>(NanoKind::Project, &filter).await?.data
+            .into_iter()
+            .map(|project| (project.id(), Rc::new(project)))
+            .collect::<HashMap<_, _>>();
+        let project_count = projects.len() as u64;
+
+        let challenge_ids = project_challenges.iter()
+            .map(|pc| pc.data.challenge_id)
+            .collect::<HashSet<_>>();
+        let challenges = fetch_all(challenge_ids.into_iter().collect(), FETCH_CONCURRENCY, |id| async move {
+            let challenge = self.get_typed::<ChallengeObject>(NanoKind::Challenge, id).await?.data;
+            Ok((id, Rc::new(challenge)))
+        }).await?.into_iter().collect::<HashMap<_, _>>();
+
+        let missing_ids = project_challenges.iter()
+            .map(|pc| pc.data.project_id)
+            .filter(|id| !projects.contains_key(id))
+            .collect::<HashSet<_>>();
+        projects.extend(fetch_all(missing_ids.into_iter().collect(), FETCH_CONCURRENCY, |id| async move {
+            let project = self.get_typed::<ProjectObject>(NanoKind::Project, id).await?.data;
+            Ok((id, Rc::new(project)))
+        }).await?);
+
+        let entries = project_challenges.into_iter()
+            .map(|project_challenge| HistoryEntry {
+                challenge: Rc::clone(&challenges[&project_challenge.data.challenge_id]),
+                project: Rc::clone(&projects[&project_challenge.data.project_id]),
+                project_challenge,
+            })
+            .collect();
+
+        Ok(WritingHistory::new(user, entries, project_count))
+    }
+
+    // Images
+
+    /// The largest image, in bytes, that can be uploaded as an avatar or cover
+    pub const MAX_IMAGE_SIZE: usize = 5 * 1024 * 1024;
+
+    fn check_image<'a>(field: &'a str, data: &'a [u8], mime: &'a str) -> Result<ImageUpload<'a>, Error> {
+        let (extension, magic): (_, &[u8]) = match mime {
+            "image/png" => ("png", b"\x89PNG"),
+            "image/jpeg" => ("jpg", b"\xFF\xD8\xFF"),
+            "image/gif" => ("gif", b"GIF8"),
+            _ => return Err(Error::ValidationError(format!(
+                "Unsupported image type '{}', expected image/png, image/jpeg, or image/gif", mime
+            )))
+        };
+
+        if data.is_empty() || data.len() > NanoClient::MAX_IMAGE_SIZE {
+            return Err(Error::ValidationError(format!(
+                "Image is {} bytes, expected between 1 and {}", data.len(), NanoClient::MAX_IMAGE_SIZE
+            )))
+        }
+
+        if !data.starts_with(magic) {
+            return Err(Error::ValidationError(format!("Image contents aren't of type '{}'", mime)))
+        }
+
+        Ok(ImageUpload { field, data, mime, extension })
+    }
+
+    async fn upload_image<U>(&self, ty: NanoKind, id: u64, field: &str, data: &[u8], mime: &str) -> Result<ItemResponse<U>, Error>
+        where
+            U: ObjectInfo + DeserializeOwned + std::fmt::Debug
+    {
+        let upload = NanoClient::check_image(field, data, mime)?;
+        let path = format!("{}/{}/{}", ty.api_name(), id, field);
+
+        self.retry(&Method::POST, &path, || self.make_upload(&path, &upload)).await
+    }
+
+    /// Upload a new avatar image for a User. `mime` must be one of `image/png`, `image/jpeg`, or
+    /// `image/gif`, matching the data, and the image must be at most [`Self::MAX_IMAGE_SIZE`] bytes
+    pub async fn upload_avatar(&self, user_id: u64, data: &[u8], mime: &str) -> Result<ItemResponse<UserObject>, Error> {
+        self.upload_image(NanoKind::User, user_id, "avatar", data, mime).await
+    }
+
+    /// Upload a new cover image for a Project. Images are checked as in [`Self::upload_avatar`]
+    pub async fn upload_project_cover(&self, project_id: u64, data: &[u8], mime: &str) -> Result<ItemResponse<ProjectObject>, Error> {
+        self.upload_image(NanoKind::Project, project_id, "cover", data, mime).await
+    }
+
+    /// Upload a new avatar image for a Group. Images are checked as in [`Self::upload_avatar`]
+    pub async fn upload_group_avatar(&self, group_id: u64, data: &[u8], mime: &str) -> Result<ItemResponse<GroupObject>, Error> {
+        self.upload_image(NanoKind::Group, group_id, "avatar", data, mime).await
+    }
+
     // Type queries
 
     /// Get all accessible items of a specific kind, with included linked items and filtering to
@@ -355,3 +1089,39 @@ impl NanoClient {
         self.retry_request(&rel.related, Method::GET, &()).await
     }
 }
+
+macro_rules! write_methods {
+    ($( $name:ident )+) => {
+        paste! {
+            impl NanoClient {
+                $(
+
+                #[doc = "Create a new " $name " from the given data"]
+                pub async fn [<create_ $name:snake>](&self, data: &[<$name Data>]) -> Result<ItemResponse<[<$name Object>]>, Error> {
+                    self.create_typed(NanoKind::$name, data).await
+                }
+
+                #[doc = "Replace every attribute of the " $name " with a given ID with the given data. To"]
+                #[doc = "change only some attributes, use [`Self::update`] with just those"]
+                pub async fn [<update_ $name:snake>](&self, id: u64, data: &[<$name Data>]) -> Result<ItemResponse<[<$name Object>]>, Error> {
+                    self.update_typed(NanoKind::$name, id, data).await
+                }
+
+                #[doc = "Delete the " $name " with a given ID"]
+                pub async fn [<delete_ $name:snake>](&self, id: u64) -> Result<(), Error> {
+                    self.delete(NanoKind::$name, id).await
+                }
+
+                )+
+            }
+        }
+    }
+}
+
+write_methods!(
+    FavoriteAuthor
+    FavoriteBook
+    Genre
+    WritingLocation
+    WritingMethod
+);
@@ -0,0 +1,147 @@
+use super::NanoClient;
+use super::governor::{Backoff, Governor, RateLimit};
+use crate::error::Error;
+use crate::session::{SessionState, SessionStore};
+use crate::credentials::{CredentialProvider, Credentials};
+use crate::middleware::Middleware;
+use crate::transport::{HttpTransport, ReqwestTransport};
+
+use std::cell::RefCell;
+
+use reqwest::Client;
+
+/// A builder for a [`NanoClient`], for configuration beyond what the simple constructors allow.
+/// Without any configuration, it builds the same client as [`NanoClient::new_anon`]
+#[derive(Debug)]
+pub struct NanoClientBuilder {
+    base_url: String,
+    credentials: Option<Box<dyn CredentialProvider>>,
+    token: Option<String>,
+    store: Option<Box<dyn SessionStore>>,
+    rate_limit: Option<RateLimit>,
+    backoff: Backoff,
+    middleware: Vec<Box<dyn Middleware>>,
+    transport: Option<Box<dyn HttpTransport>>,
+}
+
+impl NanoClientBuilder {
+    /// Create a new builder, with the default configuration
+    pub fn new() -> NanoClientBuilder {
+        NanoClientBuilder {
+            base_url: NanoClient::BASE_URL.to_string(),
+            credentials: None,
+            token: None,
+            store: None,
+            rate_limit: None,
+            backoff: Backoff::default(),
+            middleware: Vec::new(),
+            transport: None,
+        }
+    }
+
+    /// Send requests to a different URL than the real Nano API, such as a proxy or a
+    /// [`MockServer`](crate::mock::MockServer). Should end with a `/`
+    pub fn base_url(mut self, url: &str) -> NanoClientBuilder {
+        self.base_url = url.to_string();
+        self
+    }
+
+    /// Log in with a username or email and password
+    pub fn user(self, user: &str, pass: &str) -> NanoClientBuilder {
+        self.credentials(Credentials::new(user, pass))
+    }
+
+    /// Log in with credentials from a provider. See [`NanoClient::with_credentials`]
+    pub fn credentials<P: CredentialProvider + 'static>(mut self, provider: P) -> NanoClientBuilder {
+        self.credentials = Some(Box::new(provider));
+        self
+    }
+
+    /// Start authenticated by the token of an existing session. See [`NanoClient::with_token`]
+    pub fn token(mut self, token: &str) -> NanoClientBuilder {
+        self.token = Some(token.to_string());
+        self
+    }
+
+    /// Restore and save the session using a store. A session in the store takes priority over
+    /// [`Self::token`]. See [`NanoClient::with_store`]
+    pub fn session_store<S: SessionStore + 'static>(mut self, store: S) -> NanoClientBuilder {
+        self.store = Some(Box::new(store));
+        self
+    }
+
+    /// Limit how often this client sends requests. Requests over the limit wait until they're
+    /// allowed. By default, there's no limit, except any set with
+    /// [`set_global_rate_limit`](super::set_global_rate_limit)
+    pub fn rate_limit(mut self, limit: RateLimit) -> NanoClientBuilder {
+        self.rate_limit = Some(limit);
+        self
+    }
+
+    /// Set how failed requests are retried. By default, they're retried up to 3 times
+    pub fn backoff(mut self, backoff: Backoff) -> NanoClientBuilder {
+        self.backoff = backoff;
+        self
+    }
+
+    /// Add a middleware, which sees every request after those already added, and every response
+    /// before them. See [`crate::middleware`]
+    pub fn middleware<M: Middleware + 'static>(mut self, middleware: M) -> NanoClientBuilder {
+        self.middleware.push(Box::new(middleware));
+        self
+    }
+
+    /// Send requests, and wait between them, with a different transport. By default, requests
+    /// are sent with reqwest. See [`crate::transport`]
+    pub fn transport<T: HttpTransport + 'static>(mut self, transport: T) -> NanoClientBuilder {
+        self.transport = Some(Box::new(transport));
+        self
+    }
+
+    /// Build the client. Fails if the rate limit allows no requests, or the session store can't
+    /// be read
+    pub fn build(self) -> Result<NanoClient, Error> {
+        if let Some(limit) = &self.rate_limit {
+            limit.check()?;
+        }
+
+        let client = Client::new();
+        let transport = self.transport
+            .unwrap_or_else(|| Box::new(ReqwestTransport::from_client(client.clone())));
+
+        let state = match self.token {
+            Some(token) => SessionState::authenticated(token),
+            None => SessionState::Anonymous
+        };
+
+        let mut client = NanoClient {
+            client,
+            base_url: self.base_url,
+            credentials: self.credentials,
+            state: RefCell::new(state),
+            store: None,
+            governor: Governor::new(self.rate_limit, self.backoff),
+            middleware: self.middleware,
+            transport,
+        };
+
+        if let Some(store) = self.store {
+            client.set_store(store)?;
+        }
+
+        Ok(client)
+    }
+
+    /// Build a [blocking client](crate::blocking::NanoClient). Fails if the rate limit allows no
+    /// requests, the session store can't be read, or the client's runtime can't be started
+    #[cfg(feature = "blocking")]
+    pub fn build_blocking(self) -> Result<crate::blocking::NanoClient, Error> {
+        crate::blocking::NanoClient::from_async(self.build()?)
+    }
+}
+
+impl Default for NanoClientBuilder {
+    fn default() -> NanoClientBuilder {
+        NanoClientBuilder::new()
+    }
+}
@@ -0,0 +1,219 @@
+use crate::error::Error;
+use crate::transport::HttpTransport;
+
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use reqwest::StatusCode;
+
+/// A limit on how often requests can be sent, as a number of requests per period. Short bursts of
+/// up to `requests` are allowed, after which requests are spaced out evenly over the period
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct RateLimit {
+    /// The number of requests allowed in each period
+    pub requests: u32,
+    /// The length of the period
+    pub per: Duration,
+}
+
+impl RateLimit {
+    /// Create a new limit of a number of requests per period. Fails if either is zero, since
+    /// such a limit would allow no requests, or any number of them
+    pub fn new(requests: u32, per: Duration) -> Result<RateLimit, Error> {
+        let limit = RateLimit { requests, per };
+        limit.check()?;
+        Ok(limit)
+    }
+
+    /// The fields of a limit are public, so it may not have gone through `RateLimit::new`
+    pub(crate) fn check(&self) -> Result<(), Error> {
+        if self.requests == 0 || self.per.as_nanos() == 0 {
+            return Err(Error::ValidationError(format!(
+                "A rate limit needs at least one request per period, not {} per {:?}", self.requests, self.per
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// How failed requests are retried. Requests are retried after Nano reports it's overloaded
+/// (429 or 5xx responses) or the connection fails, waiting longer after each failure. If Nano
+/// sends a `Retry-After` header, that wait is used instead, unless it's longer than `max_delay`,
+/// in which case the request fails with [`Error::Throttled`] rather than stalling the client.
+///
+/// Requests that create or change things are only retried if they can't have reached Nano,
+/// that is after 429 or 503 responses or connection failures
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Backoff {
+    /// The most times a single request is retried. Zero disables retrying
+    pub max_retries: u32,
+    /// The longest wait before the first retry. Each retry doubles this, up to `max_delay`, and
+    /// the actual wait is a random duration up to that
+    pub base_delay: Duration,
+    /// The longest wait before any retry. A longer `Retry-After` isn't waited for
+    pub max_delay: Duration,
+}
+
+impl Backoff {
+    /// Never retry failed requests
+    pub fn none() -> Backoff {
+        Backoff { max_retries: 0, ..Backoff::default() }
+    }
+
+    pub(crate) fn delay(&self, attempt: u32) -> Duration {
+        let max = self.base_delay
+            .checked_mul(1 << attempt.min(16))
+            .unwrap_or(self.max_delay)
+            .min(self.max_delay);
+
+        // Full jitter, so many clients failing at once don't all retry at once
+        let random = RandomState::new().build_hasher().finish();
+        max.mul_f64(random as f64 / u64::MAX as f64)
+    }
+}
+
+impl Default for Backoff {
+    fn default() -> Backoff {
+        Backoff {
+            max_retries: 3,
+            base_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(30),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct TokenBucket {
+    limit: RateLimit,
+    tokens: f64,
+    last: Instant,
+}
+
+impl TokenBucket {
+    pub(crate) fn new(limit: RateLimit) -> TokenBucket {
+        TokenBucket { limit, tokens: limit.requests as f64, last: Instant::now() }
+    }
+
+    fn refill(&mut self, now: Instant) {
+        let rate = self.limit.requests as f64 / self.limit.per.as_secs_f64();
+        let elapsed = now.saturating_duration_since(self.last).as_secs_f64();
+
+        self.tokens = (self.tokens + elapsed * rate).min(self.limit.requests as f64);
+        self.last = now;
+    }
+
+    /// How long until a request can be sent, or zero if one can be sent right now
+    pub(crate) fn wait(&mut self, now: Instant) -> Duration {
+        self.refill(now);
+
+        if self.tokens >= 1.0 {
+            Duration::from_secs(0)
+        } else {
+            self.limit.per.mul_f64((1.0 - self.tokens) / self.limit.requests as f64)
+        }
+    }
+
+    /// Use up a request. Should only be called right after `wait` returns zero
+    pub(crate) fn take(&mut self) {
+        self.tokens -= 1.0;
+    }
+}
+
+static GLOBAL_LIMIT: Mutex<Option<TokenBucket>> = Mutex::new(None);
+
+/// Set a limit shared by every client in this process, on top of each client's own limit.
+/// `None` removes the limit. Fails, leaving any existing limit in place, if the limit allows no
+/// requests
+pub fn set_global_rate_limit(limit: Option<RateLimit>) -> Result<(), Error> {
+    if let Some(limit) = &limit {
+        limit.check()?;
+    }
+    *GLOBAL_LIMIT.lock().unwrap() = limit.map(TokenBucket::new);
+    Ok(())
+}
+
+/// Rate limiting and retry state for a single client
+#[derive(Debug)]
+pub(crate) struct Governor {
+    bucket: Option<Mutex<TokenBucket>>,
+    pub(crate) backoff: Backoff,
+}
+
+impl Governor {
+    pub(crate) fn new(limit: Option<RateLimit>, backoff: Backoff) -> Governor {
+        Governor { bucket: limit.map(|limit| Mutex::new(TokenBucket::new(limit))), backoff }
+    }
+
+    /// Use up a request if both this client's limit and the global limit allow one, otherwise
+    /// return how long until they might
+    fn try_take(&self) -> Duration {
+        let now = Instant::now();
+        let mut global = GLOBAL_LIMIT.lock().unwrap();
+        let mut local = self.bucket.as_ref().map(|bucket| bucket.lock().unwrap());
+
+        let wait = global.as_mut().map_or(Duration::from_secs(0), |bucket| bucket.wait(now))
+            .max(local.as_mut().map_or(Duration::from_secs(0), |bucket| bucket.wait(now)));
+
+        if wait == Duration::from_secs(0) {
+            if let Some(bucket) = global.as_mut() {
+                bucket.take();
+            }
+            if let Some(bucket) = local.as_mut() {
+                bucket.take();
+            }
+        }
+
+        wait
+    }
+
+    /// Wait until both this client's limit and the global limit allow another request
+    pub(crate) async fn acquire(&self, transport: &dyn HttpTransport) {
+        loop {
+            let wait = self.try_take();
+
+            if wait == Duration::from_secs(0) {
+                return;
+            }
+
+            transport.delay(wait).await;
+        }
+    }
+
+    /// How long to wait before retrying a failed request, or None if it shouldn't be retried
+    pub(crate) fn retry_delay(&self, err: &Error, attempt: u32, idempotent: bool) -> Option<Duration> {
+        if attempt >= self.backoff.max_retries {
+            return None;
+        }
+
+        match err {
+            Error::Throttled(_, Some(retry_after)) => Some(*retry_after).filter(|wait| *wait <= self.backoff.max_delay),
+            Error::Throttled(_, None) => Some(self.backoff.delay(attempt)),
+            Error::SimpleNanoError(status, _) if status.is_server_error() && idempotent => {
+                Some(self.backoff.delay(attempt))
+            },
+            Error::ReqwestError(err) if err.is_connect() || (idempotent && err.is_timeout()) => {
+                Some(self.backoff.delay(attempt))
+            },
+            _ => None
+        }
+    }
+}
+
+/// Whether a response status means Nano wants the client to slow down
+pub(crate) fn is_throttled(status: StatusCode) -> bool {
+    status == StatusCode::TOO_MANY_REQUESTS || status == StatusCode::SERVICE_UNAVAILABLE
+}
+
+/// Parse a `Retry-After` header, which is either a number of seconds or an HTTP date
+pub(crate) fn parse_retry_after(val: &str) -> Option<Duration> {
+    if let Ok(secs) = val.trim().parse() {
+        return Some(Duration::from_secs(secs));
+    }
+
+    let date = chrono::DateTime::parse_from_rfc2822(val.trim()).ok()?;
+    (date.with_timezone(&chrono::Utc) - chrono::Utc::now())
+        .to_std()
+        .ok()
+}
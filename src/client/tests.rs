@@ -84,25 +84,1291 @@ async fn test_get_id() {
     assert_eq!(badge.data.kind(), NanoKind::Badge, "get_id with Badge kind didn't return a badge")
 }
 
-/*
-TODO: Make tests for more stuff. Some examples used while writing all this:
+async fn mock_client() -> (crate::mock::MockServer, NanoClient) {
+    let server = crate::mock::MockServer::new();
+    server.add_user("writer", "hunter2");
+    let client = server.user_client("writer", "hunter2")
+        .await
+        .expect("Couldn't log in to the mock server");
+    (server, client)
+}
+
+#[tokio::test]
+async fn test_favorite_book_crud() {
+    let (server, client) = mock_client().await;
+
+    let book = client.create_favorite_book(&FavoriteBookData { title: "Dune".to_string(), user_id: 1 })
+        .await
+        .expect("Couldn't create a favorite book");
+    assert_eq!(book.data.data.title, "Dune");
+
+    let id = book.data.id();
+    let book = client.update_favorite_book(id, &FavoriteBookData { title: "Emma".to_string(), user_id: 1 })
+        .await
+        .expect("Couldn't update a favorite book");
+    assert_eq!(book.data.data.title, "Emma");
+    assert_eq!(server.get(NanoKind::FavoriteBook, id).unwrap()["title"], "Emma");
+
+    client.delete_favorite_book(id)
+        .await
+        .expect("Couldn't delete a favorite book");
+    assert!(server.get(NanoKind::FavoriteBook, id).is_none());
+}
+
+#[tokio::test]
+async fn test_writing_method_crud() {
+    let (server, client) = mock_client().await;
+
+    let method = client.create_writing_method(&WritingMethodData { name: "Dictation".to_string() })
+        .await
+        .expect("Couldn't create a writing method");
+    let id = method.data.id();
+
+    let fetched = client.get_id(NanoKind::WritingMethod, id)
+        .await
+        .expect("Couldn't get a created writing method");
+    assert_eq!(fetched.data.unwrap_writing_method().data.name, "Dictation");
+
+    client.delete_writing_method(id)
+        .await
+        .expect("Couldn't delete a writing method");
+    assert!(server.get(NanoKind::WritingMethod, id).is_none());
+}
+
+#[tokio::test]
+async fn test_attach_genre() {
+    let (server, client) = mock_client().await;
+    let project = server.insert(NanoKind::Project, serde_json::json!({ "title": "Untitled" }));
+    let genre = client.create_genre(&GenreData { name: "Fantasy".to_string(), user_id: 1 })
+        .await
+        .expect("Couldn't create a genre")
+        .data
+        .id();
+
+    client.attach_genre(project, genre)
+        .await
+        .expect("Couldn't attach a genre to a project");
+    assert_eq!(server.related(NanoKind::Project, project, NanoKind::Genre), vec![genre]);
+
+    client.detach_genre(project, genre)
+        .await
+        .expect("Couldn't detach a genre from a project");
+    assert!(server.related(NanoKind::Project, project, NanoKind::Genre).is_empty());
+    assert!(server.get(NanoKind::Genre, genre).is_some());
+}
+
+#[tokio::test]
+async fn test_write_needs_login() {
+    let server = crate::mock::MockServer::start().await;
+    let client = server.client();
+
+    let res = client.create_favorite_author(&FavoriteAuthorData { name: "Le Guin".to_string(), user_id: 1 }).await;
+    assert!(matches!(res, Err(Error::SimpleNanoError(StatusCode::UNAUTHORIZED, _))));
+}
+
+fn mock_project(user_id: u64, unit_type: u8) -> serde_json::Value {
+    serde_json::json!({
+        "created-at": "2020-10-01T00:00:00Z",
+        "privacy": 2,
+        "slug": "my-novel",
+        "status": "In Progress",
+        "title": "My Novel",
+        "unit-type": unit_type,
+        "user-id": user_id,
+        "writing-type": 0
+    })
+}
+
+fn mock_challenge(unit_type: u8) -> serde_json::Value {
+    serde_json::json!({
+        "default-goal": 50000,
+        "ends-at": "2020-11-30",
+        "event-type": 0,
+        "name": "NaNoWriMo 2020",
+        "starts-at": "2020-11-01",
+        "unit-type": unit_type,
+        "user-id": 1,
+        "writing-type": 0
+    })
+}
+
+#[tokio::test]
+async fn test_enroll() {
+    let (server, client) = mock_client().await;
+    let project = server.insert(NanoKind::Project, mock_project(1, 0));
+    let challenge = server.insert(NanoKind::Challenge, mock_challenge(0));
+
+    let pc = client.enroll(project, challenge, Quantity::words(50000))
+        .await
+        .expect("Couldn't enroll a project in a challenge");
+    assert_eq!(pc.data.data.project_id, project);
+    assert_eq!(pc.data.data.challenge_id, challenge);
+    assert_eq!(pc.data.data.name, "NaNoWriMo 2020");
+
+    let pc = client.update_goal(pc.data.id(), Quantity::words(30000))
+        .await
+        .expect("Couldn't update a challenge goal");
+    assert_eq!(pc.data.data.goal, 30000);
+    assert_eq!(pc.data.data.goal_quantity().to_string(), "30,000 words");
+    let res = client.update_goal(pc.data.id(), Quantity::hours(30)).await;
+    assert!(matches!(res, Err(Error::ValidationError(_))));
+
+    client.unenroll(pc.data.id())
+        .await
+        .expect("Couldn't unenroll a project from a challenge");
+    assert!(server.get(NanoKind::ProjectChallenge, pc.data.id()).is_none());
+}
+
+#[tokio::test]
+async fn test_enroll_validation() {
+    let (server, client) = mock_client().await;
+    let project = server.insert(NanoKind::Project, mock_project(1, 0));
+    let hours = server.insert(NanoKind::Challenge, mock_challenge(1));
+
+    let words = server.insert(NanoKind::Challenge, mock_challenge(0));
+
+    assert!(matches!(client.enroll(project, hours, Quantity::hours(0)).await, Err(Error::ValidationError(_))));
+    assert!(matches!(client.enroll(project, hours, Quantity::hours(30)).await, Err(Error::ValidationError(_))));
+    assert!(matches!(client.enroll(project, words, Quantity::hours(30)).await, Err(Error::ValidationError(_))));
+    let partial = Quantity::Hours(chrono::Duration::minutes(90));
+    assert!(matches!(client.enroll(project, hours, partial).await, Err(Error::ValidationError(_))));
+    assert!(matches!(client.update_goal(1, Quantity::words(0)).await, Err(Error::ValidationError(_))));
+    let writes = server.requests()
+        .into_iter()
+        .filter(|(method, path)| *method != Method::GET && path != "users/sign_in")
+        .count();
+    assert_eq!(writes, 0);
+}
+
+#[tokio::test]
+async fn test_create_custom_challenge() {
+    let (server, client) = mock_client().await;
+    let project = server.insert(NanoKind::Project, mock_project(1, 0));
+
+    let mut custom = CustomChallenge {
+        name: "Spring Sprint".to_string(),
+        starts_at: chrono::NaiveDate::from_ymd_opt(2021, 4, 1).unwrap(),
+        ends_at: chrono::NaiveDate::from_ymd_opt(2021, 4, 30).unwrap(),
+        default_goal: Quantity::words(20000),
+        writing_type: crate::WritingType::Novel,
+    };
+
+    let challenge = client.create_custom_challenge(project, &custom)
+        .await
+        .expect("Couldn't create a custom challenge");
+    assert_eq!(challenge.data.data.event_type, Some(crate::EventType::Custom));
+    assert_eq!(challenge.data.data.user_id, 1);
+    assert_eq!(challenge.data.data.unit_type, crate::UnitType::Words);
+
+    custom.ends_at = custom.starts_at;
+    assert!(matches!(client.create_custom_challenge(project, &custom).await, Err(Error::ValidationError(_))));
+
+    custom.ends_at = chrono::NaiveDate::from_ymd_opt(2021, 4, 30).unwrap();
+    custom.default_goal = Quantity::words(0);
+    assert!(matches!(client.create_custom_challenge(project, &custom).await, Err(Error::ValidationError(_))));
+
+    custom.default_goal = Quantity::hours(20);
+    assert!(matches!(client.create_custom_challenge(project, &custom).await, Err(Error::ValidationError(_))));
+}
+
+fn mock_badge(badge_type: &str, list_order: u64) -> serde_json::Value {
+    serde_json::json!({
+        "active": true,
+        "adheres-to": "project_challenge",
+        "awarded": "You did it!",
+        "awarded-description": "Awarded for trying",
+        "badge-type": badge_type,
+        "description": "A badge",
+        "generic-description": "A badge",
+        "list-order": list_order,
+        "suborder": null,
+        "title": format!("Badge {}", list_order),
+        "unawarded": "Not yet",
+        "winner": false
+    })
+}
+
+fn mock_project_challenge(project_id: u64, challenge_id: u64) -> serde_json::Value {
+    serde_json::json!({
+        "challenge-id": challenge_id,
+        "current-count": 0,
+        "ends-at": "2020-11-30",
+        "event-type": 0,
+        "goal": 50000,
+        "name": "NaNoWriMo 2020",
+        "project-id": project_id,
+        "starts-at": "2020-11-01",
+        "unit-type": 0,
+        "user-id": 1
+    })
+}
+
+#[tokio::test]
+async fn test_self_awarded_badges() {
+    let (server, client) = mock_client().await;
+    let pc = server.insert(NanoKind::ProjectChallenge, mock_project_challenge(10, 20));
+    let self_awarded = server.insert(NanoKind::Badge, mock_badge("self-awarded", 2));
+    let word_count = server.insert(NanoKind::Badge, mock_badge("word count", 1));
+
+    let awarded = client.award_badge(pc, self_awarded)
+        .await
+        .expect("Couldn't award a self-awarded badge");
+    assert_eq!(awarded.data.data.badge_id, self_awarded);
+    assert!(matches!(client.award_badge(pc, word_count).await, Err(Error::ValidationError(_))));
+
+    let badges = client.badges_for(pc)
+        .await
+        .expect("Couldn't get the badges for a project challenge");
+    assert_eq!(badges.earned.len(), 1);
+    assert_eq!(badges.earned[0].0.id(), self_awarded);
+    assert_eq!(badges.unearned.iter().map(|badge| badge.id()).collect::<Vec<_>>(), vec![word_count]);
+
+    client.revoke_badge(awarded.data.id())
+        .await
+        .expect("Couldn't revoke a self-awarded badge");
+    assert!(server.get(NanoKind::UserBadge, awarded.data.id()).is_none());
+
+    let nano_awarded = server.insert(NanoKind::UserBadge, serde_json::json!({
+        "badge-id": word_count,
+        "created-at": "2020-11-02T00:00:00Z",
+        "project-challenge-id": pc,
+        "user-id": 1
+    }));
+    assert!(matches!(client.revoke_badge(nano_awarded).await, Err(Error::ValidationError(_))));
+}
+
+fn mock_history_challenge(server: &crate::mock::MockServer, project_id: u64, event_type: u8, starts_at: &str, count: u64) -> u64 {
+    let mut challenge = mock_challenge(0);
+    challenge["event-type"] = event_type.into();
+    challenge["starts-at"] = starts_at.into();
+    let challenge_id = server.insert(NanoKind::Challenge, challenge);
+
+    let mut pc = mock_project_challenge(project_id, challenge_id);
+    pc["event-type"] = event_type.into();
+    pc["starts-at"] = starts_at.into();
+    pc["current-count"] = count.into();
+    server.insert(NanoKind::ProjectChallenge, pc);
+    challenge_id
+}
+
+#[tokio::test]
+async fn test_history() {
+    let (server, client) = mock_client().await;
+    let first = server.insert(NanoKind::Project, mock_project(1, 0));
+    let second = server.insert(NanoKind::Project, mock_project(1, 0));
+    server.insert(NanoKind::Project, mock_project(2, 0));
+
+    mock_history_challenge(&server, first, 0, "2020-11-01", 52000);
+    mock_history_challenge(&server, first, 0, "2019-11-01", 30000);
+    mock_history_challenge(&server, second, 1, "2020-04-01", 50000);
+    // A second Camp in the same year is a separate event
+    mock_history_challenge(&server, second, 1, "2020-07-01", 8000);
+    let other = server.insert(NanoKind::Project, mock_project(2, 0));
+    let mut pc = mock_project_challenge(other, 1);
+    pc["user-id"] = 2.into();
+    server.insert(NanoKind::ProjectChallenge, pc);
+
+    server.update(NanoKind::User, 1, serde_json::json!({
+        "stats-projects": 2,
+        "stats-word-count": 128000,
+        "stats-wordiest": 52000,
+        "stats-years-done": 2,
+        "stats-years-won": 2
+    }));
+
+    let history = client.history(1)
+        .await
+        .expect("Couldn't get a user's history");
+
+    let events = history.events.iter()
+        .map(|event| (event.event_type, event.year, event.won(), event.word_count()))
+        .collect::<Vec<_>>();
+    assert_eq!(events, vec![
+        (crate::EventType::NanoWrimo, 2019, false, 30000),
+        (crate::EventType::CampNano, 2020, true, 50000),
+        (crate::EventType::CampNano, 2020, false, 8000),
+        (crate::EventType::NanoWrimo, 2020, true, 52000),
+    ]);
+    assert_eq!(history.entries().count(), 4);
+    assert_eq!(history.events_of(crate::EventType::NanoWrimo).count(), 2);
+    assert_eq!(history.word_count(), 140000);
+
+    let entry = &history.events[0].entries[0];
+    assert_eq!((entry.project.id(), entry.final_count(), entry.won()), (first, 30000, false));
+    assert_eq!(entry.challenge.data.starts_at, chrono::NaiveDate::from_ymd_opt(2019, 11, 1).unwrap());
+
+    let discrepancies = history.discrepancies.iter()
+        .map(|found| (found.stat, found.reported, found.computed))
+        .collect::<Vec<_>>();
+    assert_eq!(discrepancies, vec![("stats-word-count", 128000, 140000), ("stats-years-won", 2, 1)]);
+}
+
+const PNG: &[u8] = b"\x89PNG\r\n\x1a\nnot really a png";
+
+#[tokio::test]
+async fn test_upload_images() {
+    let (server, client) = mock_client().await;
+    let project = server.insert(NanoKind::Project, mock_project(1, 0));
+    let group = server.insert(NanoKind::Group, serde_json::json!({
+        "approved-by-id": 0,
+        "cancelled-by-id": 0,
+        "created-at": "2020-01-01T00:00:00Z",
+        "group-type": "region",
+        "name": "Somewhere",
+        "slug": "somewhere",
+        "updated-at": "2020-01-01T00:00:00Z"
+    }));
+
+    let user = client.upload_avatar(1, PNG, "image/png")
+        .await
+        .expect("Couldn't upload a user avatar");
+    assert!(user.data.data.avatar.is_some());
+
+    let project = client.upload_project_cover(project, PNG, "image/png")
+        .await
+        .expect("Couldn't upload a project cover");
+    assert!(project.data.data.cover.is_some());
+
+    let group = client.upload_group_avatar(group, PNG, "image/png")
+        .await
+        .expect("Couldn't upload a group avatar");
+    assert!(group.data.data.avatar.is_some());
+
+    // Uploads are encoded the same way when sent over the network
+    let server = crate::mock::MockServer::start().await;
+    server.add_user("writer", "hunter2");
+    let client = server.user_client("writer", "hunter2").await.unwrap();
+    let user = client.upload_avatar(1, PNG, "image/png")
+        .await
+        .expect("Couldn't upload a user avatar over the network");
+    assert!(user.data.data.avatar.is_some());
+}
+
+#[tokio::test]
+async fn test_upload_validation() {
+    let (_server, client) = mock_client().await;
+
+    assert!(matches!(client.upload_avatar(1, PNG, "image/bmp").await, Err(Error::ValidationError(_))));
+    assert!(matches!(client.upload_avatar(1, PNG, "image/jpeg").await, Err(Error::ValidationError(_))));
+    assert!(matches!(client.upload_avatar(1, &[], "image/png").await, Err(Error::ValidationError(_))));
+
+    let huge = [PNG, &vec![0; NanoClient::MAX_IMAGE_SIZE]].concat();
+    assert!(matches!(client.upload_avatar(1, &huge, "image/png").await, Err(Error::ValidationError(_))));
+}
+
+#[tokio::test]
+async fn test_with_token() {
+    let (server, client) = mock_client().await;
+    let session = client.session().expect("Logged in client had no session");
+
+    let mut restored = NanoClient::with_token(&session.auth_token);
+    restored.base_url = server.url();
+    restored.transport = Box::new(server.transport());
+
+    let user = restored.current_user()
+        .await
+        .expect("Couldn't get current user with a restored token");
+    assert_eq!(user.data.data.name, "writer");
+}
+
+#[tokio::test]
+async fn test_session_store() {
+    let server = crate::mock::MockServer::start().await;
+    server.add_user("writer", "hunter2");
+    let store = crate::session::MemorySessionStore::new();
+
+    let mut client = NanoClient::new_url(&server.url(), "writer", "hunter2");
+    client.set_store(Box::new(store.clone())).unwrap();
+    client.login().await.expect("Couldn't log in");
+    assert_eq!(store.load().unwrap(), client.session());
+
+    // The restored session is used as-is, so the wrong password is never sent
+    let mut restored = NanoClient::new_url(&server.url(), "writer", "wrong");
+    restored.set_store(Box::new(store.clone())).unwrap();
+    restored.current_user()
+        .await
+        .expect("Couldn't get current user with a restored session");
+
+    restored.logout().await.expect("Couldn't log out");
+    assert_eq!(store.load().unwrap(), None);
+}
+
+#[tokio::test]
+async fn test_expired_session() {
+    let server = crate::mock::MockServer::start().await;
+    server.add_user("writer", "hunter2");
+    let store = crate::session::MemorySessionStore::new();
+    let stale = Session { auth_token: "stale".to_string() };
+    store.save(&stale).unwrap();
+
+    let mut client = NanoClient::new_url(&server.url(), "writer", "hunter2");
+    client.set_store(Box::new(store.clone())).unwrap();
+    client.current_user()
+        .await
+        .expect("Client didn't log in again after its session was rejected");
+
+    let saved = store.load().unwrap().expect("New session wasn't saved");
+    assert_ne!(saved, stale);
+
+    let mut token_only = NanoClient::with_token("stale");
+    token_only.base_url = server.url();
+    assert!(matches!(
+        token_only.current_user().await,
+        Err(Error::SimpleNanoError(StatusCode::UNAUTHORIZED, _))
+    ));
+
+    // A rejected session is cleared from the store, even if the client can't log in again
+    store.save(&stale).unwrap();
+    let mut restored = NanoClient::with_store(store.clone()).unwrap();
+    restored.base_url = server.url();
+    assert!(restored.current_user().await.is_err());
+    assert_eq!(store.load().unwrap(), None);
+}
+
+#[test]
+fn test_file_session_store() {
+    let path = std::env::temp_dir().join(format!("nanowrimo-session-{}.json", std::process::id()));
+    let store = crate::session::FileSessionStore::new(&path);
+    let session = Session { auth_token: "token".to_string() };
+
+    assert_eq!(store.load().unwrap(), None);
+    store.save(&session).unwrap();
+    assert_eq!(store.load().unwrap(), Some(session.clone()));
+
+    // A file others could read is replaced by one only the owner can
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o644)).unwrap();
+        store.save(&session).unwrap();
+        assert_eq!(std::fs::metadata(&path).unwrap().permissions().mode() & 0o777, 0o600);
+    }
+
+    store.clear().unwrap();
+    assert_eq!(store.load().unwrap(), None);
+    assert!(!path.exists());
+}
+
+#[test]
+fn test_file_session_store_concurrent() {
+    let dir = std::env::temp_dir().join(format!("nanowrimo-sessions-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("session.json");
+
+    let handles = (0..8)
+        .map(|idx| {
+            let store = crate::session::FileSessionStore::new(&path);
+            std::thread::spawn(move || {
+                for _ in 0..20 {
+                    store.save(&Session { auth_token: format!("token-{}", idx) }).unwrap();
+                }
+            })
+        })
+        .collect::<Vec<_>>();
+    for handle in handles {
+        handle.join().expect("Saving from several threads at once failed");
+    }
+
+    // Every save finished whole, and left no temporary files behind
+    let store = crate::session::FileSessionStore::new(&path);
+    assert!(store.load().unwrap().unwrap().auth_token.starts_with("token-"));
+    assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 1);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[tokio::test]
+async fn test_debug_redaction() {
+    let (_server, client) = mock_client().await;
+    let token = client.session().unwrap().auth_token;
+
+    let client_debug = format!("{:?}", client);
+    assert!(!client_debug.contains("hunter2"), "Password in client Debug: {}", client_debug);
+    assert!(!client_debug.contains(&token), "Token in client Debug: {}", client_debug);
+
+    let req_debug = format!("{:?}", client.request("users/current", Method::GET));
+    assert!(!req_debug.contains(&token), "Token in request Debug: {}", req_debug);
+    assert!(!format!("{:?}", client.session()).contains(&token));
+}
+
+#[tokio::test]
+async fn test_credential_providers() {
+    use crate::credentials::{CallbackCredentials, EnvCredentials, FileCredentials};
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    let server = crate::mock::MockServer::start().await;
+    server.add_user("writer", "hunter2");
+
+    std::env::set_var("NANO_TEST_PROVIDER_USER", "writer");
+    std::env::set_var("NANO_TEST_PROVIDER_PASS", "hunter2");
+    let mut client = NanoClient::with_credentials(
+        EnvCredentials::new("NANO_TEST_PROVIDER_USER", "NANO_TEST_PROVIDER_PASS")
+    );
+    client.base_url = server.url();
+    client.login().await.expect("Couldn't log in with environment credentials");
+
+    let path = std::env::temp_dir().join(format!("nanowrimo-credentials-{}.json", std::process::id()));
+    std::fs::write(&path, r#"{"identifier": "writer", "password": "hunter2"}"#).unwrap();
+    let mut client = NanoClient::with_credentials(FileCredentials::new(&path));
+    client.base_url = server.url();
+    client.login().await.expect("Couldn't log in with file credentials");
+    std::fs::remove_file(&path).unwrap();
+
+    let calls = Rc::new(Cell::new(0));
+    let counter = calls.clone();
+    let mut client = NanoClient::with_credentials(CallbackCredentials::new(move || {
+        counter.set(counter.get() + 1);
+        Ok(Credentials::new("writer", "hunter2"))
+    }));
+    client.base_url = server.url();
+    assert_eq!(calls.get(), 0, "Credentials were fetched before logging in");
+    client.current_user().await.expect("Client didn't log in when first required");
+    assert_eq!(calls.get(), 1);
+
+    let mut client = NanoClient::with_credentials(EnvCredentials::new("NANO_TEST_MISSING", "NANO_TEST_MISSING"));
+    client.base_url = server.url();
+    assert!(matches!(client.login().await, Err(Error::CredentialError(_))));
+}
+
+fn sign_ins(server: &crate::mock::MockServer) -> usize {
+    server.requests()
+        .iter()
+        .filter(|(_, path)| path == "users/sign_in")
+        .count()
+}
+
+#[tokio::test]
+async fn test_session_state_transitions() {
+    let server = crate::mock::MockServer::start().await;
+    server.add_user("writer", "hunter2");
+    let client = NanoClient::new_url(&server.url(), "writer", "hunter2");
+
+    assert_eq!(client.session_state(), SessionState::Anonymous);
+    assert!(!client.is_logged_in());
+
+    let before = chrono::Utc::now();
+    client.login().await.expect("Couldn't log in");
+    let first_token = match client.session_state() {
+        SessionState::Authenticated { since, token } => {
+            assert!(since >= before);
+            token
+        },
+        state => panic!("Expected Authenticated after log in, got {:?}", state)
+    };
+    assert!(client.is_logged_in());
+
+    // A rejected token is noticed, and the client logs in again exactly once
+    server.expire_sessions();
+    client.current_user().await.expect("Client didn't recover from an expired session");
+    assert_eq!(sign_ins(&server), 2);
+    match client.session_state() {
+        SessionState::Authenticated { token, .. } => assert_ne!(token, first_token),
+        state => panic!("Expected Authenticated after logging in again, got {:?}", state)
+    }
+
+    client.logout().await.expect("Couldn't log out");
+    assert_eq!(client.session_state(), SessionState::Anonymous);
+}
 
-client.search("craftspider").await.unwrap();
+#[tokio::test]
+async fn test_expired_without_credentials() {
+    let (server, client) = mock_client().await;
+    let mut token_only = NanoClient::with_token(&client.session().unwrap().auth_token);
+    token_only.base_url = server.url();
+    token_only.transport = Box::new(server.transport());
+    assert!(token_only.is_logged_in());
+
+    server.expire_sessions();
+    assert!(token_only.current_user().await.is_err());
+    assert_eq!(token_only.session_state(), SessionState::Expired);
+    assert!(!token_only.is_logged_in());
+    assert_eq!(sign_ins(&server), 1);
+}
+
+#[tokio::test]
+async fn test_guest_never_logs_in() {
+    let server = crate::mock::MockServer::start().await;
+    let mut client = server.client();
+
+    assert!(matches!(
+        client.current_user().await,
+        Err(Error::SimpleNanoError(StatusCode::UNAUTHORIZED, _))
+    ));
+    assert!(matches!(client.login().await, Err(Error::CredentialError(_))));
+    assert_eq!(client.session_state(), SessionState::Anonymous);
+
+    client.change_user(None, None).await.expect("Couldn't change a guest to a guest");
+    assert!(server.requests().iter().all(|(_, path)| path != "users/sign_in" && path != "users/logout"));
+}
+
+fn fast_backoff(max_retries: u32) -> Backoff {
+    Backoff {
+        max_retries,
+        base_delay: std::time::Duration::from_millis(1),
+        max_delay: std::time::Duration::from_millis(5),
+    }
+}
+
+#[tokio::test]
+async fn test_retry_server_errors() {
+    let server = crate::mock::MockServer::start().await;
+    server.add_user("writer", "hunter2");
+    let client = NanoClient::builder()
+        .base_url(&server.url())
+        .user("writer", "hunter2")
+        .backoff(fast_backoff(2))
+        .build()
+        .unwrap();
+    client.login().await.unwrap();
+
+    server.fail_next(StatusCode::BAD_GATEWAY, None);
+    server.fail_next(StatusCode::SERVICE_UNAVAILABLE, None);
+    client.current_user().await.expect("Couldn't retry after server errors");
+    assert_eq!(server.requests().iter().filter(|(_, path)| path == "users/current").count(), 3);
+
+    for _ in 0..3 {
+        server.fail_next(StatusCode::INTERNAL_SERVER_ERROR, None);
+    }
+    assert!(matches!(
+        client.current_user().await,
+        Err(Error::SimpleNanoError(StatusCode::INTERNAL_SERVER_ERROR, _))
+    ));
+
+    // Creating isn't idempotent, so only throttling is retried
+    server.fail_next(StatusCode::INTERNAL_SERVER_ERROR, None);
+    assert!(client.create_genre(&GenreData { name: "Horror".to_string(), user_id: 1 }).await.is_err());
+    server.fail_next(StatusCode::TOO_MANY_REQUESTS, None);
+    client.create_genre(&GenreData { name: "Horror".to_string(), user_id: 1 })
+        .await
+        .expect("Couldn't retry after being throttled");
+    assert_eq!(server.requests().iter().filter(|(method, _)| method == Method::POST).count(), 4);
+}
+
+#[tokio::test]
+async fn test_retry_after() {
+    let (server, client) = mock_client().await;
+
+    server.fail_next(StatusCode::TOO_MANY_REQUESTS, Some(1));
+    client.current_user().await.expect("Couldn't retry after being throttled");
+    assert_eq!(server.waited(), std::time::Duration::from_secs(1));
+
+    let client = NanoClient::builder()
+        .base_url(&server.url())
+        .transport(server.transport())
+        .backoff(Backoff::none())
+        .build()
+        .unwrap();
+    server.fail_next(StatusCode::TOO_MANY_REQUESTS, Some(30));
+    match client.get_id(NanoKind::Genre, 1).await {
+        Err(Error::Throttled(StatusCode::TOO_MANY_REQUESTS, Some(wait))) => {
+            assert_eq!(wait, std::time::Duration::from_secs(30))
+        },
+        other => panic!("Expected to be throttled, got {:?}", other)
+    }
+
+    // A wait longer than the backoff allows isn't waited for
+    let client = NanoClient::builder()
+        .base_url(&server.url())
+        .transport(server.transport())
+        .build()
+        .unwrap();
+    server.fail_next(StatusCode::TOO_MANY_REQUESTS, Some(86400));
+    let start = std::time::Instant::now();
+    assert!(matches!(client.get_id(NanoKind::Genre, 1).await, Err(Error::Throttled(_, Some(_)))));
+    assert!(start.elapsed() < std::time::Duration::from_secs(5));
+
+    assert_eq!(governor::parse_retry_after("120"), Some(std::time::Duration::from_secs(120)));
+    assert_eq!(governor::parse_retry_after("Wed, 21 Oct 2015 07:28:00 GMT"), None);
+    assert!(governor::parse_retry_after("soon").is_none());
+}
+
+#[tokio::test]
+async fn test_rate_limit() {
+    let server = crate::mock::MockServer::start().await;
+    let id = server.insert(NanoKind::Genre, serde_json::json!({ "name": "Horror", "user-id": 1 }));
+    let client = NanoClient::builder()
+        .base_url(&server.url())
+        .rate_limit(RateLimit::new(2, std::time::Duration::from_millis(200)).unwrap())
+        .build()
+        .unwrap();
+
+    let start = std::time::Instant::now();
+    for _ in 0..6 {
+        client.get_id(NanoKind::Genre, id).await.unwrap();
+    }
+
+    // Two requests are allowed at once, then one every 100ms
+    assert!(start.elapsed() >= std::time::Duration::from_millis(350));
+}
+
+#[test]
+fn test_token_bucket() {
+    let start = std::time::Instant::now();
+    let mut bucket = governor::TokenBucket::new(RateLimit::new(3, std::time::Duration::from_secs(3)).unwrap());
+
+    for _ in 0..3 {
+        assert_eq!(bucket.wait(start), std::time::Duration::from_secs(0));
+        bucket.take();
+    }
+    assert_eq!(bucket.wait(start), std::time::Duration::from_secs(1));
+    assert_eq!(bucket.wait(start + std::time::Duration::from_secs(1)), std::time::Duration::from_secs(0));
+
+    // Limits that would allow no requests, or any number of them, are refused
+    assert!(matches!(RateLimit::new(0, std::time::Duration::from_secs(1)), Err(Error::ValidationError(_))));
+    assert!(matches!(RateLimit::new(1, std::time::Duration::from_secs(0)), Err(Error::ValidationError(_))));
+    let zero = RateLimit { requests: 0, per: std::time::Duration::from_secs(1) };
+    assert!(matches!(NanoClient::builder().rate_limit(zero).build(), Err(Error::ValidationError(_))));
+    assert!(matches!(set_global_rate_limit(Some(zero)), Err(Error::ValidationError(_))));
+
+    let backoff = Backoff::default();
+    for attempt in 0..10 {
+        assert!(backoff.delay(attempt) <= backoff.max_delay);
+    }
+}
 
-client.random_offer().await.unwrap();
+#[derive(Debug)]
+struct TraceMiddleware {
+    name: &'static str,
+    trace: std::rc::Rc<RefCell<Vec<String>>>,
+}
+
+impl Middleware for TraceMiddleware {
+    fn on_request(&self, _req: &mut RequestInfo) -> Result<(), Error> {
+        self.trace.borrow_mut().push(format!("{} request", self.name));
+        Ok(())
+    }
+
+    fn on_response(&self, req: &RequestInfo, resp: &mut RawResponse) -> Result<(), Error> {
+        self.trace.borrow_mut().push(format!("{} response {} {}", self.name, req.url.path(), resp.status.as_u16()));
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+struct ChaosMiddleware {
+    failures: RefCell<u32>,
+}
+
+impl Middleware for ChaosMiddleware {
+    fn intercept(&self, req: &RequestInfo) -> Option<Result<RawResponse, Error>> {
+        if req.url.path() == "/offers" {
+            Some(Ok(RawResponse::new(StatusCode::OK, "[]")))
+        } else {
+            None
+        }
+    }
+
+    fn on_response(&self, req: &RequestInfo, resp: &mut RawResponse) -> Result<(), Error> {
+        assert_eq!(req.headers["X-Client"], "nanowrimo-tests");
+
+        let mut failures = self.failures.borrow_mut();
+        if *failures > 0 {
+            *failures -= 1;
+            resp.status = StatusCode::SERVICE_UNAVAILABLE;
+        }
+        Ok(())
+    }
+}
+
+#[tokio::test]
+async fn test_middleware() {
+    let server = crate::mock::MockServer::start().await;
+    server.add_user("writer", "hunter2");
+    let trace = std::rc::Rc::new(RefCell::new(Vec::new()));
+    let chaos = std::rc::Rc::new(ChaosMiddleware { failures: RefCell::new(0) });
+
+    let client = NanoClient::builder()
+        .base_url(&server.url())
+        .user("writer", "hunter2")
+        .backoff(fast_backoff(2))
+        .middleware(TraceMiddleware { name: "outer", trace: trace.clone() })
+        .middleware(crate::middleware::DefaultHeader::new("X-Client", "nanowrimo-tests"))
+        .middleware(chaos.clone())
+        .middleware(TraceMiddleware { name: "inner", trace: trace.clone() })
+        .build()
+        .unwrap();
+
+    client.login().await.unwrap();
+    assert_eq!(*trace.borrow(), [
+        "outer request",
+        "inner request",
+        "inner response /users/sign_in 200",
+        "outer response /users/sign_in 200",
+    ]);
+
+    // The chaos middleware answers without sending, so inner never sees the request go out
+    trace.borrow_mut().clear();
+    assert!(client.offers().await.unwrap().is_empty());
+    assert_eq!(*trace.borrow(), ["outer request", "inner request", "outer response /offers 200"]);
+    assert!(server.requests().iter().all(|(_, path)| path != "offers"));
+
+    // Faked failures are retried like real ones
+    trace.borrow_mut().clear();
+    *chaos.failures.borrow_mut() = 1;
+    client.current_user().await.expect("Couldn't retry after a faked failure");
+    assert_eq!(trace.borrow().last().unwrap(), "outer response /users/current 200");
+    assert_eq!(trace.borrow().iter().filter(|line| line.ends_with("503")).count(), 1);
 
-client.store_items().await.unwrap();
+    let client = NanoClient::builder()
+        .base_url(&server.url())
+        .middleware(crate::middleware::DefaultHeader::new("X Client", "nanowrimo-tests"))
+        .build()
+        .unwrap();
+    assert!(matches!(client.offers().await, Err(Error::ValidationError(_))));
+}
+
+#[derive(Debug)]
+struct RewriteMiddleware {
+    from: String,
+    to: String,
+}
+
+impl Middleware for RewriteMiddleware {
+    fn on_request(&self, req: &mut RequestInfo) -> Result<(), Error> {
+        if req.url.path() == self.from {
+            req.url.set_path(&self.to);
+            req.method = Method::GET;
+            req.body = None;
+        }
+        Ok(())
+    }
+}
+
+#[tokio::test]
+async fn test_middleware_rewrite() {
+    let server = crate::mock::MockServer::start().await;
+    let id = server.insert(NanoKind::Genre, serde_json::json!({ "name": "Horror", "user-id": 1 }));
+
+    // The request is sent as the middleware left it, not as it was built
+    let client = NanoClient::builder()
+        .base_url(&server.url())
+        .middleware(RewriteMiddleware { from: "/genres/999".to_string(), to: format!("/genres/{}", id) })
+        .build()
+        .unwrap();
+    let genre = client.get_id(NanoKind::Genre, 999).await.expect("Rewritten request wasn't sent");
+    assert_eq!(genre.data.id(), id);
+
+    let client = NanoClient::builder()
+        .base_url(&server.url())
+        .middleware(RewriteMiddleware { from: format!("/genres/{}", id), to: format!("/genres/{}", id) })
+        .build()
+        .unwrap();
+    // Turned into a GET, so the genre comes back instead of the empty response a delete expects
+    assert!(client.delete(NanoKind::Genre, id).await.is_err());
+    assert_eq!(server.requests().last().unwrap(), &(Method::GET, format!("genres/{}", id)));
+    assert!(server.get(NanoKind::Genre, id).is_some());
+}
+
+#[tokio::test]
+async fn test_schema_mismatch() {
+    let server = crate::mock::MockServer::start().await;
+    let id = server.insert(NanoKind::Genre, serde_json::json!({ "name": 5, "user-id": 1 }));
+    let client = server.client();
+
+    assert!(matches!(client.get_id(NanoKind::Genre, id).await, Err(Error::SerdeError(_))));
+}
 
-client.offers().await.unwrap();
+#[cfg(feature = "tracing")]
+#[derive(Default)]
+struct FieldCollector {
+    spans: std::sync::Mutex<Vec<&'static tracing::Metadata<'static>>>,
+    entered: std::sync::Mutex<Vec<tracing::span::Id>>,
+    fields: std::sync::Arc<std::sync::Mutex<Vec<(String, String)>>>,
+}
 
-for (_, link) in &user.data.relationships.relations {
-    dbg!(link);
-    let _ = dbg!(client.get_all_related(link).await);
-    tokio::time::delay_for(std::time::Duration::from_secs(1)).await;
+#[cfg(feature = "tracing")]
+impl tracing::field::Visit for &FieldCollector {
+    fn record_debug(&mut self, field: &tracing::field::Field, value: &dyn std::fmt::Debug) {
+        self.fields.lock().unwrap().push((field.name().to_string(), format!("{:?}", value)));
+    }
 }
 
-dbg!(client.primary_project(user.data.id).await.unwrap());
+#[cfg(feature = "tracing")]
+impl tracing::Subscriber for FieldCollector {
+    fn enabled(&self, _: &tracing::Metadata<'_>) -> bool {
+        true
+    }
+
+    fn new_span(&self, span: &tracing::span::Attributes<'_>) -> tracing::span::Id {
+        span.record(&mut &*self);
+        let mut spans = self.spans.lock().unwrap();
+        spans.push(span.metadata());
+        tracing::span::Id::from_u64(spans.len() as u64)
+    }
 
-let _ = dbg!(client.pages("pep-talks").await);
-let _ = dbg!(client.pages("nano-prep-101").await);
-*/
+    fn record(&self, _: &tracing::span::Id, values: &tracing::span::Record<'_>) {
+        values.record(&mut &*self);
+    }
+
+    fn record_follows_from(&self, _: &tracing::span::Id, _: &tracing::span::Id) {}
+
+    fn event(&self, event: &tracing::Event<'_>) {
+        event.record(&mut &*self);
+    }
+
+    fn enter(&self, span: &tracing::span::Id) {
+        self.entered.lock().unwrap().push(span.clone());
+    }
+
+    fn exit(&self, _: &tracing::span::Id) {
+        self.entered.lock().unwrap().pop();
+    }
+
+    fn current_span(&self) -> tracing_core::span::Current {
+        match self.entered.lock().unwrap().last() {
+            Some(id) => tracing_core::span::Current::new(id.clone(), self.spans.lock().unwrap()[id.into_u64() as usize - 1]),
+            None => tracing_core::span::Current::none()
+        }
+    }
+}
+
+#[cfg(feature = "tracing")]
+#[tokio::test]
+async fn test_tracing() {
+    let collector = FieldCollector::default();
+    let fields = collector.fields.clone();
+    let _guard = tracing::subscriber::set_default(collector);
+
+    let server = crate::mock::MockServer::start().await;
+    server.add_user("writer", "hunter2");
+    let id = server.insert(NanoKind::Genre, serde_json::json!({ "name": "Horror", "user-id": 1 }));
+    let client = NanoClient::builder()
+        .base_url(&server.url())
+        .user("writer", "hunter2")
+        .backoff(fast_backoff(1))
+        .build()
+        .unwrap();
+
+    server.fail_next(StatusCode::BAD_GATEWAY, None);
+    client.get_id(NanoKind::Genre, id).await.unwrap();
+
+    let find = |name: &str| fields.lock().unwrap()
+        .iter()
+        .filter(|(field, _)| field == name)
+        .map(|(_, val)| val.clone())
+        .collect::<Vec<_>>();
+
+    assert_eq!(find("path"), [format!("{:?}", format!("genres/{}", id))]);
+    assert_eq!(find("kind"), ["\"genres\""]);
+    assert_eq!(find("id"), [id.to_string()]);
+    // Retries are fields of the call's one span, rather than spans of their own
+    assert_eq!(find("status"), ["200"]);
+    assert_eq!(find("retries"), ["1"]);
+    assert_eq!(find("relogin"), ["false"]);
+
+    // Relogging in shows up, but the credentials and token don't
+    client.delete_genre(id).await.unwrap();
+    assert_eq!(find("path").len(), 2);
+    assert_eq!(find("status"), ["200", "204"]);
+    assert_eq!(find("relogin"), ["false", "true"]);
+    assert!(fields.lock().unwrap().iter().all(|(_, val)| !val.contains("hunter2") && !val.contains("mock-token")));
+
+    let id = server.insert(NanoKind::Genre, serde_json::json!({ "name": 5, "user-id": 1 }));
+    assert!(client.get_id(NanoKind::Genre, id).await.is_err());
+    assert_eq!(find("json_path"), ["data.attributes.name"]);
+}
+
+fn cassette(name: &str) -> crate::cassette::Cassette {
+    crate::cassette::Cassette::load(format!("{}/tests/cassettes/{}.json", env!("CARGO_MANIFEST_DIR"), name))
+        .expect("Couldn't load cassette")
+}
+
+/// Record the examples cassette from a mock server. Set `NANO_UPDATE_CASSETTES` to overwrite the
+/// saved cassette, instead of checking it's up to date
+#[tokio::test]
+async fn test_record_examples() {
+    let server = crate::mock::MockServer::new();
+    let user = server.add_user("CraftSpider", "hunter2");
+    server.update(NanoKind::User, user, serde_json::json!({
+        "avatar": "https://nanowrimo.imgix.net/avatars/craftspider.png",
+        "bio": "Writing every November",
+        "email": "craftspider@example.com",
+        "laurels": 12,
+        "location": "Seattle, WA",
+        "slug": "craftspider",
+        "stats-projects": 6,
+        "stats-projects-enabled": true,
+        "stats-streak": 30,
+        "stats-streak-enabled": true,
+        "stats-word-count": 310522,
+        "stats-word-count-enabled": true,
+        "stats-years-done": 6,
+        "stats-years-enabled": true,
+        "stats-years-won": 3,
+        "time-zone": "America/Los_Angeles"
+    }));
+
+    let project = server.insert(NanoKind::Project, serde_json::json!({
+        "cover": null,
+        "created-at": "2020-10-01T00:00:00Z",
+        "excerpt": null,
+        "pinterest-url": null,
+        "playlist-url": null,
+        "primary": 1,
+        "privacy": 2,
+        "slug": "the-lighthouse",
+        "status": "In Progress",
+        "summary": "A keeper and a storm",
+        "title": "The Lighthouse",
+        "unit-count": 50000,
+        "unit-type": 0,
+        "user-id": user,
+        "writing-type": 0
+    }));
+    let book = server.insert(NanoKind::FavoriteBook, serde_json::json!({ "title": "Dune", "user-id": user }));
+    let badge = server.insert(NanoKind::Badge, mock_badge("word count", 1));
+    server.relate(NanoKind::User, user, NanoKind::Project, project);
+    server.relate(NanoKind::User, user, NanoKind::FavoriteBook, book);
+    server.relate(NanoKind::User, user, NanoKind::Badge, badge);
+
+    for (headline, code) in [("Scrivener discount", "NANO2020"), ("Novel software trial", "WRIMO")] {
+        server.insert(NanoKind::Post, serde_json::json!({
+            "api-code": null,
+            "body": format!("Get 20 percent off with code {}", code),
+            "card-image": null,
+            "content-type": "Plain Text",
+            "expires-at": "2021-01-31",
+            "external-link": "https://example.com/offer",
+            "headline": headline,
+            "offer-code": code,
+            "order": 1,
+            "published": true,
+            "subhead": null
+        }));
+    }
+    server.add_store_item("winner-shirt-2020", "https://cdn.shopify.com/winner-shirt-2020.png", "2020 Winner Shirt");
+    server.add_store_item("camp-mug", "https://cdn.shopify.com/camp-mug.png", "Camp Mug");
+    for (url, headline, content_type) in [("pep-talks", "Pep Talks", "Group of page cards"), ("nano-prep-101", "NaNo Prep 101", "General content")] {
+        server.insert(NanoKind::Page, serde_json::json!({
+            "body": format!("<p>{}</p>", headline),
+            "content-type": content_type,
+            "headline": headline,
+            "promotional-card-image": null,
+            "show-after": null,
+            "url": url
+        }));
+    }
+
+    let recorder = crate::cassette::Recorder::new();
+    let client = NanoClient::builder()
+        .base_url(&server.url())
+        .transport(server.transport())
+        .user("CraftSpider", "hunter2")
+        .middleware(recorder.clone())
+        .build()
+        .unwrap();
+
+    client.login().await.unwrap();
+    client.search("craftspider").await.unwrap();
+    client.random_offer().await.unwrap();
+    client.store_items().await.unwrap();
+    client.offers().await.unwrap();
+    client.pages("pep-talks").await.unwrap();
+    client.pages("nano-prep-101").await.unwrap();
+    let current = client.current_user().await.unwrap();
+    let relations = &current.data.relationships().as_ref().unwrap().relations;
+    client.get_all_related(&relations[&NanoKind::Project]).await.unwrap();
+    client.get_all_related(&relations[&NanoKind::FavoriteBook]).await.unwrap();
+    assert!(client.get_all_related(&relations[&NanoKind::Badge]).await.is_err());
+
+    let path = format!("{}/tests/cassettes/examples.json", env!("CARGO_MANIFEST_DIR"));
+    if std::env::var_os("NANO_UPDATE_CASSETTES").is_some() {
+        recorder.cassette().save(&path).unwrap();
+    }
+    assert_eq!(recorder.cassette(), cassette("examples"), "Cassette is out of date, rerun with NANO_UPDATE_CASSETTES set");
+}
+
+#[tokio::test]
+async fn test_replay_examples() {
+    let replayer = std::rc::Rc::new(crate::cassette::Replayer::new(cassette("examples")));
+    let client = NanoClient::builder()
+        .user("CraftSpider", "hunter2")
+        .middleware(replayer.clone())
+        .build()
+        .unwrap();
+
+    client.login().await.unwrap();
+    let found = client.search("craftspider").await.unwrap();
+    assert_eq!(found.data.len(), 1);
+    assert_eq!(found.data[0].data.name, "CraftSpider");
+    assert_eq!(found.data[0].data.stats.word_count, 310522);
+
+    let offer = client.random_offer().await.unwrap();
+    assert_eq!(offer.data.data.offer_code.as_deref(), Some("NANO2020"));
+
+    let items = client.store_items().await.unwrap();
+    assert_eq!(items[0].image, "https://cdn.shopify.com/winner-shirt-2020.png");
+
+    let offers = client.offers().await.unwrap();
+    assert_eq!(offers.iter().map(|offer| offer.data.data.offer_code.as_deref().unwrap()).collect::<Vec<_>>(), ["NANO2020", "WRIMO"]);
+
+    let pep_talks = client.pages("pep-talks").await.unwrap();
+    assert_eq!(pep_talks.data.data.headline, "Pep Talks");
+    assert_eq!(pep_talks.data.data.content_type, crate::ContentType::GroupOfPageCards);
+    let prep = client.pages("nano-prep-101").await.unwrap();
+    assert_eq!(prep.data.data.headline, "NaNo Prep 101");
+
+    let user = client.current_user().await.unwrap();
+    let relations = &user.data.relationships().as_ref().unwrap().relations;
+    assert_eq!(relations.len(), 3);
+
+    // The primary project is the one marked as such among the user's projects
+    let projects = client.get_all_related(&relations[&NanoKind::Project]).await.unwrap();
+    assert!(matches!(&projects.data[0], Object::Project(project) if project.data.title == "The Lighthouse" && project.data.primary == Some(1)));
+
+    let books = client.get_all_related(&relations[&NanoKind::FavoriteBook]).await.unwrap();
+    assert!(matches!(&books.data[0], Object::FavoriteBook(book) if book.data.title == "Dune"));
+
+    // Some relations can't be fetched directly, and Nano responds with a 404
+    assert!(matches!(
+        client.get_all_related(&relations[&NanoKind::Badge]).await,
+        Err(Error::SimpleNanoError(StatusCode::NOT_FOUND, _))
+    ));
+
+    assert!(replayer.remaining().is_empty());
+    assert!(matches!(client.fundometer().await, Err(Error::CassetteError(_))));
+}
+
+#[tokio::test]
+async fn test_record_replay() {
+    let server = crate::mock::MockServer::start().await;
+    server.add_user("writer", "hunter2");
+    server.update(NanoKind::User, 1, serde_json::json!({ "email": "writer@example.com" }));
+    let recorder = crate::cassette::Recorder::new();
+    let client = NanoClient::builder()
+        .base_url(&server.url())
+        .user("writer", "hunter2")
+        .middleware(recorder.clone())
+        .build()
+        .unwrap();
+
+    client.login().await.unwrap();
+    let genre = client.create_genre(&GenreData { name: "Horror".to_string(), user_id: 1 }).await.unwrap();
+    let user = client.current_user().await.unwrap();
+
+    let path = std::env::temp_dir().join(format!("nanowrimo-cassette-{}.json", std::process::id()));
+    recorder.cassette().save(&path).unwrap();
+    let saved = std::fs::read_to_string(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    assert!(!saved.contains("hunter2"));
+    assert!(!saved.contains("mock-token"));
+    assert!(!saved.contains("writer@example.com"));
+
+    let recorded = serde_json::from_str::<crate::cassette::Cassette>(&saved).unwrap();
+    assert_eq!(
+        recorded.interactions.iter().map(|int| int.request.path.as_str()).collect::<Vec<_>>(),
+        ["users/sign_in", "genres", "users/current"]
+    );
+
+    let replayer = std::rc::Rc::new(crate::cassette::Replayer::new(recorded));
+    let client = NanoClient::builder()
+        .user("writer", "hunter2")
+        .middleware(replayer.clone())
+        .build()
+        .unwrap();
+
+    client.login().await.unwrap();
+    let replayed = client.create_genre(&GenreData { name: "Horror".to_string(), user_id: 1 }).await.unwrap();
+    assert_eq!(replayed.data.id(), genre.data.id());
+    assert_eq!(client.current_user().await.unwrap().data.data.name, user.data.data.name);
+    assert!(replayer.remaining().is_empty());
+}
+
+/// A transport that answers from a mock server, and records what it sends and waits for
+#[derive(Debug)]
+struct InstantTransport {
+    inner: crate::mock::MockTransport,
+    sent: RefCell<Vec<String>>,
+    delays: RefCell<Vec<std::time::Duration>>,
+}
+
+impl HttpTransport for InstantTransport {
+    fn send<'a>(&'a self, req: &'a RequestInfo) -> crate::transport::TransportFuture<'a, Result<RawResponse, Error>> {
+        self.sent.borrow_mut().push(format!("{} {}", req.method, req.url.path()));
+        self.inner.send(req)
+    }
+
+    fn delay(&self, duration: std::time::Duration) -> crate::transport::TransportFuture<'static, ()> {
+        self.delays.borrow_mut().push(duration);
+        self.inner.delay(duration)
+    }
+}
+
+#[tokio::test]
+async fn test_transport() {
+    let server = crate::mock::MockServer::new();
+    server.add_user("writer", "hunter2");
+    let genre = server.insert(NanoKind::Genre, serde_json::json!({ "name": "Horror", "user-id": 1 }));
+
+    let transport = std::rc::Rc::new(InstantTransport {
+        inner: server.transport(),
+        sent: RefCell::new(Vec::new()),
+        delays: RefCell::new(Vec::new()),
+    });
+    let client = NanoClient::builder()
+        .base_url(&server.url())
+        .user("writer", "hunter2")
+        .transport(transport.clone())
+        .build()
+        .unwrap();
+
+    // Waits go through the transport too, so a long Retry-After doesn't hold up the test
+    client.login().await.unwrap();
+    server.fail_next(StatusCode::SERVICE_UNAVAILABLE, Some(20));
+    let start = std::time::Instant::now();
+    client.get_id(NanoKind::Genre, genre).await.unwrap();
+    assert!(start.elapsed() < std::time::Duration::from_secs(5));
+
+    assert_eq!(*transport.delays.borrow(), [std::time::Duration::from_secs(20)]);
+    assert_eq!(server.waited(), std::time::Duration::from_secs(20));
+    assert_eq!(*transport.sent.borrow(), ["POST /users/sign_in".to_string(), format!("GET /genres/{}", genre), format!("GET /genres/{}", genre)]);
+    assert_eq!(server.requests().len(), 3);
+}
+
+#[cfg(feature = "blocking")]
+#[test]
+fn test_blocking_replay() {
+    let client = crate::blocking::NanoClient::builder()
+        .middleware(crate::cassette::Replayer::new(cassette("examples")))
+        .build_blocking()
+        .unwrap();
+
+    let found = client.search("craftspider").unwrap();
+    assert_eq!(found.data[0].data.name, "CraftSpider");
+
+    let user = client.current_user().unwrap();
+    let relations = &user.data.relationships().as_ref().unwrap().relations;
+    let books = client.get_all_related(&relations[&NanoKind::FavoriteBook]).unwrap();
+    assert!(matches!(&books.data[0], Object::FavoriteBook(book) if book.data.title == "Dune"));
+
+    assert!(matches!(
+        client.get_all_related(&relations[&NanoKind::Badge]),
+        Err(Error::SimpleNanoError(StatusCode::NOT_FOUND, _))
+    ));
+}
+
+#[cfg(feature = "blocking")]
+#[test]
+fn test_blocking_mock() {
+    let server = crate::mock::MockServer::new();
+    server.add_user("writer", "hunter2");
+
+    let mut client = crate::blocking::NanoClient::builder()
+        .base_url(&server.url())
+        .transport(server.transport())
+        .user("writer", "hunter2")
+        .build_blocking()
+        .unwrap();
+
+    client.login().unwrap();
+    assert!(client.is_logged_in());
+
+    let genre = client.create_genre(&GenreData { name: "Horror".to_string(), user_id: 1 }).unwrap();
+    let fetched = client.get_id(NanoKind::Genre, genre.data.id()).unwrap();
+    assert!(matches!(&fetched.data, Object::Genre(genre) if genre.data.name == "Horror"));
+
+    client.change_user(None, None).unwrap();
+    assert!(!client.is_logged_in());
+    assert!(client.create_genre(&GenreData { name: "Comedy".to_string(), user_id: 1 }).is_err());
+}
@@ -0,0 +1,178 @@
+//! Instrumentation of API calls, which does nothing unless the `tracing` feature is enabled
+
+use crate::error::Error;
+use crate::kind::NanoKind;
+
+use std::future::Future;
+use std::time::Instant;
+
+use reqwest::{Method, StatusCode};
+use serde::de::DeserializeOwned;
+
+#[cfg(feature = "tracing")]
+use crate::data::{CollectionResponse, ItemResponse, KindVisitor, ObjectInfo};
+#[cfg(feature = "tracing")]
+use serde::Serialize;
+#[cfg(feature = "tracing")]
+use tracing::{Instrument, Span, field};
+
+/// The kind of object a path is for, taken from its first segment
+pub(crate) fn path_kind(path: &str) -> Option<NanoKind> {
+    path.split('/').next().and_then(|name| NanoKind::from_name(name).ok())
+}
+
+/// The span of a single API call, across all its attempts. Retries and logging in again are
+/// recorded as fields of the one span, and the status is that of the last attempt
+pub(crate) struct CallTrace {
+    #[cfg(feature = "tracing")]
+    span: Span,
+    start: Instant,
+    status: Option<StatusCode>,
+    retries: u32,
+    relogin: bool,
+}
+
+impl CallTrace {
+    /// Start tracing a call. The kind and ID, if any, are taken from the path. Paths never
+    /// contain credentials, and headers and bodies are never recorded
+    pub(crate) fn new(method: &Method, path: &str) -> CallTrace {
+        let kind = path_kind(path);
+        let id = path.split('/').nth(1).and_then(|id| id.parse::<u64>().ok());
+
+        #[cfg(feature = "tracing")]
+        let span = tracing::info_span!(
+            "nano_request",
+            method = %method,
+            path = path,
+            kind = field::Empty,
+            id = field::Empty,
+            status = field::Empty,
+            latency_ms = field::Empty,
+            retries = field::Empty,
+            relogin = field::Empty,
+        );
+
+        #[cfg(feature = "tracing")]
+        {
+            if let Some(kind) = kind {
+                span.record("kind", kind.api_name());
+            }
+            if let Some(id) = id {
+                span.record("id", id);
+            }
+        }
+
+        #[cfg(not(feature = "tracing"))]
+        let _ = (method, kind, id);
+
+        CallTrace {
+            #[cfg(feature = "tracing")]
+            span,
+            start: Instant::now(),
+            status: None,
+            retries: 0,
+            relogin: false,
+        }
+    }
+
+    /// Note the status of an attempt at the call, if it got a response
+    pub(crate) fn attempt(&mut self, status: Option<StatusCode>) {
+        if status.is_some() {
+            self.status = status;
+        }
+    }
+
+    /// Run part of the call inside this trace's span
+    pub(crate) async fn within<F: Future>(&self, fut: F) -> F::Output {
+        #[cfg(feature = "tracing")]
+        let fut = fut.instrument(self.span.clone());
+
+        fut.await
+    }
+
+    /// Note that the call failed and is about to be retried
+    pub(crate) fn retry(&mut self, err: &Error, delay: std::time::Duration) {
+        self.retries += 1;
+
+        #[cfg(feature = "tracing")]
+        self.span.in_scope(|| tracing::debug!(error = %err, delay_ms = delay.as_millis() as u64, "Retrying request"));
+
+        #[cfg(not(feature = "tracing"))]
+        let _ = (err, delay);
+    }
+
+    /// Note that the client had to log in again during the call
+    pub(crate) fn relogin(&mut self) {
+        self.relogin = true;
+    }
+
+    /// Finish the call, recording how it went
+    pub(crate) fn finish<U>(self, res: &Result<U, Error>) {
+        #[cfg(feature = "tracing")]
+        {
+            if let Some(status) = self.status {
+                self.span.record("status", status.as_u16());
+            }
+            self.span.record("latency_ms", self.start.elapsed().as_millis() as u64);
+            self.span.record("retries", self.retries);
+            self.span.record("relogin", self.relogin);
+
+            if let Err(err) = res {
+                self.span.in_scope(|| tracing::warn!(error = %err, "Request failed"));
+            }
+        }
+
+        #[cfg(not(feature = "tracing"))]
+        let _ = (self.start, self.status, self.retries, self.relogin, res);
+    }
+}
+
+/// Finds where a response body stops matching the Object type of a kind. Objects are tagged by
+/// their type, and serde can't see past the tag, so the concrete type is used instead
+#[cfg(feature = "tracing")]
+struct PathProbe<'a> {
+    body: &'a [u8],
+}
+
+#[cfg(feature = "tracing")]
+impl KindVisitor for PathProbe<'_> {
+    type Output = Option<(String, String)>;
+
+    fn visit<O>(self) -> Self::Output
+        where
+            O: ObjectInfo + Serialize + DeserializeOwned + std::fmt::Debug
+    {
+        let is_collection = serde_json::from_slice::<serde_json::Value>(self.body)
+            .map(|val| val["data"].is_array())
+            .unwrap_or(false);
+
+        let mut des = serde_json::Deserializer::from_slice(self.body);
+        let err = if is_collection {
+            serde_path_to_error::deserialize::<_, CollectionResponse<O>>(&mut des).err()
+        } else {
+            serde_path_to_error::deserialize::<_, ItemResponse<O>>(&mut des).err()
+        };
+        err.map(|err| (err.path().to_string(), err.inner().to_string()))
+    }
+}
+
+/// Parse JSON that wasn't recognised as a response as the expected type, to get the error
+/// explaining why not. With tracing, the path to the field that couldn't be parsed is logged at
+/// debug level, found by parsing the body as the Object type of `kind`
+pub(crate) fn parse_body<U: DeserializeOwned>(body: &[u8], kind: Option<NanoKind>) -> Result<U, Error> {
+    let res = serde_json::from_slice(body);
+
+    #[cfg(feature = "tracing")]
+    if let Err(err) = &res {
+        let probe = kind.and_then(|kind| crate::data::visit_kind(kind, PathProbe { body }).flatten());
+        match probe {
+            Some((path, error)) => tracing::debug!(json_path = %path, error = %error, "Couldn't parse response"),
+            None => tracing::debug!(error = %err, "Couldn't parse response"),
+        }
+    }
+
+    #[cfg(not(feature = "tracing"))]
+    let _ = kind;
+
+    Ok(res?)
+}
@@ -0,0 +1,153 @@
+//! Sources of the username and password a [`NanoClient`](crate::NanoClient) logs in with. Providers
+//! are only asked for credentials when the client actually logs in, and secrets are kept in
+//! [`Secret`]s, which never show their contents in `Debug` output.
+
+use crate::error::Error;
+
+use std::env;
+use std::fmt;
+use std::fs;
+use std::path::PathBuf;
+
+use serde::Deserialize;
+
+/// A string that shouldn't be shown in logs, such as a password or authorization token.
+/// Its `Debug` output is redacted, and it has no `Display` or `Serialize` implementation
+#[derive(Deserialize, Clone, PartialEq, Eq)]
+#[serde(transparent)]
+pub struct Secret(String);
+
+impl Secret {
+    /// Wrap a value as a secret
+    pub fn new<S: Into<String>>(val: S) -> Secret {
+        Secret(val.into())
+    }
+
+    /// Get the actual value of this secret
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Debug for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Secret(<redacted>)")
+    }
+}
+
+impl From<String> for Secret {
+    fn from(val: String) -> Secret {
+        Secret(val)
+    }
+}
+
+impl From<&str> for Secret {
+    fn from(val: &str) -> Secret {
+        Secret(val.to_string())
+    }
+}
+
+/// A username or email, and password, to log in with
+#[derive(Deserialize, Debug, Clone)]
+pub struct Credentials {
+    /// The username or email of the user
+    pub identifier: String,
+    /// The password of the user
+    pub password: Secret,
+}
+
+impl Credentials {
+    /// Create a new set of credentials
+    pub fn new<P: Into<Secret>>(identifier: &str, password: P) -> Credentials {
+        Credentials { identifier: identifier.to_string(), password: password.into() }
+    }
+}
+
+/// A source of [`Credentials`] for a client to log in with. The client asks for credentials each
+/// time it logs in, and doesn't keep them afterwards
+pub trait CredentialProvider: fmt::Debug {
+    /// Get the credentials to log in with
+    fn credentials(&self) -> Result<Credentials, Error>;
+}
+
+impl CredentialProvider for Credentials {
+    fn credentials(&self) -> Result<Credentials, Error> {
+        Ok(self.clone())
+    }
+}
+
+/// A [`CredentialProvider`] that reads credentials from environment variables
+#[derive(Debug, Clone)]
+pub struct EnvCredentials {
+    identifier_var: String,
+    password_var: String,
+}
+
+impl EnvCredentials {
+    /// Read credentials from the environment variables with the given names
+    pub fn new(identifier_var: &str, password_var: &str) -> EnvCredentials {
+        EnvCredentials { identifier_var: identifier_var.to_string(), password_var: password_var.to_string() }
+    }
+}
+
+impl Default for EnvCredentials {
+    /// Read credentials from `NANO_USERNAME` and `NANO_PASSWORD`
+    fn default() -> EnvCredentials {
+        EnvCredentials::new("NANO_USERNAME", "NANO_PASSWORD")
+    }
+}
+
+impl CredentialProvider for EnvCredentials {
+    fn credentials(&self) -> Result<Credentials, Error> {
+        let var = |name: &str| env::var(name)
+            .map_err(|err| Error::CredentialError(format!("Couldn't read ${}: {}", name, err)));
+
+        Ok(Credentials::new(&var(&self.identifier_var)?, var(&self.password_var)?))
+    }
+}
+
+/// A [`CredentialProvider`] that reads credentials from a JSON file, in the form
+/// `{"identifier": "...", "password": "..."}`. The file is read again on every log in
+#[derive(Debug, Clone)]
+pub struct FileCredentials {
+    path: PathBuf,
+}
+
+impl FileCredentials {
+    /// Read credentials from the file at a given path
+    pub fn new<P: Into<PathBuf>>(path: P) -> FileCredentials {
+        FileCredentials { path: path.into() }
+    }
+}
+
+impl CredentialProvider for FileCredentials {
+    fn credentials(&self) -> Result<Credentials, Error> {
+        let data = fs::read(&self.path)?;
+        Ok(serde_json::from_slice(&data)?)
+    }
+}
+
+/// A [`CredentialProvider`] that calls a function for credentials, for example to prompt the user
+/// or query a keyring
+pub struct CallbackCredentials<F> {
+    callback: F,
+}
+
+impl<F: Fn() -> Result<Credentials, Error>> CallbackCredentials<F> {
+    /// Get credentials from a given function
+    pub fn new(callback: F) -> CallbackCredentials<F> {
+        CallbackCredentials { callback }
+    }
+}
+
+impl<F> fmt::Debug for CallbackCredentials<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CallbackCredentials").finish()
+    }
+}
+
+impl<F: Fn() -> Result<Credentials, Error>> CredentialProvider for CallbackCredentials<F> {
+    fn credentials(&self) -> Result<Credentials, Error> {
+        (self.callback)()
+    }
+}
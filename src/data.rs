@@ -1,4 +1,5 @@
 use crate::{NanoKind, PrivacySetting, ProjectStatus, EventType, GroupType, EntryMethod, AdminLevel, ActionType, DisplayStatus, WritingType, ContentType, RegistrationPath, BadgeType, JoiningRule, UnitType, AdheresTo, Feeling, How, Where, InvitationStatus};
+use crate::quantity::Quantity;
 use crate::utils::*;
 
 use std::collections::HashMap;
@@ -8,8 +9,21 @@ use paste::paste;
 use serde::{Serialize, Deserialize};
 use serde::de::DeserializeOwned;
 
+#[cfg(test)]
+mod tests;
+
 // TODO: A lot of these shouldn't be pub, constructing them yourself is dangerous
-// TODO: May be possible to make time_zone a type from chrono
+
+/// An IANA time zone, such as `America/Los_Angeles`. With the `tz` feature, this is a
+/// [`Tz`](crate::tz::Tz), so a zone that isn't known fails when the data is loaded. Otherwise,
+/// it's the name of the zone
+#[cfg(feature = "tz")]
+pub type TimeZoneId = chrono_tz::Tz;
+/// An IANA time zone, such as `America/Los_Angeles`. With the `tz` feature, this is a
+/// `chrono_tz::Tz`, so a zone that isn't known fails when the data is loaded. Otherwise, it's the
+/// name of the zone
+#[cfg(not(feature = "tz"))]
+pub type TimeZoneId = String;
 
 #[derive(Serialize, Deserialize, Debug)]
 #[serde(untagged, bound(deserialize = "T: DeserializeOwned"))]
@@ -152,13 +166,34 @@ pub struct PostInfo {
 #[serde(deny_unknown_fields)]
 pub struct ObjectRef {
     /// The ID of the referenced Object
-    #[serde(deserialize_with = "de_str_num")]
+    #[serde(deserialize_with = "de_str_num", serialize_with = "se_str_num")]
     pub id: u64,
     /// The kind of the referenced Object
     #[serde(rename = "type", deserialize_with = "de_nanokind", serialize_with = "se_nanokind")]
     pub kind: NanoKind
 }
 
+/// The body of a request which creates or modifies an Object, in the JSON:API form Nano expects
+#[derive(Serialize, Debug)]
+pub(crate) struct WriteRequest<'a, T: Serialize> {
+    pub data: WriteData<'a, T>
+}
+
+#[derive(Serialize, Debug)]
+pub(crate) struct WriteData<'a, T: Serialize> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    #[serde(rename = "type", serialize_with = "se_nanokind")]
+    pub kind: NanoKind,
+    pub attributes: &'a T
+}
+
+/// The body of a request which adds or removes links from a relationship of an Object
+#[derive(Serialize, Debug)]
+pub(crate) struct RelationRequest<'a> {
+    pub data: &'a [ObjectRef]
+}
+
 /// A trait for all types that represent an 'Object' in the Nano API. See [`Object`] for the
 /// most general form of this.
 pub trait ObjectInfo {
@@ -327,10 +362,31 @@ macro_rules! obj_ty {
                 }
             }
             )+
+
+            /// Call a visitor with the concrete Object type for a kind, or return None if the
+            /// kind has no Object type of its own
+            #[cfg_attr(not(any(test, feature = "tracing")), allow(dead_code))]
+            pub(crate) fn visit_kind<V: KindVisitor>(kind: NanoKind, visitor: V) -> Option<V::Output> {
+                match kind {
+                    $( NanoKind::$name => Some(visitor.visit::<[<$name Object>]>()), )+
+                    _ => None
+                }
+            }
         }
     }
 }
 
+/// Something to do with the concrete Object type of a kind that's only known at runtime, such as
+/// the kind of a request. See [`visit_kind`]
+#[cfg_attr(not(any(test, feature = "tracing")), allow(dead_code))]
+pub(crate) trait KindVisitor {
+    type Output;
+
+    fn visit<O>(self) -> Self::Output
+        where
+            O: ObjectInfo + Serialize + DeserializeOwned + std::fmt::Debug;
+}
+
 obj_ty!(
     Badge
     Challenge
@@ -395,6 +451,18 @@ pub struct ChallengeData {
     pub writing_type: WritingType,
 }
 
+/// The user-chosen parts of a new custom challenge. The rest of the [`ChallengeData`] is filled
+/// in when the challenge is created
+#[derive(Debug, Clone)]
+pub struct CustomChallenge {
+    pub name: String,
+    pub starts_at: NaiveDate,
+    pub ends_at: NaiveDate,
+    /// The goal, which also sets the unit the challenge counts
+    pub default_goal: Quantity,
+    pub writing_type: WritingType,
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 #[serde(rename_all = "kebab-case", deny_unknown_fields)]
 pub struct DailyAggregateData {
@@ -448,7 +516,8 @@ pub struct GroupData {
     pub plate: Option<String>,
     pub slug: String,
     pub start_dt: Option<DateTime<Utc>>,
-    pub time_zone: Option<String>,
+    /// This group's time zone, if it has one
+    pub time_zone: Option<TimeZoneId>,
     pub updated_at: DateTime<Utc>,
     pub url: Option<String>,
     pub user_id: Option<u64>
@@ -475,8 +544,10 @@ pub struct LocationData {
     pub municipality: Option<String>,
     pub name: String,
     pub neighborhood: Option<String>,
+    /// Text rather than a number, since postal codes outside the US can have letters. Nano
+    /// sometimes sends US ones as numbers
     #[serde(deserialize_with = "de_opt_str_num")]
-    pub postal_code: Option<u64>,
+    pub postal_code: Option<String>,
     pub state: String,
     #[serde(rename = "street1")]
     pub street1: Option<String>,
@@ -621,8 +692,10 @@ pub struct UserData {
 
     pub notifications_viewed_at: DateTime<Utc>,
     pub plate: Option<String>,
+    /// Text rather than a number, since postal codes outside the US can have letters. Nano
+    /// sometimes sends US ones as numbers
     #[serde(deserialize_with = "de_opt_str_num")]
-    pub postal_code: Option<u64>,
+    pub postal_code: Option<String>,
 
     #[serde(flatten)]
     pub privacy_settings: Option<PrivacySettings>,
@@ -635,7 +708,8 @@ pub struct UserData {
     #[serde(flatten)]
     pub stats: StatsInfo,
 
-    pub time_zone: String
+    /// This user's time zone
+    pub time_zone: TimeZoneId
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -809,6 +883,16 @@ pub struct UserBadgeData {
     pub user_id: u64,
 }
 
+/// The badges that can be earned in a single ProjectChallenge, split by whether they have been.
+/// Each list is in the order Nano displays them
+#[derive(Debug)]
+pub struct ChallengeBadges {
+    /// Badges already earned, along with the UserBadge recording each award
+    pub earned: Vec<(BadgeObject, UserBadgeObject)>,
+    /// Badges not yet earned
+    pub unearned: Vec<BadgeObject>,
+}
+
 // This doesn't like deny_unknown_fields, I think due to the custom serialize/deserialize impls
 #[derive(Serialize, Deserialize, Debug)]
 pub struct RelationInfo {
@@ -0,0 +1,409 @@
+use super::*;
+
+use std::collections::BTreeSet;
+use std::fs;
+
+use serde_json::{Map, Value};
+
+use crate::schema::{DriftDetector, DriftReport, GroupDrift, JsonType, KindSchema};
+
+/// What a fixture document should parse into. Attributes not listed are expected to come out
+/// exactly as they went in
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+struct Expected {
+    kind: String,
+    id: u64,
+    #[serde(default)]
+    attributes: Map<String, Value>,
+    #[serde(default)]
+    relations: HashMap<String, String>,
+    #[serde(default)]
+    included: HashMap<String, Vec<u64>>,
+    /// Part of the message parsing should fail with, for documents Nano shouldn't send
+    #[serde(default)]
+    error: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+struct Fixture {
+    document: Value,
+    expected: Expected,
+}
+
+fn fixtures() -> Vec<(String, Fixture)> {
+    let dir = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/objects");
+    let mut out = fs::read_dir(dir)
+        .expect("Couldn't read fixture directory")
+        .map(|entry| {
+            let path = entry.unwrap().path();
+            let name = path.file_stem().unwrap().to_string_lossy().into_owned();
+            let fixture = serde_json::from_slice(&fs::read(&path).unwrap())
+                .unwrap_or_else(|err| panic!("Invalid fixture {}: {}", name, err));
+            (name, fixture)
+        })
+        .collect::<Vec<_>>();
+
+    out.sort_by(|a, b| a.0.cmp(&b.0));
+    out
+}
+
+fn check(fixture: &Fixture) -> Result<(), String> {
+    let expected = &fixture.expected;
+    let resp = serde_json::from_str::<ItemResponse>(&fixture.document.to_string());
+    if let Some(error) = &expected.error {
+        return match resp {
+            Ok(_) => Err(format!("expected an error containing {:?}, but it parsed", error)),
+            Err(err) if err.to_string().contains(error.as_str()) => Ok(()),
+            Err(err) => Err(format!("expected an error containing {:?}, got {}", error, err)),
+        };
+    }
+    let resp = resp.map_err(|err| format!("couldn't parse: {}", err))?;
+    let obj = &resp.data;
+
+    if obj.kind().api_name() != expected.kind {
+        return Err(format!("expected kind {}, got {}", expected.kind, obj.kind().api_name()));
+    }
+    if obj.id() != expected.id {
+        return Err(format!("expected ID {}, got {}", expected.id, obj.id()));
+    }
+
+    let mut attributes = fixture.document["data"]["attributes"].as_object().unwrap().clone();
+    attributes.extend(expected.attributes.clone());
+
+    let parsed = serde_json::to_value(obj).unwrap()["attributes"].take();
+    for (key, val) in &attributes {
+        if parsed.get(key) != Some(val) {
+            return Err(format!("expected attribute {} to be {}, got {:?}", key, val, parsed.get(key)));
+        }
+    }
+    for key in parsed.as_object().unwrap().keys() {
+        if !attributes.contains_key(key) {
+            return Err(format!("got unexpected attribute {}", key));
+        }
+    }
+
+    let (relations, included) = match obj.relationships() {
+        Some(info) => (
+            info.relations.iter()
+                .map(|(kind, link)| (kind.api_name().to_string(), link.related.clone()))
+                .collect(),
+            info.included.iter()
+                .map(|(kind, refs)| (kind.api_name().to_string(), refs.iter().map(|obj_ref| obj_ref.id).collect()))
+                .collect(),
+        ),
+        None => (HashMap::new(), HashMap::new())
+    };
+
+    if relations != expected.relations {
+        return Err(format!("expected relations {:?}, got {:?}", expected.relations, relations));
+    }
+    if included != expected.included {
+        return Err(format!("expected included {:?}, got {:?}", expected.included, included));
+    }
+
+    for obj_ref in resp.data.relationships().iter().flat_map(|info| info.included.values().flatten()) {
+        if fixture.document.get("included").is_some() && resp.get_ref(obj_ref).is_none() {
+            return Err(format!("included object {:?} wasn't found", obj_ref));
+        }
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_object_fixtures() {
+    let failures = fixtures()
+        .iter()
+        .filter_map(|(name, fixture)| check(fixture).err().map(|err| format!("{}: {}", name, err)))
+        .collect::<Vec<_>>();
+
+    assert!(failures.is_empty(), "Fixtures failed:\n{}", failures.join("\n"));
+}
+
+#[test]
+fn test_fixtures_cover_objects() {
+    let covered = fixtures()
+        .into_iter()
+        .map(|(_, fixture)| fixture.expected.kind)
+        .collect::<BTreeSet<_>>();
+
+    let kinds = [
+        NanoKind::Badge, NanoKind::Challenge, NanoKind::DailyAggregate, NanoKind::FavoriteAuthor,
+        NanoKind::FavoriteBook, NanoKind::Genre, NanoKind::Group, NanoKind::GroupExternalLink,
+        NanoKind::Location, NanoKind::NanoMessage, NanoKind::Notification, NanoKind::Page, NanoKind::Post,
+        NanoKind::Project, NanoKind::ProjectSession, NanoKind::StopWatch, NanoKind::Timer, NanoKind::User,
+        NanoKind::WritingLocation, NanoKind::WritingMethod, NanoKind::GroupUser, NanoKind::LocationGroup,
+        NanoKind::ProjectChallenge, NanoKind::UserBadge,
+    ];
+
+    for kind in kinds.iter() {
+        assert!(covered.contains(kind.api_name()), "No fixture for {:?}", kind);
+    }
+}
+
+#[test]
+fn test_typed_fixtures() {
+    let fixtures = fixtures().into_iter().collect::<HashMap<_, _>>();
+    let parse = |name: &str| fixtures[name].document.to_string();
+
+    let timer = serde_json::from_str::<ItemResponse<TimerObject>>(&parse("timer")).unwrap();
+    assert_eq!(timer.data.data.duration, chrono::Duration::minutes(25));
+
+    let location = serde_json::from_str::<ItemResponse<LocationObject>>(&parse("location")).unwrap();
+    assert_eq!(location.data.data.postal_code.as_deref(), Some("98104"));
+    let location = serde_json::from_str::<ItemResponse<LocationObject>>(&parse("location_foreign_postcode")).unwrap();
+    assert_eq!(location.data.data.postal_code.as_deref(), Some("SW1A 1AA"));
+
+    let user = serde_json::from_str::<ItemResponse<UserObject>>(&parse("user")).unwrap();
+    assert!(user.data.data.email_settings.is_none());
+    assert_eq!(user.data.data.stats.word_count, 310522);
+
+    let user = serde_json::from_str::<ItemResponse<UserObject>>(&parse("user_current")).unwrap();
+    let settings = user.data.data.privacy_settings.unwrap();
+    assert_eq!(settings.view_projects, PrivacySetting::Buddies);
+    assert!(user.data.data.email_settings.unwrap().writing_reminders);
+    assert_eq!(user.data.data.registration_path, RegistrationPath::Google);
+
+    let session = serde_json::from_str::<ItemResponse<ProjectSessionObject>>(&parse("project_session_negative")).unwrap();
+    assert_eq!(session.data.data.count, -350);
+
+    let message = serde_json::from_str::<ItemResponse<NanoMessageObject>>(&parse("nano_message")).unwrap();
+    assert_eq!(message.data.data.created_at, "2020-11-01T08:00:00Z".parse::<DateTime<Utc>>().unwrap());
+
+    let project = serde_json::from_str::<ItemResponse<ProjectObject>>(&parse("project")).unwrap();
+    let challenges = &project.data.relationships().as_ref().unwrap().included[&NanoKind::ProjectChallenge];
+    let challenge = project.get_ref(&challenges[0]).unwrap().unwrap_project_challenge();
+    assert_eq!(challenge.data.current_count, 51234);
+}
+
+#[test]
+fn test_str_nums() {
+    #[derive(Deserialize)]
+    struct Nums {
+        #[serde(deserialize_with = "de_str_num")]
+        num: u64,
+        #[serde(deserialize_with = "de_opt_str_num")]
+        opt: Option<i64>,
+    }
+
+    let nums = serde_json::from_str::<Nums>(r#"{"num": "12", "opt": "-3"}"#).unwrap();
+    assert_eq!((nums.num, nums.opt), (12, Some(-3)));
+
+    let nums = serde_json::from_str::<Nums>(r#"{"num": "12", "opt": 7}"#).unwrap();
+    assert_eq!(nums.opt, Some(7));
+    let nums = serde_json::from_str::<Nums>(r#"{"num": "12", "opt": null}"#).unwrap();
+    assert_eq!(nums.opt, None);
+
+    // Only null is missing, anything else that isn't a number, or a string of one, fails
+    for opt in &["\"\"", "\"N/A\"", "7.5", "[7]", "true"] {
+        let res = serde_json::from_str::<Nums>(&format!(r#"{{"num": "0", "opt": {}}}"#, opt));
+        assert!(res.is_err(), "{} should fail", opt);
+    }
+
+    // Text, like postal codes, can be sent as a number too
+    #[derive(Deserialize)]
+    struct Code {
+        #[serde(deserialize_with = "de_opt_str_num")]
+        code: Option<String>,
+    }
+    let code = serde_json::from_str::<Code>(r#"{"code": 98104}"#).unwrap();
+    assert_eq!(code.code.as_deref(), Some("98104"));
+
+    assert!(serde_json::from_str::<Nums>(r#"{"num": 12, "opt": null}"#).is_err());
+    assert!(serde_json::from_str::<Nums>(r#"{"num": "twelve", "opt": null}"#).is_err());
+}
+
+#[test]
+fn test_store_item() {
+    let item = serde_json::from_str::<StoreItem>(
+        r#"{"handle": "mug", "image": {"src": "https://cdn.example.com/mug.png"}, "title": "Mug"}"#
+    ).unwrap();
+    assert_eq!(item.image, "https://cdn.example.com/mug.png");
+
+    assert!(serde_json::from_str::<StoreItem>(r#"{"handle": "mug", "image": "mug.png", "title": "Mug"}"#).is_err());
+}
+
+#[test]
+fn test_relation_links() {
+    let info = serde_json::from_value::<RelationInfo>(serde_json::json!({
+        "projects": {
+            "links": { "self": "/users/1/relationships/projects", "related": "/users/1/projects" },
+            "data": [{ "type": "projects", "id": "5" }]
+        },
+        "user": {
+            "links": { "self": "/projects/5/relationships/user", "related": "/projects/5/user" },
+            "data": null
+        },
+        "unknown-things": {
+            "links": { "self": "/users/1/relationships/unknown-things", "related": "/users/1/unknown-things" },
+            "data": [{ "type": "unknown-things", "id": "1" }]
+        }
+    })).unwrap();
+
+    assert_eq!(info.relations.len(), 2);
+    assert_eq!(info.relations[&NanoKind::User].related, "/projects/5/user");
+    assert_eq!(info.included.len(), 1);
+    assert_eq!(info.included[&NanoKind::Project][0].id, 5);
+
+    assert!(serde_json::from_value::<RelationInfo>(serde_json::json!({
+        "projects": { "links": { "self": "/users/1/relationships/projects" } }
+    })).is_err());
+}
+
+#[test]
+fn test_fixtures_match_schema() {
+    let mut detector = DriftDetector::new();
+    for (_, fixture) in fixtures().into_iter().filter(|(_, fixture)| fixture.expected.error.is_none()) {
+        detector.observe_document(&fixture.document);
+    }
+
+    let report = detector.report();
+    assert!(report.unknown_kinds.is_empty(), "Unknown kinds: {:?}", report.unknown_kinds);
+    for (kind, drift) in &report.kinds {
+        assert!(!drift.has_drift(), "Fixtures for {} don't match the schema: {:#?}", kind, drift);
+    }
+
+    let uncertain = report.kinds["project-challenges"].uncertain
+        .iter()
+        .map(|drift| drift.field.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(uncertain, ["speed", "when", "writing-location"]);
+}
+
+/// Parses a document into the typed Object for its kind, and gets the attributes it serializes
+/// back into
+struct TypedAttributes<'a>(&'a Value);
+
+impl KindVisitor for TypedAttributes<'_> {
+    type Output = Result<BTreeSet<String>, serde_json::Error>;
+
+    fn visit<O>(self) -> Self::Output
+        where
+            O: ObjectInfo + Serialize + DeserializeOwned + std::fmt::Debug
+    {
+        let resp = serde_json::from_str::<ItemResponse<O>>(&self.0.to_string())?;
+        let attributes = serde_json::to_value(&resp.data)?["attributes"].take();
+        Ok(attributes.as_object().into_iter().flat_map(|attrs| attrs.keys().cloned()).collect())
+    }
+}
+
+#[test]
+fn test_schema_matches_types() {
+    let mut typed = HashMap::<NanoKind, BTreeSet<String>>::new();
+    for (name, fixture) in fixtures().into_iter().filter(|(_, fixture)| fixture.expected.error.is_none()) {
+        let kind = NanoKind::from_name(&fixture.expected.kind).unwrap();
+        let keys = visit_kind(kind, TypedAttributes(&fixture.document))
+            .unwrap_or_else(|| panic!("{}: {:?} has no typed Object", name, kind))
+            .unwrap_or_else(|err| panic!("{}: couldn't parse as a typed Object: {}", name, err));
+        typed.entry(kind).or_default().extend(keys);
+    }
+
+    for (kind, keys) in typed {
+        let schema = KindSchema::of(kind).unwrap_or_else(|| panic!("No schema for {:?}", kind));
+        let fields = schema.fields.iter().map(|field| field.name.to_string()).collect::<BTreeSet<_>>();
+        assert_eq!(
+            fields, keys,
+            "Schema for {:?} doesn't match its Data type. Missing from the schema: {:?}, missing from the type: {:?}",
+            kind, keys.difference(&fields).collect::<Vec<_>>(), fields.difference(&keys).collect::<Vec<_>>()
+        );
+    }
+}
+
+#[test]
+fn test_schema_drift() {
+    let fixtures = fixtures().into_iter().collect::<HashMap<_, _>>();
+
+    let mut doc = fixtures["project_challenge"].document.clone();
+    let attributes = doc["data"]["attributes"].as_object_mut().unwrap();
+    attributes.remove("goal");
+    attributes.remove("streak");
+    attributes.insert("speed".to_string(), serde_json::json!("fast"));
+    attributes.insert("sparkle-level".to_string(), serde_json::json!(9000));
+
+    let mut user = fixtures["user_current"].document.clone();
+    let attributes = user["data"]["attributes"].as_object_mut().unwrap();
+    attributes.remove("email-newsletter");
+    attributes.insert("time-zone".to_string(), Value::Null);
+
+    let mut detector = DriftDetector::new();
+    detector.observe_document(&doc);
+    detector.observe_document(&user);
+    detector.observe_object(&serde_json::json!({ "type": "quests", "id": "1", "attributes": {} }));
+    let report = detector.report();
+
+    assert!(report.has_drift() && report.is_breaking());
+    assert_eq!(report.unknown_kinds.iter().collect::<Vec<_>>(), ["quests"]);
+
+    let drift = &report.kinds["project-challenges"];
+    assert_eq!(drift.added.len(), 1);
+    assert_eq!(drift.added[0].field, "sparkle-level");
+    assert_eq!(drift.added[0].observed, [JsonType::Integer]);
+    assert_eq!(drift.added[0].examples, [serde_json::json!(9000)]);
+
+    let removed = drift.removed.iter().map(|drift| (drift.field.as_str(), drift.breaking)).collect::<Vec<_>>();
+    assert_eq!(removed, [("goal", true), ("streak", false)]);
+
+    assert_eq!(drift.changed.len(), 1);
+    assert_eq!(drift.changed[0].field, "speed");
+    assert_eq!(drift.changed[0].expected, [JsonType::Null, JsonType::Integer]);
+    assert_eq!(drift.changed[0].observed, [JsonType::String]);
+
+    let drift = &report.kinds["users"];
+    assert_eq!(drift.changed[0].field, "time-zone");
+    assert!(drift.removed.is_empty());
+    assert_eq!(drift.partial_groups, [GroupDrift {
+        group: "email".to_string(),
+        missing: vec!["email-newsletter".to_string()],
+        objects: 1,
+        breaking: false,
+    }]);
+
+    // The report is machine-readable, and reads back the same
+    let json = serde_json::to_string(&report).unwrap();
+    assert_eq!(serde_json::from_str::<DriftReport>(&json).unwrap(), report);
+}
+
+#[cfg(feature = "tz")]
+#[test]
+fn test_time_zones() {
+    use crate::error::Error;
+    use crate::tz::{self, Tz};
+
+    let fixtures = fixtures().into_iter().collect::<HashMap<_, _>>();
+    let parse = |name: &str| fixtures[name].document.to_string();
+
+    let user = serde_json::from_str::<ItemResponse<UserObject>>(&parse("user")).unwrap().data.data;
+    assert_eq!(user.tz(), Tz::America__Los_Angeles);
+    assert!(matches!(tz::parse("Mars/Olympus_Mons"), Err(Error::UnknownTimeZone(_))));
+
+    // A time zone that isn't known fails when the user is loaded, not when it's used
+    let mut doc = fixtures["user"].document.clone();
+    doc["data"]["attributes"]["time-zone"] = Value::from("Mars/Olympus_Mons");
+    assert!(serde_json::from_str::<ItemResponse<UserObject>>(&doc.to_string()).is_err());
+
+    // Ended early on the 1st in UTC, but still on Halloween in Hawaii
+    let session = serde_json::from_str::<ItemResponse<ProjectSessionObject>>(&parse("project_session")).unwrap().data.data;
+    assert_eq!(session.end.date_naive(), NaiveDate::from_ymd_opt(2020, 11, 1).unwrap());
+    assert_eq!(session.local_date(Tz::Pacific__Honolulu), NaiveDate::from_ymd_opt(2020, 10, 31).unwrap());
+    assert_eq!(user.local(session.end).to_rfc3339(), "2020-11-01T00:45:00-07:00");
+    assert_eq!(session.local_start(Tz::UTC), None);
+
+    // Daylight saving time ended in between the two sessions
+    let session = serde_json::from_str::<ItemResponse<ProjectSessionObject>>(&parse("project_session_negative")).unwrap().data.data;
+    assert_eq!(user.local(session.end).to_rfc3339(), "2020-11-02T01:30:00-08:00");
+    assert_eq!(session.local_date(user.tz()), session.session_date);
+    assert_eq!(session.local_start(Tz::Asia__Tokyo).unwrap().to_rfc3339(), "2020-11-02T17:30:00+09:00");
+
+    // Events without a time zone use the one they're given
+    let group = serde_json::from_str::<ItemResponse<GroupObject>>(&parse("group")).unwrap().data.data;
+    assert_eq!(group.tz(), Some(Tz::America__Los_Angeles));
+    assert_eq!(group.local_start(Tz::UTC), None);
+
+    let event = serde_json::from_str::<ItemResponse<GroupObject>>(&parse("group_event")).unwrap().data.data;
+    assert_eq!(event.tz(), None);
+    let start = event.local_start(Tz::America__Los_Angeles).unwrap();
+    assert_eq!(start.to_rfc3339(), "2020-11-07T10:00:00-08:00");
+    assert_eq!(event.local_end(Tz::UTC).unwrap().to_rfc3339(), "2020-11-07T22:00:00+00:00");
+}
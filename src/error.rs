@@ -1,4 +1,5 @@
-use std::{error, fmt};
+use std::{error, fmt, io};
+use std::time::Duration;
 
 use reqwest::StatusCode;
 use crate::ErrorData;
@@ -11,7 +12,22 @@ pub enum Error {
     /// An error caused by an invalid response from the Nano API
     SimpleNanoError(StatusCode, String),
     /// An error from Nano with multiple complex inner values
-    NanoErrors(Vec<ErrorData>)
+    NanoErrors(Vec<ErrorData>),
+    /// An error caused by a response that couldn't be parsed into the expected type
+    SerdeError(serde_json::Error),
+    /// An error caused by a request that was rejected locally, before being sent to Nano
+    ValidationError(String),
+    /// An error from reading or writing a local file, such as a saved session
+    IoError(io::Error),
+    /// An error caused by being unable to get credentials to log in with
+    CredentialError(String),
+    /// An error caused by Nano asking the client to slow down, with how long Nano asked it to wait
+    /// if it said
+    Throttled(StatusCode, Option<Duration>),
+    /// An error caused by a time zone name that isn't in the IANA time zone database
+    UnknownTimeZone(String),
+    /// An error from replaying a cassette, such as a request that wasn't recorded
+    CassetteError(String),
 }
 
 impl fmt::Display for Error {
@@ -20,10 +36,18 @@ impl fmt::Display for Error {
             Error::ReqwestError(err) => write!(f, "Reqwest Error: {}", err),
             Error::SimpleNanoError(code, message) => write!(f, "NanoWrimo API Error: {} (status code {})", message, code.as_u16()),
             Error::NanoErrors(errs) => {
-                errs.iter().map(|err| {
+                errs.iter().try_for_each(|err| {
                     write!(f, "{} ({}): {} (status code {})", err.title, err.code, err.detail, err.status)
-                }).collect::<Result<_, _>>()
+                })
             },
+            Error::SerdeError(err) => write!(f, "Serde Error: {}", err),
+            Error::ValidationError(message) => write!(f, "Invalid Request: {}", message),
+            Error::IoError(err) => write!(f, "IO Error: {}", err),
+            Error::CredentialError(message) => write!(f, "Credential Error: {}", message),
+            Error::Throttled(code, Some(wait)) => write!(f, "Throttled: retry after {}s (status code {})", wait.as_secs(), code.as_u16()),
+            Error::Throttled(code, None) => write!(f, "Throttled (status code {})", code.as_u16()),
+            Error::UnknownTimeZone(name) => write!(f, "Unknown Time Zone: {}", name),
+            Error::CassetteError(message) => write!(f, "Cassette Error: {}", message),
         }
     }
 }
@@ -34,6 +58,13 @@ impl error::Error for Error {
             Error::ReqwestError(err) => Some(err),
             Error::SimpleNanoError(..) => None,
             Error::NanoErrors(..) => None,
+            Error::SerdeError(err) => Some(err),
+            Error::ValidationError(..) => None,
+            Error::IoError(err) => Some(err),
+            Error::CredentialError(..) => None,
+            Error::Throttled(..) => None,
+            Error::UnknownTimeZone(..) => None,
+            Error::CassetteError(..) => None,
         }
     }
 }
@@ -43,3 +74,15 @@ impl From<reqwest::Error> for Error {
         Error::ReqwestError(err)
     }
 }
+
+impl From<serde_json::Error> for Error {
+    fn from(err: serde_json::Error) -> Error {
+        Error::SerdeError(err)
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Error {
+        Error::IoError(err)
+    }
+}
@@ -0,0 +1,268 @@
+//! Ranked leaderboards for the members of a group taking part in a Challenge, such as a region
+//! or a group of buddies during NaNoWriMo.
+//!
+//! A [`Leaderboard`] remembers who the members are and what it last saw for each of them, so it
+//! can be refreshed as often as needed, and only asks Nano for the daily counts of members whose
+//! count has changed. Members are ranked by how far through their goal they are, so members
+//! counting hours and words can share a leaderboard. Members who don't let the group see their
+//! projects are left out.
+//!
+//! ```no_run
+//! # async fn example(client: &nanowrimo::NanoClient) -> Result<(), nanowrimo::Error> {
+//! use nanowrimo::leaderboard::Leaderboard;
+//!
+//! let mut board = Leaderboard::new(1234, 56).concurrency(8);
+//! board.refresh(client, chrono::Utc::now().date_naive()).await?;
+//! for (rank, entry) in board.entries().iter().enumerate() {
+//!     println!("{}. {}: {} ({:.1}%)", rank + 1, entry.name, entry.count, entry.percent());
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::analytics::{StreakCounter, StreakRules};
+use crate::client::{fetch_all, NanoClient};
+use crate::data::*;
+use crate::enums::{GroupType, InvitationStatus, PrivacySetting};
+use crate::error::Error;
+use crate::kind::NanoKind;
+use crate::quantity::Quantity;
+
+use std::collections::HashMap;
+
+use chrono::{NaiveDate, Utc};
+use reqwest::StatusCode;
+
+#[cfg(test)]
+mod tests;
+
+/// How many members are fetched at once, unless set otherwise
+const DEFAULT_CONCURRENCY: usize = 4;
+
+/// A member of the group, whose projects the group is allowed to see
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Member {
+    user_id: u64,
+    name: String,
+}
+
+/// One member's place on a leaderboard
+#[derive(Debug, Clone, PartialEq)]
+pub struct LeaderboardEntry {
+    pub user_id: u64,
+    /// The member's display name
+    pub name: String,
+    /// The ProjectChallenge the member is taking part with. If they have several, the one with
+    /// the highest count
+    pub project_challenge_id: u64,
+    pub project_id: u64,
+    /// The count so far, in the unit of the challenge
+    pub count: Quantity,
+    pub goal: Quantity,
+    /// The count written on the day the leaderboard was last refreshed
+    pub today: Quantity,
+    /// How many days in a row, up to the day the leaderboard was last refreshed, the member has
+    /// written on
+    pub streak: u64,
+    as_of: NaiveDate,
+}
+
+impl LeaderboardEntry {
+    /// How far through the goal the member is, as a percentage. Can be more than 100
+    pub fn percent(&self) -> f64 {
+        self.count.ratio(&self.goal).unwrap_or(0.0) * 100.0
+    }
+}
+
+/// A leaderboard for the members of a Buddies, WritingGroup, or Region group, in a single
+/// Challenge. Members who haven't joined the Challenge aren't ranked
+#[derive(Debug, Clone)]
+pub struct Leaderboard {
+    group_id: u64,
+    challenge_id: u64,
+    concurrency: usize,
+    members: Option<Vec<Member>>,
+    entries: Vec<LeaderboardEntry>,
+}
+
+/// Get the ProjectChallenge a user is ranked by in a Challenge, the one with the highest count,
+/// or None if they aren't taking part
+pub(crate) async fn fetch_project_challenge(client: &NanoClient, user_id: u64, challenge_id: u64) -> Result<Option<ProjectChallengeObject>, Error> {
+    let filter = [("user_id", user_id), ("challenge_id", challenge_id)];
+    Ok(client.get_all_typed::<ProjectChallengeObject>(NanoKind::ProjectChallenge, &filter)
+        .await?
+        .data
+        .into_iter()
+        .max_by_key(|pc| pc.data.current_count))
+}
+
+/// Whether an error is Nano refusing to show a user's projects, as it does for users who hide
+/// them from whoever is asking
+pub(crate) fn projects_denied(err: &Error) -> bool {
+    matches!(err, Error::SimpleNanoError(StatusCode::FORBIDDEN, _) | Error::SimpleNanoError(StatusCode::NOT_FOUND, _))
+}
+
+/// Get the daily counts of a ProjectChallenge, leaving out any in a different unit
+pub(crate) async fn fetch_daily(client: &NanoClient, pc: &ProjectChallengeObject) -> Result<Vec<DailyAggregateData>, Error> {
+    Ok(client.daily_aggregates(pc.id()).await?.data
+        .into_iter()
+        .map(|agg| agg.data)
+        .filter(|agg| agg.unit_type == pc.data.unit_type)
+        .collect())
+}
+
+pub(crate) fn projects_visible(user: &UserData, group_type: GroupType) -> bool {
+    // Nano only sends privacy settings for the logged in user. Anyone else is assumed visible
+    // here, and treated as hidden later if Nano won't list their projects
+    match user.privacy_settings.as_ref().map(|settings| settings.view_projects) {
+        Some(PrivacySetting::Private) => false,
+        Some(PrivacySetting::Buddies) => group_type == GroupType::Buddies,
+        Some(PrivacySetting::Anyone) | None => true,
+    }
+}
+
+impl Leaderboard {
+    /// Create an empty leaderboard for a group and a Challenge. Nothing is fetched until it's
+    /// refreshed
+    pub fn new(group_id: u64, challenge_id: u64) -> Leaderboard {
+        Leaderboard {
+            group_id,
+            challenge_id,
+            concurrency: DEFAULT_CONCURRENCY,
+            members: None,
+            entries: Vec::new(),
+        }
+    }
+
+    /// Set how many members are fetched at once. Requests still go through the client's rate
+    /// limit, if it has one
+    pub fn concurrency(mut self, concurrency: usize) -> Leaderboard {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+
+    /// The ID of the group this ranks
+    pub fn group_id(&self) -> u64 {
+        self.group_id
+    }
+
+    /// The ID of the Challenge this ranks
+    pub fn challenge_id(&self) -> u64 {
+        self.challenge_id
+    }
+
+    /// The ranked entries, furthest through their goal first, as of the last refresh
+    pub fn entries(&self) -> &[LeaderboardEntry] {
+        &self.entries
+    }
+
+    /// The rank of a member, starting from 1, if they're on the leaderboard
+    pub fn rank_of(&self, user_id: u64) -> Option<usize> {
+        self.entries.iter()
+            .position(|entry| entry.user_id == user_id)
+            .map(|pos| pos + 1)
+    }
+
+    /// Fetch the members of the group again, such as after someone joins. Members whose
+    /// projects the group can't see are left out. Fails if the group isn't a Buddies,
+    /// WritingGroup, or Region group
+    pub async fn reload_members(&mut self, client: &NanoClient) -> Result<(), Error> {
+        let group = client.get_typed::<GroupObject>(NanoKind::Group, self.group_id).await?.data;
+        let group_type = group.data.group_type;
+        if !matches!(group_type, GroupType::Buddies | GroupType::WritingGroup | GroupType::Region) {
+            return Err(Error::ValidationError(format!(
+                "Leaderboards are only for buddies, writing groups and regions, not {:?} groups", group_type
+            )))
+        }
+
+        let user_ids = client.get_all_typed::<GroupUserObject>(NanoKind::GroupUser, &[("group_id", self.group_id)])
+            .await?
+            .data
+            .into_iter()
+            .filter(|member| member.data.invitation_accepted == InvitationStatus::Accepted)
+            .filter(|member| member.data.exit_at.is_none())
+            .map(|member| member.data.user_id)
+            .collect::<Vec<_>>();
+
+        let users = fetch_all(user_ids, self.concurrency, |user_id| async move {
+            client.get_typed::<UserObject>(NanoKind::User, user_id).await
+        }).await?;
+
+        let mut members = users.into_iter()
+            .map(|user| user.data)
+            .filter(|user| projects_visible(&user.data, group_type))
+            .map(|user| Member { user_id: user.id(), name: user.data.name })
+            .collect::<Vec<_>>();
+        members.sort_by_key(|member| member.user_id);
+        members.dedup();
+
+        self.members = Some(members);
+        Ok(())
+    }
+
+    async fn fetch_entry(&self, client: &NanoClient, member: &Member, today: NaiveDate, previous: Option<&LeaderboardEntry>) -> Result<Option<LeaderboardEntry>, Error> {
+        let pc = match fetch_project_challenge(client, member.user_id, self.challenge_id).await {
+            Ok(Some(pc)) => pc,
+            Ok(None) => return Ok(None),
+            Err(err) if projects_denied(&err) => return Ok(None),
+            Err(err) => return Err(err)
+        };
+
+        let unit_type = pc.data.unit_type;
+        let count = Quantity::from_count(unit_type, pc.data.current_count as i64);
+        let goal = Quantity::from_count(unit_type, pc.data.goal as i64);
+
+        // Nothing has changed since the last refresh, so the daily counts haven't either
+        if let Some(prev) = previous {
+            if prev.project_challenge_id == pc.id() && prev.count == count && prev.as_of == today {
+                return Ok(Some(LeaderboardEntry { name: member.name.clone(), goal, ..prev.clone() }))
+            }
+        }
+
+        let aggregates = fetch_daily(client, &pc).await?;
+
+        let mut counter = StreakCounter::with_rules(Utc, StreakRules::any_writing(unit_type));
+        counter.add_aggregates(&aggregates);
+
+        let written_today = aggregates.iter().filter(|agg| agg.day == today).map(|agg| agg.count).sum::<u64>();
+
+        Ok(Some(LeaderboardEntry {
+            user_id: member.user_id,
+            name: member.name.clone(),
+            project_challenge_id: pc.id(),
+            project_id: pc.data.project_id,
+            count,
+            goal,
+            today: Quantity::from_count(unit_type, written_today as i64),
+            streak: counter.streaks(today).current.map_or(0, |streak| streak.days),
+            as_of: today,
+        }))
+    }
+
+    /// Fetch the latest counts for every member, and rank them again. Members are fetched the
+    /// first time, and kept after that. Daily counts are only fetched for members whose count
+    /// changed, or if `today` has
+    pub async fn refresh(&mut self, client: &NanoClient, today: NaiveDate) -> Result<(), Error> {
+        if self.members.is_none() {
+            self.reload_members(client).await?;
+        }
+
+        let members = self.members.clone().unwrap_or_default();
+        let previous = self.entries.iter()
+            .map(|entry| (entry.user_id, entry))
+            .collect::<HashMap<_, _>>();
+
+        let this = &*self;
+        let entries = fetch_all(members, self.concurrency, |member| {
+            let previous = previous.get(&member.user_id).copied();
+            async move { this.fetch_entry(client, &member, today, previous).await }
+        }).await?;
+
+        let mut entries = entries.into_iter().flatten().collect::<Vec<_>>();
+        // Counts in different units can't be compared, but how far through their goals they are can
+        entries.sort_by(|a, b| b.percent().total_cmp(&a.percent()).then_with(|| a.name.cmp(&b.name)));
+
+        self.entries = entries;
+        Ok(())
+    }
+}
@@ -0,0 +1,96 @@
+use super::*;
+use crate::mock::MockServer;
+use crate::mock::fixtures::*;
+
+use serde_json::json;
+
+fn aggregate_requests(server: &MockServer) -> usize {
+    server.requests()
+        .iter()
+        .filter(|(_, path)| path.ends_with("daily-aggregates"))
+        .count()
+}
+
+#[tokio::test]
+async fn test_leaderboard() {
+    let server = MockServer::new();
+    let writer = server.add_user("writer", "hunter2");
+    let alice = server.add_user("alice", "pass");
+    let hidden = server.add_user("hidden", "pass");
+    let buddies_only = server.add_user("buddies", "pass");
+    let left = server.add_user("left", "pass");
+    let invited = server.add_user("invited", "pass");
+    let secret = server.add_user("secret", "pass");
+    let client = server.user_client("writer", "hunter2").await.unwrap();
+
+    server.update(NanoKind::User, hidden, privacy(0));
+    server.update(NanoKind::User, buddies_only, privacy(1));
+    // Nano doesn't send the privacy settings of other users, only refuses to list their projects
+    server.hide_projects(secret);
+
+    let region = group(&server, "region");
+    for user in &[writer, alice, hidden, buddies_only, secret] {
+        join(&server, region, *user, 1, None);
+    }
+    join(&server, region, left, 1, Some("2020-11-05T00:00:00Z"));
+    join(&server, region, invited, 0, None);
+
+    enroll(&server, writer, 12000, &[(9, 2000), (10, 1000)]);
+    enroll(&server, alice, 20000, &[(7, 5000), (8, 5000), (9, 5000), (10, 5000)]);
+    // Only the ProjectChallenge with the highest count is ranked
+    enroll(&server, alice, 300, &[]);
+    for user in &[hidden, buddies_only, left, invited, secret] {
+        enroll(&server, *user, 40000, &[]);
+    }
+
+    let mut board = Leaderboard::new(region, CHALLENGE).concurrency(2);
+    board.refresh(&client, nov(10)).await.expect("Couldn't refresh a leaderboard");
+
+    let entries = board.entries();
+    assert_eq!(entries.iter().map(|entry| entry.name.as_str()).collect::<Vec<_>>(), vec!["alice", "writer"]);
+    assert_eq!((entries[0].count, entries[0].today, entries[0].streak), (Quantity::words(20000), Quantity::words(5000), 4));
+    assert_eq!(entries[0].percent(), 40.0);
+    assert_eq!((entries[1].count, entries[1].today, entries[1].streak), (Quantity::words(12000), Quantity::words(1000), 2));
+    assert_eq!(board.rank_of(writer), Some(2));
+    assert_eq!(board.rank_of(hidden), None);
+    assert_eq!(board.rank_of(secret), None);
+    assert_eq!(aggregate_requests(&server), 2);
+
+    // Refreshing again only fetches daily counts for members whose count changed
+    let pc = enroll(&server, writer, 25000, &[(9, 2000), (10, 14000)]);
+    board.refresh(&client, nov(10)).await.unwrap();
+    assert_eq!(aggregate_requests(&server), 3);
+    assert_eq!(board.rank_of(writer), Some(1));
+    assert_eq!((board.entries()[0].project_challenge_id, board.entries()[0].today), (pc, Quantity::words(14000)));
+
+    // A new day fetches everyone again
+    board.refresh(&client, nov(11)).await.unwrap();
+    assert_eq!(aggregate_requests(&server), 5);
+    assert_eq!((board.entries()[1].today, board.entries()[1].streak), (Quantity::words(0), 4));
+
+    // Buddies can see projects shared only with buddies
+    let buddies = group(&server, "buddies");
+    join(&server, buddies, writer, 1, None);
+    join(&server, buddies, buddies_only, 1, None);
+    let mut board = Leaderboard::new(buddies, CHALLENGE);
+    board.refresh(&client, nov(10)).await.unwrap();
+    assert_eq!(board.entries().iter().map(|entry| entry.user_id).collect::<Vec<_>>(), vec![buddies_only, writer]);
+
+    // Members writing in hours are counted in hours, and any hours written keep a streak going.
+    // They're ranked against members writing words by how far through their goal they are
+    let writing_group = group(&server, "writing group");
+    let timed = server.add_user("timed", "pass");
+    join(&server, writing_group, timed, 1, None);
+    join(&server, writing_group, alice, 1, None);
+    enroll_with(&server, timed, json!({ "current-count": 30, "goal": 40, "unit-type": 1 }), &[(9, 2), (10, 1)]);
+    let mut board = Leaderboard::new(writing_group, CHALLENGE);
+    board.refresh(&client, nov(10)).await.unwrap();
+    assert_eq!(board.entries().iter().map(|entry| entry.user_id).collect::<Vec<_>>(), vec![timed, alice]);
+    let entry = &board.entries()[0];
+    assert_eq!((entry.count, entry.goal, entry.today, entry.streak), (Quantity::hours(30), Quantity::hours(40), Quantity::hours(1), 2));
+    assert_eq!(entry.percent(), 75.0);
+
+    let event = group(&server, "event");
+    let res = Leaderboard::new(event, CHALLENGE).refresh(&client, nov(10)).await;
+    assert!(matches!(res, Err(Error::ValidationError(_))));
+}
@@ -10,9 +10,30 @@ mod enums;
 pub mod data;
 pub mod error;
 pub mod client;
+pub mod credentials;
+pub mod session;
+pub mod middleware;
+pub mod transport;
+pub mod cassette;
+pub mod schema;
+pub mod analytics;
+pub mod leaderboard;
+pub mod region;
+pub mod quantity;
+#[cfg(feature = "blocking")]
+pub mod blocking;
+#[cfg(feature = "tz")]
+pub mod tz;
+#[cfg(any(test, feature = "mock"))]
+pub mod mock;
 
 pub use kind::NanoKind;
 pub use enums::*;
 pub use data::*;
 pub use error::Error;
-pub use client::NanoClient;
+pub use quantity::Quantity;
+pub use client::{NanoClient, NanoClientBuilder};
+pub use session::{Session, SessionState, SessionStore};
+pub use credentials::{CredentialProvider, Credentials, Secret};
+pub use middleware::Middleware;
+pub use transport::HttpTransport;
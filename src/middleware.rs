@@ -0,0 +1,137 @@
+//! Hooks into every request a [`NanoClient`](crate::NanoClient) sends, for adding headers,
+//! logging, metrics, or faking responses in tests. Middleware is registered with
+//! [`NanoClientBuilder::middleware`](crate::NanoClientBuilder::middleware).
+//!
+//! Middleware wraps the request like layers: the first registered sees the request first, and
+//! the response last. Every attempt at a request passes through the middleware, including retries
+//! and logging in.
+
+use crate::error::Error;
+
+use std::fmt;
+use std::rc::Rc;
+use std::sync::Arc;
+
+use reqwest::{Method, Request, StatusCode, Url};
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+
+/// The parts of a request that middleware can inspect and change, after it's been built. The
+/// request is sent as it is after every middleware has seen it
+#[derive(Debug, Clone)]
+pub struct RequestInfo {
+    /// The method of the request
+    pub method: Method,
+    /// The full URL of the request, including the query
+    pub url: Url,
+    /// The headers of the request. The `Authorization` header is marked sensitive, so it's
+    /// redacted from `Debug` output
+    pub headers: HeaderMap,
+    /// The body of the request, or None if it has no body
+    pub body: Option<Vec<u8>>,
+}
+
+impl RequestInfo {
+    pub(crate) fn from_request(req: &Request) -> RequestInfo {
+        RequestInfo {
+            method: req.method().clone(),
+            url: req.url().clone(),
+            headers: req.headers().clone(),
+            body: req.body().and_then(|body| body.as_bytes()).map(|body| body.to_vec()),
+        }
+    }
+}
+
+/// A response received from Nano, or made up by a middleware, before it's parsed
+#[derive(Debug, Clone)]
+pub struct RawResponse {
+    /// The status of the response
+    pub status: StatusCode,
+    /// The headers of the response
+    pub headers: HeaderMap,
+    /// The body of the response, usually JSON
+    pub body: Vec<u8>,
+}
+
+impl RawResponse {
+    /// Create a new response with no headers
+    pub fn new<B: Into<Vec<u8>>>(status: StatusCode, body: B) -> RawResponse {
+        RawResponse { status, headers: HeaderMap::new(), body: body.into() }
+    }
+}
+
+/// A hook into the requests sent by a client. All methods do nothing by default, so
+/// implementations only need the ones they use
+pub trait Middleware: fmt::Debug {
+    /// Inspect or change a request before it's sent, such as its method, URL, headers or body.
+    /// Returning an error fails the request with that error, without sending it
+    fn on_request(&self, _req: &mut RequestInfo) -> Result<(), Error> {
+        Ok(())
+    }
+
+    /// Answer a request without sending it, with either a response or an error. If a middleware
+    /// answers, the request isn't seen by any middleware registered after it, and a response is
+    /// only seen by those registered before it
+    fn intercept(&self, _req: &RequestInfo) -> Option<Result<RawResponse, Error>> {
+        None
+    }
+
+    /// Inspect or change a response before it's parsed. Returning an error fails the request
+    /// with that error, and skips the remaining middleware
+    fn on_response(&self, _req: &RequestInfo, _resp: &mut RawResponse) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+impl<M: Middleware + ?Sized> Middleware for Rc<M> {
+    fn on_request(&self, req: &mut RequestInfo) -> Result<(), Error> {
+        (**self).on_request(req)
+    }
+
+    fn intercept(&self, req: &RequestInfo) -> Option<Result<RawResponse, Error>> {
+        (**self).intercept(req)
+    }
+
+    fn on_response(&self, req: &RequestInfo, resp: &mut RawResponse) -> Result<(), Error> {
+        (**self).on_response(req, resp)
+    }
+}
+
+impl<M: Middleware + ?Sized> Middleware for Arc<M> {
+    fn on_request(&self, req: &mut RequestInfo) -> Result<(), Error> {
+        (**self).on_request(req)
+    }
+
+    fn intercept(&self, req: &RequestInfo) -> Option<Result<RawResponse, Error>> {
+        (**self).intercept(req)
+    }
+
+    fn on_response(&self, req: &RequestInfo, resp: &mut RawResponse) -> Result<(), Error> {
+        (**self).on_response(req, resp)
+    }
+}
+
+/// A [`Middleware`] that adds a header to every request
+#[derive(Debug, Clone)]
+pub struct DefaultHeader {
+    name: String,
+    value: String,
+}
+
+impl DefaultHeader {
+    /// Add a header with a given name and value
+    pub fn new(name: &str, value: &str) -> DefaultHeader {
+        DefaultHeader { name: name.to_string(), value: value.to_string() }
+    }
+}
+
+impl Middleware for DefaultHeader {
+    fn on_request(&self, req: &mut RequestInfo) -> Result<(), Error> {
+        let name = HeaderName::from_bytes(self.name.as_bytes())
+            .map_err(|_| Error::ValidationError(format!("Invalid header name: {}", self.name)))?;
+        let value = HeaderValue::from_str(&self.value)
+            .map_err(|_| Error::ValidationError(format!("Invalid value for header {}", self.name)))?;
+
+        req.headers.insert(name, value);
+        Ok(())
+    }
+}
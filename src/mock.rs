@@ -0,0 +1,759 @@
+//! An in-process imitation of the Nano API, for testing code built on this crate without network
+//! access or real credentials. Requires the `mock` feature.
+//!
+//! The mock understands logging in and out, `users/current`, user search, sponsor offers, store
+//! items, and the generic routes shared by every [`NanoKind`]: listing (with `filter`, `include`
+//! and `page`), getting by ID or slug, creating, updating, deleting, and adding or removing
+//! relationship links. Avatar and cover image uploads are accepted, and replace the image URL with
+//! a placeholder. It doesn't attempt to copy Nano's permission rules, beyond requiring a log-in for
+//! anything that modifies data, and hiding the projects of users set with
+//! [`MockServer::hide_projects`].
+//!
+//! Failures, such as rate limiting or server errors, can be queued with
+//! [`MockServer::fail_next`], to test how clients handle them.
+//!
+//! A server can either listen on a local port, with [`MockServer::start`], or only answer
+//! requests in memory, through a [`MockTransport`], with [`MockServer::new`]. In memory, no
+//! sockets are bound, and clients reach the server without any networking.
+
+use crate::{NanoClient, NanoKind};
+use crate::error::Error;
+use crate::middleware::{RawResponse, RequestInfo};
+use crate::transport::{HttpTransport, TransportFuture};
+
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::convert::Infallible;
+use std::fmt;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use hyper::{Body, HeaderMap, Method, Request, Response, Server, StatusCode};
+use hyper::header::HeaderValue;
+use hyper::service::{make_service_fn, service_fn};
+use serde_json::{json, Map, Value};
+
+#[cfg(test)]
+pub(crate) mod fixtures;
+
+struct MockObject {
+    attributes: Map<String, Value>,
+    relations: HashMap<NanoKind, Vec<u64>>,
+}
+
+#[derive(Default)]
+struct MockState {
+    next_id: u64,
+    logins: HashMap<String, (String, u64)>,
+    sessions: HashMap<String, u64>,
+    objects: HashMap<(NanoKind, u64), MockObject>,
+    log: Vec<(Method, String)>,
+    failures: VecDeque<(StatusCode, Option<u64>)>,
+    store_items: Vec<Value>,
+    waited: Duration,
+    hidden_projects: HashSet<u64>,
+}
+
+struct MockResponse {
+    status: StatusCode,
+    body: Option<Value>,
+    retry_after: Option<u64>,
+}
+
+impl MockResponse {
+    fn ok(body: Value) -> MockResponse {
+        MockResponse { status: StatusCode::OK, body: Some(body), retry_after: None }
+    }
+
+    fn no_content() -> MockResponse {
+        MockResponse { status: StatusCode::NO_CONTENT, body: None, retry_after: None }
+    }
+
+    fn error(status: StatusCode, message: &str) -> MockResponse {
+        MockResponse { status, body: Some(json!({ "error": message })), retry_after: None }
+    }
+}
+
+fn value_str(val: &Value) -> String {
+    match val {
+        Value::String(str) => str.clone(),
+        other => other.to_string(),
+    }
+}
+
+fn parse_refs(data: &Value) -> Vec<(String, u64)> {
+    let refs = match data {
+        Value::Array(refs) => refs.iter().collect(),
+        Value::Object(_) => vec![data],
+        _ => Vec::new(),
+    };
+
+    refs.into_iter()
+        .filter_map(|obj_ref| {
+            let kind = obj_ref.get("type")?.as_str()?.to_string();
+            let id = value_str(obj_ref.get("id")?).parse().ok()?;
+            Some((kind, id))
+        })
+        .collect()
+}
+
+fn parse_multipart<'a>(content_type: &str, body: &'a [u8]) -> Option<(String, String, &'a [u8])> {
+    let boundary = format!("--{}", content_type.split("boundary=").nth(1)?);
+    let start = find(body, boundary.as_bytes())? + boundary.len();
+    let part = &body[start..];
+    let end = find(part, boundary.as_bytes())?;
+    let part = &part[..end];
+
+    let head_end = find(part, b"\r\n\r\n")?;
+    let head = std::str::from_utf8(&part[..head_end]).ok()?;
+    let content = part[head_end + 4..].strip_suffix(b"\r\n")?;
+
+    let mut name = None;
+    let mut part_type = None;
+    for line in head.lines() {
+        let lower = line.to_ascii_lowercase();
+        if lower.starts_with("content-disposition:") {
+            name = line.split("name=\"").nth(1).and_then(|rest| rest.split('"').next());
+        } else if lower.starts_with("content-type:") {
+            part_type = line.split(':').nth(1).map(str::trim);
+        }
+    }
+
+    Some((name?.to_string(), part_type?.to_string(), content))
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|window| window == needle)
+}
+
+fn default_user(identifier: &str) -> Value {
+    json!({
+        "admin-level": 0,
+        "avatar": null,
+        "bio": null,
+        "confirmed-at": "2020-01-01T00:00:00Z",
+        "created-at": "2020-01-01T00:00:00Z",
+        "discourse-username": null,
+        "email": null,
+        "halo": false,
+        "laurels": 0,
+        "location": null,
+        "name": identifier,
+        "notifications-viewed-at": "2020-01-01T00:00:00Z",
+        "plate": null,
+        "postal-code": null,
+        "registration-path": "email",
+        "setting-session-count-by-session": 0,
+        "setting-session-more-info": false,
+        "slug": identifier,
+        "stats-projects": 0,
+        "stats-projects-enabled": false,
+        "stats-streak": 0,
+        "stats-streak-enabled": false,
+        "stats-word-count": 0,
+        "stats-word-count-enabled": false,
+        "stats-wordiest": 0,
+        "stats-wordiest-enabled": false,
+        "stats-writing-pace": null,
+        "stats-writing-pace-enabled": false,
+        "stats-years-done": null,
+        "stats-years-enabled": false,
+        "stats-years-won": null,
+        "time-zone": "UTC"
+    })
+}
+
+impl MockState {
+    fn insert(&mut self, kind: NanoKind, attributes: Value) -> u64 {
+        self.next_id += 1;
+        let attributes = match attributes {
+            Value::Object(map) => map,
+            _ => Map::new(),
+        };
+
+        self.objects.insert((kind, self.next_id), MockObject { attributes, relations: HashMap::new() });
+        self.next_id
+    }
+
+    fn find_id(&self, kind: NanoKind, id: &str) -> Option<u64> {
+        if let Ok(id) = id.parse() {
+            return if self.objects.contains_key(&(kind, id)) { Some(id) } else { None };
+        }
+
+        // Pages have no slug, and are found by their URL instead
+        let field = if kind == NanoKind::Page { "url" } else { "slug" };
+        self.objects.iter()
+            .find(|((obj_kind, _), obj)| {
+                *obj_kind == kind && obj.attributes.get(field).and_then(Value::as_str) == Some(id)
+            })
+            .map(|((_, obj_id), _)| *obj_id)
+    }
+
+    fn render(&self, kind: NanoKind, id: u64) -> Value {
+        let obj = &self.objects[&(kind, id)];
+        let this = format!("/{}/{}", kind.api_name(), id);
+
+        let mut out = json!({
+            "type": kind.api_name(),
+            "id": id.to_string(),
+            "attributes": obj.attributes,
+            "links": { "self": this },
+        });
+
+        if !obj.relations.is_empty() {
+            let relations = obj.relations.iter()
+                .map(|(rel, ids)| {
+                    let data = ids.iter()
+                        .map(|id| json!({ "type": rel.api_name(), "id": id.to_string() }))
+                        .collect::<Vec<_>>();
+
+                    (rel.api_name().to_string(), json!({
+                        "links": {
+                            "self": format!("{}/relationships/{}", this, rel.api_name()),
+                            "related": format!("{}/{}", this, rel.api_name()),
+                        },
+                        "data": data,
+                    }))
+                })
+                .collect::<Map<_, _>>();
+
+            out["relationships"] = Value::Object(relations);
+        }
+
+        out
+    }
+
+    fn included(&self, found: &[(NanoKind, u64)], query: &[(String, String)]) -> Option<Value> {
+        let include = query.iter().find(|(key, _)| key == "include")?;
+        let kinds = include.1.split(',')
+            .filter_map(|name| NanoKind::from_name(name).ok())
+            .collect::<Vec<_>>();
+
+        let mut included = Vec::new();
+        for (kind, id) in found {
+            let obj = &self.objects[&(*kind, *id)];
+            for rel in &kinds {
+                for rel_id in obj.relations.get(rel).into_iter().flatten() {
+                    if self.objects.contains_key(&(*rel, *rel_id)) && !included.contains(&(*rel, *rel_id)) {
+                        included.push((*rel, *rel_id));
+                    }
+                }
+            }
+        }
+
+        Some(Value::Array(included.into_iter().map(|(kind, id)| self.render(kind, id)).collect()))
+    }
+
+    fn respond_many(&self, mut found: Vec<(NanoKind, u64)>, query: &[(String, String)]) -> MockResponse {
+        found.sort_by_key(|(_, id)| *id);
+
+        let mut out = json!({
+            "data": found.iter().map(|(kind, id)| self.render(*kind, *id)).collect::<Vec<_>>()
+        });
+        if let Some(included) = self.included(&found, query) {
+            out["included"] = included;
+        }
+
+        MockResponse::ok(out)
+    }
+
+    fn respond_one(&self, kind: NanoKind, id: u64, query: &[(String, String)]) -> MockResponse {
+        let mut out = json!({ "data": self.render(kind, id) });
+        if let Some(included) = self.included(&[(kind, id)], query) {
+            out["included"] = included;
+        }
+
+        MockResponse::ok(out)
+    }
+
+    /// Whether a listing is of the projects of a user who hides them from the requester
+    fn projects_hidden(&self, kind: NanoKind, query: &[(String, String)], user: Option<u64>) -> bool {
+        if !matches!(kind, NanoKind::Project | NanoKind::ProjectChallenge) {
+            return false;
+        }
+        query.iter()
+            .filter(|(key, _)| key == "filter[user_id]")
+            .filter_map(|(_, val)| val.parse::<u64>().ok())
+            .any(|owner| self.hidden_projects.contains(&owner) && user != Some(owner))
+    }
+
+    fn collection(&self, kind: NanoKind, query: &[(String, String)]) -> MockResponse {
+        let filters = query.iter()
+            .filter_map(|(key, val)| {
+                let attr = key.strip_prefix("filter[")?.strip_suffix(']')?;
+                Some((attr.replace('_', "-"), val))
+            })
+            .collect::<Vec<_>>();
+
+        let mut found = self.objects.iter()
+            .filter(|((obj_kind, _), obj)| {
+                *obj_kind == kind && filters.iter().all(
+                    |(attr, val)| obj.attributes.get(attr).map(value_str).as_ref() == Some(*val)
+                )
+            })
+            .map(|(key, _)| *key)
+            .collect::<Vec<_>>();
+
+        let page_param = |name: &str| query.iter()
+            .find(|(key, _)| key == name)
+            .and_then(|(_, val)| val.parse::<usize>().ok());
+        if let Some(size) = page_param("page[size]") {
+            let number = page_param("page[number]").unwrap_or(1).max(1);
+            found.sort_by_key(|(_, id)| *id);
+            found = found.into_iter().skip((number - 1) * size).take(size).collect();
+        }
+
+        self.respond_many(found, query)
+    }
+
+    fn search(&self, query: &[(String, String)]) -> MockResponse {
+        let name = query.iter()
+            .find(|(key, _)| key == "q")
+            .map(|(_, val)| val.to_lowercase())
+            .unwrap_or_default();
+
+        let found = self.objects.iter()
+            .filter(|((kind, _), obj)| {
+                *kind == NanoKind::User && obj.attributes.get("name")
+                    .and_then(Value::as_str)
+                    .is_some_and(|user| user.to_lowercase().contains(&name))
+            })
+            .map(|(key, _)| *key)
+            .collect();
+
+        self.respond_many(found, &[])
+    }
+
+    /// Posts with an offer code, in order of ID
+    fn offers(&self) -> Vec<Value> {
+        let mut found = self.objects.iter()
+            .filter(|((kind, _), obj)| {
+                *kind == NanoKind::Post && obj.attributes.get("offer-code").is_some_and(|code| !code.is_null())
+            })
+            .map(|((_, id), _)| *id)
+            .collect::<Vec<_>>();
+        found.sort_unstable();
+
+        found.into_iter()
+            .map(|id| json!({ "data": self.render(NanoKind::Post, id) }))
+            .collect()
+    }
+
+    fn sign_in(&mut self, body: &Value) -> MockResponse {
+        let identifier = body.get("identifier").and_then(Value::as_str).unwrap_or("");
+        let password = body.get("password").and_then(Value::as_str).unwrap_or("");
+
+        match self.logins.get(identifier) {
+            Some((expected, id)) if expected == password => {
+                let id = *id;
+                let token = format!("mock-token-{}-{}", id, self.log.len());
+                self.sessions.insert(token.clone(), id);
+                MockResponse::ok(json!({ "auth_token": token }))
+            },
+            _ => MockResponse::error(StatusCode::UNAUTHORIZED, "Invalid identifier or password")
+        }
+    }
+
+    fn create(&mut self, kind: NanoKind, body: &Value) -> MockResponse {
+        let data = &body["data"];
+        if data["type"].as_str() != Some(kind.api_name()) {
+            return MockResponse::error(StatusCode::CONFLICT, "Object type doesn't match endpoint");
+        }
+
+        let id = self.insert(kind, data["attributes"].clone());
+        if let Some(Value::Object(relations)) = data.get("relationships") {
+            for (name, rel) in relations {
+                if let Ok(rel_kind) = NanoKind::from_name(name) {
+                    let ids = parse_refs(&rel["data"]).into_iter().map(|(_, id)| id).collect();
+                    self.objects.get_mut(&(kind, id)).unwrap().relations.insert(rel_kind, ids);
+                }
+            }
+        }
+
+        let mut resp = self.respond_one(kind, id, &[]);
+        resp.status = StatusCode::CREATED;
+        resp
+    }
+
+    fn update(&mut self, kind: NanoKind, id: u64, body: &Value) -> MockResponse {
+        let obj = self.objects.get_mut(&(kind, id)).unwrap();
+        if let Some(Value::Object(attributes)) = body["data"].get("attributes") {
+            for (key, val) in attributes {
+                obj.attributes.insert(key.clone(), val.clone());
+            }
+        }
+
+        self.respond_one(kind, id, &[])
+    }
+
+    fn upload(&mut self, kind: NanoKind, id: u64, field: &str, content_type: Option<&str>, body: &[u8]) -> MockResponse {
+        let (name, part_type, content) = match content_type.and_then(|content_type| parse_multipart(content_type, body)) {
+            Some(part) => part,
+            None => return MockResponse::error(StatusCode::BAD_REQUEST, "Expected a multipart upload")
+        };
+
+        if name != field || !part_type.starts_with("image/") || content.is_empty() {
+            return MockResponse::error(StatusCode::UNPROCESSABLE_ENTITY, "Invalid image upload");
+        }
+
+        let url = format!("https://mock.nanowrimo.org/{}/{}/{}", kind.api_name(), id, field);
+        self.objects.get_mut(&(kind, id)).unwrap().attributes.insert(field.to_string(), Value::String(url));
+
+        self.respond_one(kind, id, &[])
+    }
+
+    fn relate(&mut self, kind: NanoKind, id: u64, rel: &str, body: &Value, add: bool) -> MockResponse {
+        let rel = match NanoKind::from_name(rel) {
+            Ok(rel) => rel,
+            Err(_) => return MockResponse::error(StatusCode::NOT_FOUND, "Unknown relationship")
+        };
+        let links = self.objects.get_mut(&(kind, id)).unwrap().relations.entry(rel).or_default();
+
+        for (_, rel_id) in parse_refs(&body["data"]) {
+            if add && !links.contains(&rel_id) {
+                links.push(rel_id);
+            } else if !add {
+                links.retain(|linked| *linked != rel_id);
+            }
+        }
+
+        MockResponse::no_content()
+    }
+
+    fn handle(&mut self, method: &Method, path: &str, query: &[(String, String)], headers: &HeaderMap, raw_body: &[u8]) -> MockResponse {
+        let token = headers.get("Authorization").and_then(|val| val.to_str().ok());
+        let content_type = headers.get("Content-Type").and_then(|val| val.to_str().ok());
+        let user = token.and_then(|token| self.sessions.get(token).copied());
+        let body = serde_json::from_slice(raw_body).unwrap_or(Value::Null);
+        self.log.push((method.clone(), path.trim_matches('/').to_string()));
+
+        if let Some((status, retry_after)) = self.failures.pop_front() {
+            let mut resp = MockResponse::error(status, status.canonical_reason().unwrap_or("Error"));
+            resp.retry_after = retry_after;
+            return resp;
+        }
+
+        let segments = path.trim_matches('/').split('/').collect::<Vec<_>>();
+
+        match (method, segments.as_slice()) {
+            (&Method::POST, ["users", "sign_in"]) => return self.sign_in(&body),
+            (&Method::POST, ["users", "logout"]) => {
+                if let Some(token) = token {
+                    self.sessions.remove(token);
+                }
+                return MockResponse::no_content();
+            },
+            (&Method::GET, ["users", "current"]) => return match user {
+                Some(id) => self.respond_one(NanoKind::User, id, query),
+                None => MockResponse::error(StatusCode::UNAUTHORIZED, "Not logged in")
+            },
+            (&Method::GET, ["search"]) => return self.search(query),
+            (&Method::GET, ["random_offer"]) => return match self.offers().into_iter().next() {
+                Some(offer) => MockResponse::ok(offer),
+                None => MockResponse::error(StatusCode::NOT_FOUND, "Not Found")
+            },
+            (&Method::GET, ["offers"]) => return MockResponse::ok(Value::Array(self.offers())),
+            (&Method::GET, ["store_items"]) => return MockResponse::ok(Value::Array(self.store_items.clone())),
+            _ => ()
+        }
+
+        let (kind, rest) = match segments.split_first() {
+            Some((kind, rest)) => match NanoKind::from_name(kind) {
+                Ok(kind) => (kind, rest),
+                Err(_) => return MockResponse::error(StatusCode::NOT_FOUND, "Not Found")
+            },
+            None => return MockResponse::error(StatusCode::NOT_FOUND, "Not Found")
+        };
+
+        if *method != Method::GET && user.is_none() {
+            return MockResponse::error(StatusCode::UNAUTHORIZED, "Not logged in");
+        }
+
+        if rest.is_empty() {
+            return match *method {
+                Method::GET if self.projects_hidden(kind, query, user) => MockResponse::error(StatusCode::FORBIDDEN, "Forbidden"),
+                Method::GET => self.collection(kind, query),
+                Method::POST => self.create(kind, &body),
+                _ => MockResponse::error(StatusCode::METHOD_NOT_ALLOWED, "Method Not Allowed")
+            };
+        }
+
+        let id = match self.find_id(kind, rest[0]) {
+            Some(id) => id,
+            None => return MockResponse::error(StatusCode::NOT_FOUND, "Not Found")
+        };
+
+        match (method, &rest[1..]) {
+            (&Method::GET, []) => self.respond_one(kind, id, query),
+            (&Method::PATCH, []) => self.update(kind, id, &body),
+            (&Method::DELETE, []) => {
+                self.objects.remove(&(kind, id));
+                MockResponse::no_content()
+            },
+            (&Method::GET, [rel]) => match NanoKind::from_name(rel) {
+                // Like Nano, a user's badges can't be listed through the relationship
+                Ok(NanoKind::Badge) if kind == NanoKind::User => MockResponse::error(StatusCode::NOT_FOUND, "Not Found"),
+                Ok(rel) => {
+                    let found = self.objects[&(kind, id)].relations.get(&rel)
+                        .into_iter()
+                        .flatten()
+                        .filter(|rel_id| self.objects.contains_key(&(rel, **rel_id)))
+                        .map(|rel_id| (rel, *rel_id))
+                        .collect();
+                    self.respond_many(found, query)
+                },
+                Err(_) => MockResponse::error(StatusCode::NOT_FOUND, "Not Found")
+            },
+            (&Method::POST, [field @ "avatar"]) | (&Method::POST, [field @ "cover"]) => {
+                self.upload(kind, id, field, content_type, raw_body)
+            },
+            (&Method::POST, ["relationships", rel]) => self.relate(kind, id, rel, &body, true),
+            (&Method::DELETE, ["relationships", rel]) => self.relate(kind, id, rel, &body, false),
+            _ => MockResponse::error(StatusCode::METHOD_NOT_ALLOWED, "Method Not Allowed")
+        }
+    }
+}
+
+fn dispatch(state: &Mutex<MockState>, method: &Method, path: &str, query: Option<&str>, headers: &HeaderMap, body: &[u8]) -> RawResponse {
+    let query = match query {
+        Some(query) => reqwest::Url::parse(&format!("http://mock/?{}", query))
+            .map(|url| url.query_pairs().into_owned().collect())
+            .unwrap_or_default(),
+        None => Vec::new()
+    };
+
+    let resp = state.lock().unwrap().handle(method, path, &query, headers, body);
+
+    let mut raw = RawResponse::new(resp.status, resp.body.map(|body| body.to_string()).unwrap_or_default());
+    raw.headers.insert("Content-Type", HeaderValue::from_static("application/vnd.api+json"));
+
+    if let Some(secs) = resp.retry_after {
+        raw.headers.insert("Retry-After", HeaderValue::from(secs));
+    }
+
+    raw
+}
+
+async fn serve(state: Arc<Mutex<MockState>>, req: Request<Body>) -> Result<Response<Body>, Infallible> {
+    let (parts, body) = req.into_parts();
+    let body = hyper::body::to_bytes(body).await.unwrap_or_default();
+
+    let resp = dispatch(&state, &parts.method, parts.uri.path(), parts.uri.query(), &parts.headers, &body);
+
+    let mut response = Response::new(Body::from(resp.body));
+    *response.status_mut() = resp.status;
+    *response.headers_mut() = resp.headers;
+
+    Ok(response)
+}
+
+/// An [`HttpTransport`] that answers requests directly from a [`MockServer`], in memory. Get one
+/// with [`MockServer::transport`]
+#[derive(Clone)]
+pub struct MockTransport {
+    state: Arc<Mutex<MockState>>,
+}
+
+impl fmt::Debug for MockTransport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MockTransport").finish()
+    }
+}
+
+impl HttpTransport for MockTransport {
+    fn send<'a>(&'a self, req: &'a RequestInfo) -> TransportFuture<'a, Result<RawResponse, Error>> {
+        let body = req.body.as_deref().unwrap_or_default();
+        let resp = dispatch(&self.state, &req.method, req.url.path(), req.url.query(), &req.headers, body);
+        Box::pin(async move { Ok(resp) })
+    }
+
+    /// Doesn't wait at all, but adds the duration to [`MockServer::waited`], so retries and rate
+    /// limits don't slow down tests or need a timer
+    fn delay(&self, duration: Duration) -> TransportFuture<'static, ()> {
+        self.state.lock().unwrap().waited += duration;
+        Box::pin(async {})
+    }
+}
+
+/// A running imitation of the Nano API, either listening on a random local port or only in
+/// memory. A listening server runs until the tokio runtime it was started in shuts down.
+pub struct MockServer {
+    state: Arc<Mutex<MockState>>,
+    addr: Option<SocketAddr>,
+}
+
+impl Default for MockServer {
+    fn default() -> MockServer {
+        MockServer::new()
+    }
+}
+
+impl MockServer {
+    /// The base URL of servers that are only in memory. Nothing listens on it
+    const MEMORY_URL: &'static str = "http://mock.nanowrimo.invalid/";
+
+    /// Create a new, empty, mock server that only answers requests in memory. Clients from
+    /// [`Self::client`] and [`Self::user_client`] reach it through a [`MockTransport`], and
+    /// don't need a tokio runtime to do so
+    pub fn new() -> MockServer {
+        MockServer { state: Arc::new(Mutex::new(MockState::default())), addr: None }
+    }
+
+    /// Start a new, empty, mock server. Must be called from within a tokio runtime
+    pub async fn start() -> MockServer {
+        let state = Arc::new(Mutex::new(MockState::default()));
+
+        let service_state = state.clone();
+        let make_service = make_service_fn(move |_| {
+            let state = service_state.clone();
+            async move {
+                Ok::<_, Infallible>(service_fn(move |req| serve(state.clone(), req)))
+            }
+        });
+
+        let server = Server::bind(&SocketAddr::from(([127, 0, 0, 1], 0)))
+            .serve(make_service);
+        let addr = server.local_addr();
+
+        tokio::spawn(server);
+
+        MockServer { state, addr: Some(addr) }
+    }
+
+    /// Get the base URL of this server, in the form `NanoClient` expects. Servers that are only in
+    /// memory can't be reached at their URL, except through [`Self::transport`]
+    pub fn url(&self) -> String {
+        match self.addr {
+            Some(addr) => format!("http://{}/", addr),
+            None => MockServer::MEMORY_URL.to_string()
+        }
+    }
+
+    /// Get a transport that answers requests from this server in memory, whether or not the
+    /// server is listening
+    pub fn transport(&self) -> MockTransport {
+        MockTransport { state: self.state.clone() }
+    }
+
+    fn new_client(&self, user: &str, pass: &str) -> NanoClient {
+        let mut builder = NanoClient::builder().base_url(&self.url());
+
+        if !user.is_empty() {
+            builder = builder.user(user, pass);
+        }
+        if self.addr.is_none() {
+            builder = builder.transport(self.transport());
+        }
+
+        builder.build().expect("Clients without a session store always build")
+    }
+
+    /// Create a new client for this server, with the 'anonymous' or 'guest' user
+    pub fn client(&self) -> NanoClient {
+        self.new_client("", "")
+    }
+
+    /// Create a new client for this server, that is automatically logged in as a specific user
+    pub async fn user_client(&self, user: &str, pass: &str) -> Result<NanoClient, Error> {
+        let client = self.new_client(user, pass);
+        client.login().await?;
+        Ok(client)
+    }
+
+    /// Add a user that can log in with the given identifier and password. The user gets a
+    /// `User` object with placeholder data, which can be changed with [`Self::update`].
+    /// Returns the ID of the new user
+    pub fn add_user(&self, identifier: &str, password: &str) -> u64 {
+        let mut state = self.state.lock().unwrap();
+        let id = state.insert(NanoKind::User, default_user(identifier));
+        state.logins.insert(identifier.to_string(), (password.to_string(), id));
+        id
+    }
+
+    /// Add an object of a given kind with the given (kebab-case) attributes, returning its new ID.
+    /// The attributes aren't checked, so they should match what the `Data` type of the kind expects
+    pub fn insert(&self, kind: NanoKind, attributes: Value) -> u64 {
+        self.state.lock().unwrap().insert(kind, attributes)
+    }
+
+    /// Overwrite some of the attributes of an existing object
+    pub fn update(&self, kind: NanoKind, id: u64, attributes: Value) {
+        let mut state = self.state.lock().unwrap();
+        let obj = state.objects.get_mut(&(kind, id)).expect("No mock object with the given kind and ID");
+
+        if let Value::Object(attributes) = attributes {
+            obj.attributes.extend(attributes);
+        }
+    }
+
+    /// Link an object to another through the relationship for the other's kind
+    pub fn relate(&self, kind: NanoKind, id: u64, rel: NanoKind, rel_id: u64) {
+        let mut state = self.state.lock().unwrap();
+        let links = state.objects.get_mut(&(kind, id))
+            .expect("No mock object with the given kind and ID")
+            .relations
+            .entry(rel)
+            .or_default();
+
+        if !links.contains(&rel_id) {
+            links.push(rel_id);
+        }
+    }
+
+    /// Add an item to the store, as listed by `store_items`
+    pub fn add_store_item(&self, handle: &str, image: &str, title: &str) {
+        self.state.lock().unwrap().store_items.push(json!({
+            "handle": handle,
+            "image": { "src": image },
+            "title": title
+        }));
+    }
+
+    /// Refuse to list the projects and ProjectChallenges of a user to anyone else, as Nano does
+    /// for users who hide their projects. Nano doesn't send other users' privacy settings, so
+    /// this is the only way to tell
+    pub fn hide_projects(&self, user_id: u64) {
+        self.state.lock().unwrap().hidden_projects.insert(user_id);
+    }
+
+    /// End every log-in session, as if all their tokens had expired
+    pub fn expire_sessions(&self) {
+        self.state.lock().unwrap().sessions.clear();
+    }
+
+    /// Make the next request fail with a given status, instead of being handled normally. Calling
+    /// this several times queues several failures, one for each following request. If
+    /// `retry_after` is given, it's sent in the `Retry-After` header, in seconds
+    pub fn fail_next(&self, status: StatusCode, retry_after: Option<u64>) {
+        self.state.lock().unwrap().failures.push_back((status, retry_after));
+    }
+
+    /// Get the total time clients have been told to wait through this server's [`MockTransport`],
+    /// between retries and for rate limits. The transport doesn't really wait, it only counts
+    pub fn waited(&self) -> Duration {
+        self.state.lock().unwrap().waited
+    }
+
+    /// Get the method and path (without leading or trailing slashes) of every request this server
+    /// has received, in the order they were received
+    pub fn requests(&self) -> Vec<(Method, String)> {
+        self.state.lock().unwrap().log.clone()
+    }
+
+    /// Get the attributes of an object, if it exists
+    pub fn get(&self, kind: NanoKind, id: u64) -> Option<Value> {
+        self.state.lock().unwrap()
+            .objects
+            .get(&(kind, id))
+            .map(|obj| Value::Object(obj.attributes.clone()))
+    }
+
+    /// Get the IDs of all objects linked to an object through the relationship for a given kind
+    pub fn related(&self, kind: NanoKind, id: u64, rel: NanoKind) -> Vec<u64> {
+        self.state.lock().unwrap()
+            .objects
+            .get(&(kind, id))
+            .and_then(|obj| obj.relations.get(&rel).cloned())
+            .unwrap_or_default()
+    }
+}
@@ -0,0 +1,105 @@
+//! Objects for mock servers, shared by the tests of modules that work over the members of a
+//! group, such as leaderboards and region scans
+
+use super::MockServer;
+use crate::kind::NanoKind;
+
+use chrono::NaiveDate;
+use serde_json::{json, Value};
+
+/// The ID of the Challenge members are enrolled in. No Challenge object is created for it
+pub(crate) const CHALLENGE: u64 = 900;
+
+pub(crate) fn nov(day: u32) -> NaiveDate {
+    NaiveDate::from_ymd_opt(2020, 11, day).unwrap()
+}
+
+/// The privacy settings of a user, with a given setting for who can view their projects
+pub(crate) fn privacy(view_projects: u8) -> Value {
+    json!({
+        "privacy-send-nanomessages": 2,
+        "privacy-view-buddies": 2,
+        "privacy-view-profile": 2,
+        "privacy-view-projects": view_projects,
+        "privacy-view-search": 2,
+        "privacy-visibility-activity-logs": true,
+        "privacy-visibility-buddy-lists": true,
+        "privacy-visibility-regions": true
+    })
+}
+
+pub(crate) fn group(server: &MockServer, group_type: &str) -> u64 {
+    server.insert(NanoKind::Group, json!({
+        "approved-by-id": 0,
+        "cancelled-by-id": 0,
+        "created-at": "2020-01-01T00:00:00Z",
+        "group-type": group_type,
+        "name": "Somewhere",
+        "slug": "somewhere",
+        "updated-at": "2020-01-01T00:00:00Z"
+    }))
+}
+
+/// Add a user to a group, returning the ID of the GroupUser
+pub(crate) fn join(server: &MockServer, group_id: u64, user_id: u64, accepted: i8, exit_at: Option<&str>) -> u64 {
+    server.insert(NanoKind::GroupUser, json!({
+        "created-at": "2020-10-01T00:00:00Z",
+        "entry-at": "2020-10-01T00:00:00Z",
+        "entry-method": "join",
+        "exit-at": exit_at,
+        "exit-method": null,
+        "group-code-id": null,
+        "group-id": group_id,
+        "group-type": "region",
+        "invitation-accepted": accepted,
+        "invited-by-id": null,
+        "is-admin": false,
+        "latest-message": null,
+        "num-unread-messages": 0,
+        "primary": 1,
+        "updated-at": "2020-10-01T00:00:00Z",
+        "user-id": user_id
+    }))
+}
+
+/// Enroll a user in [`CHALLENGE`] with a words ProjectChallenge, and daily counts on days of
+/// November. Returns the ID of the ProjectChallenge
+pub(crate) fn enroll(server: &MockServer, user_id: u64, count: u64, daily: &[(u32, u64)]) -> u64 {
+    enroll_with(server, user_id, json!({ "current-count": count }), daily)
+}
+
+/// Enroll a user like [`enroll`], overriding some attributes of the ProjectChallenge. The daily
+/// counts have the same unit as the ProjectChallenge
+pub(crate) fn enroll_with(server: &MockServer, user_id: u64, attributes: Value, daily: &[(u32, u64)]) -> u64 {
+    let mut attrs = json!({
+        "challenge-id": CHALLENGE,
+        "current-count": 0,
+        "ends-at": "2020-11-30",
+        "event-type": 0,
+        "goal": 50000,
+        "name": "NaNoWriMo 2020",
+        "project-id": user_id * 100,
+        "starts-at": "2020-11-01",
+        "unit-type": 0,
+        "user-id": user_id
+    });
+    if let (Value::Object(attrs), Value::Object(overrides)) = (&mut attrs, attributes) {
+        attrs.extend(overrides);
+    }
+
+    let unit_type = attrs["unit-type"].clone();
+    let pc = server.insert(NanoKind::ProjectChallenge, attrs);
+
+    for (day, count) in daily {
+        let agg = server.insert(NanoKind::DailyAggregate, json!({
+            "count": count,
+            "day": nov(*day),
+            "project-id": user_id * 100,
+            "unit-type": unit_type,
+            "user-id": null
+        }));
+        server.relate(NanoKind::ProjectChallenge, pc, NanoKind::DailyAggregate, agg);
+    }
+
+    pc
+}
@@ -0,0 +1,226 @@
+//! Counts that know their unit. Nano sends every count as a bare number, which is words for most
+//! challenges, but whole hours for hours-based ones such as some Camp NaNoWriMo projects. A
+//! [`Quantity`] keeps the two apart, so they can't be added together by mistake, and displays
+//! each the way a writer would expect.
+//!
+//! ```
+//! use nanowrimo::{Quantity, UnitType};
+//!
+//! assert_eq!(Quantity::from_count(UnitType::Words, 2500).to_string(), "2,500 words");
+//! assert_eq!(Quantity::from_count(UnitType::Hours, 3).to_string(), "3 hours");
+//! assert!(Quantity::words(10).checked_add(Quantity::hours(1)).is_err());
+//! ```
+
+use crate::data::{ChallengeData, DailyAggregateData, ProjectChallengeData, ProjectData, ProjectSessionData};
+use crate::enums::UnitType;
+use crate::error::Error;
+
+use std::cmp::Ordering;
+use std::fmt;
+
+use chrono::Duration;
+
+#[cfg(test)]
+mod tests;
+
+/// An amount of writing, in words or in time spent
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Quantity {
+    Words(i64),
+    Hours(Duration),
+}
+
+/// Write a number with commas between groups of digits, like `25,000`
+pub(crate) fn group_digits(num: i64) -> String {
+    let digits = num.unsigned_abs().to_string();
+    let mut out = if num < 0 { String::from("-") } else { String::new() };
+
+    for (idx, digit) in digits.chars().enumerate() {
+        if idx > 0 && (digits.len() - idx) % 3 == 0 {
+            out.push(',');
+        }
+        out.push(digit);
+    }
+
+    out
+}
+
+fn plural(num: i64, unit: &str) -> String {
+    if num.abs() == 1 {
+        format!("{} {}", group_digits(num), unit)
+    } else {
+        format!("{} {}s", group_digits(num), unit)
+    }
+}
+
+impl Quantity {
+    /// A number of words
+    pub fn words(count: i64) -> Quantity {
+        Quantity::Words(count)
+    }
+
+    /// A number of whole hours. Counts too large for a [`Duration`], far beyond what anyone could
+    /// write for, are clamped to the largest one there is
+    pub fn hours(count: i64) -> Quantity {
+        Quantity::Hours(Duration::try_hours(count).unwrap_or(if count < 0 { Duration::MIN } else { Duration::MAX }))
+    }
+
+    /// A count as Nano sends it, in a given unit. Counts in hours are whole hours
+    pub fn from_count(unit_type: UnitType, count: i64) -> Quantity {
+        match unit_type {
+            UnitType::Words => Quantity::words(count),
+            UnitType::Hours => Quantity::hours(count),
+        }
+    }
+
+    /// Nothing at all, in a given unit
+    pub fn zero(unit_type: UnitType) -> Quantity {
+        Quantity::from_count(unit_type, 0)
+    }
+
+    /// The unit this is in
+    pub fn unit_type(&self) -> UnitType {
+        match self {
+            Quantity::Words(_) => UnitType::Words,
+            Quantity::Hours(_) => UnitType::Hours,
+        }
+    }
+
+    /// The count as Nano stores it: words, or whole hours, rounded towards zero
+    pub fn count(&self) -> i64 {
+        match self {
+            Quantity::Words(count) => *count,
+            Quantity::Hours(time) => time.num_hours(),
+        }
+    }
+
+    /// Whether this is nothing at all
+    pub fn is_zero(&self) -> bool {
+        match self {
+            Quantity::Words(count) => *count == 0,
+            Quantity::Hours(time) => time.is_zero(),
+        }
+    }
+
+    /// Whether Nano can store this exactly, as a count of words or of whole hours
+    pub fn is_whole(&self) -> bool {
+        match self {
+            Quantity::Words(_) => true,
+            Quantity::Hours(time) => *time == Duration::hours(time.num_hours()),
+        }
+    }
+
+    fn check_unit(&self, other: &Quantity) -> Result<(), Error> {
+        if self.unit_type() != other.unit_type() {
+            return Err(Error::ValidationError(format!(
+                "Can't combine {:?} with {:?}", other.unit_type(), self.unit_type()
+            )))
+        }
+        Ok(())
+    }
+
+    /// Add two quantities. Fails if they're in different units
+    pub fn checked_add(self, other: Quantity) -> Result<Quantity, Error> {
+        self.check_unit(&other)?;
+
+        Ok(match (self, other) {
+            (Quantity::Words(a), Quantity::Words(b)) => Quantity::Words(a + b),
+            (Quantity::Hours(a), Quantity::Hours(b)) => Quantity::Hours(a + b),
+            _ => unreachable!(),
+        })
+    }
+
+    /// Subtract a quantity from this one. Fails if they're in different units
+    pub fn checked_sub(self, other: Quantity) -> Result<Quantity, Error> {
+        self.check_unit(&other)?;
+
+        Ok(match (self, other) {
+            (Quantity::Words(a), Quantity::Words(b)) => Quantity::Words(a - b),
+            (Quantity::Hours(a), Quantity::Hours(b)) => Quantity::Hours(a - b),
+            _ => unreachable!(),
+        })
+    }
+
+    /// How many times `other` fits into this, such as the fraction of a goal written. Fails if
+    /// they're in different units, and is zero if `other` is nothing
+    pub fn ratio(&self, other: &Quantity) -> Result<f64, Error> {
+        self.check_unit(other)?;
+
+        let (a, b) = match (self, other) {
+            (Quantity::Words(a), Quantity::Words(b)) => (*a as f64, *b as f64),
+            (Quantity::Hours(a), Quantity::Hours(b)) => (a.num_seconds() as f64, b.num_seconds() as f64),
+            _ => unreachable!(),
+        };
+        Ok(if b == 0.0 { 0.0 } else { a / b })
+    }
+}
+
+/// Quantities in different units can't be compared, and aren't ordered
+impl PartialOrd for Quantity {
+    fn partial_cmp(&self, other: &Quantity) -> Option<Ordering> {
+        match (self, other) {
+            (Quantity::Words(a), Quantity::Words(b)) => a.partial_cmp(b),
+            (Quantity::Hours(a), Quantity::Hours(b)) => a.partial_cmp(b),
+            _ => None,
+        }
+    }
+}
+
+/// Words are written like `2,500 words`, and hours like `3 hours` or `1 hour 30 minutes`
+impl fmt::Display for Quantity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Quantity::Words(count) => write!(f, "{}", plural(*count, "word")),
+            Quantity::Hours(time) => {
+                let hours = time.num_hours();
+                let minutes = time.num_minutes() - hours * 60;
+
+                match (hours, minutes) {
+                    (_, 0) => write!(f, "{}", plural(hours, "hour")),
+                    (0, _) => write!(f, "{}", plural(minutes, "minute")),
+                    _ => write!(f, "{} {}", plural(hours, "hour"), plural(minutes.abs(), "minute")),
+                }
+            },
+        }
+    }
+}
+
+impl ChallengeData {
+    /// The default goal, in the unit of the Challenge
+    pub fn default_goal_quantity(&self) -> Quantity {
+        Quantity::from_count(self.unit_type, self.default_goal as i64)
+    }
+}
+
+impl ProjectData {
+    /// The count of the Project, in its unit, if it has one
+    pub fn unit_quantity(&self) -> Option<Quantity> {
+        self.unit_count.map(|count| Quantity::from_count(self.unit_type, count as i64))
+    }
+}
+
+impl ProjectChallengeData {
+    /// The goal, in the unit of the challenge
+    pub fn goal_quantity(&self) -> Quantity {
+        Quantity::from_count(self.unit_type, self.goal as i64)
+    }
+
+    /// The count so far, in the unit of the challenge
+    pub fn current_quantity(&self) -> Quantity {
+        Quantity::from_count(self.unit_type, self.current_count as i64)
+    }
+}
+
+impl DailyAggregateData {
+    /// The count for the day, in its unit
+    pub fn quantity(&self) -> Quantity {
+        Quantity::from_count(self.unit_type, self.count as i64)
+    }
+}
+
+impl ProjectSessionData {
+    /// The count of the session, in its unit. Negative for sessions that correct an earlier count
+    pub fn quantity(&self) -> Quantity {
+        Quantity::from_count(self.unit_type, self.count)
+    }
+}
@@ -0,0 +1,42 @@
+use super::*;
+
+#[test]
+fn test_quantity_display() {
+    assert_eq!(Quantity::words(1).to_string(), "1 word");
+    assert_eq!(Quantity::words(0).to_string(), "0 words");
+    assert_eq!(Quantity::words(1234567).to_string(), "1,234,567 words");
+    assert_eq!(Quantity::words(-2500).to_string(), "-2,500 words");
+
+    assert_eq!(Quantity::from_count(UnitType::Hours, 3).to_string(), "3 hours");
+    assert_eq!(Quantity::hours(1).to_string(), "1 hour");
+    assert_eq!(Quantity::Hours(Duration::minutes(90)).to_string(), "1 hour 30 minutes");
+    assert_eq!(Quantity::Hours(Duration::minutes(45)).to_string(), "45 minutes");
+    assert_eq!(Quantity::zero(UnitType::Hours).to_string(), "0 hours");
+}
+
+#[test]
+fn test_quantity_units() {
+    let words = Quantity::words(1500);
+    let hours = Quantity::Hours(Duration::minutes(150));
+
+    assert_eq!(words.unit_type(), UnitType::Words);
+    assert_eq!((hours.unit_type(), hours.count(), hours.is_whole()), (UnitType::Hours, 2, false));
+    assert!(Quantity::hours(2).is_whole());
+
+    assert_eq!(words.checked_add(Quantity::words(500)).unwrap(), Quantity::words(2000));
+    assert_eq!(hours.checked_sub(Quantity::hours(1)).unwrap(), Quantity::Hours(Duration::minutes(90)));
+    assert!(matches!(words.checked_add(hours), Err(Error::ValidationError(_))));
+    assert!(matches!(hours.checked_sub(words), Err(Error::ValidationError(_))));
+
+    assert_eq!(Quantity::hours(15).ratio(&Quantity::hours(30)).unwrap(), 0.5);
+    assert_eq!(words.ratio(&Quantity::words(0)).unwrap(), 0.0);
+    assert!(words.ratio(&Quantity::hours(1)).is_err());
+
+    assert!(Quantity::hours(1) < hours);
+    assert_eq!(words.partial_cmp(&hours), None);
+
+    // Counts from Nano too large for a Duration are clamped instead of panicking
+    assert_eq!(Quantity::from_count(UnitType::Hours, i64::MAX), Quantity::Hours(Duration::MAX));
+    assert_eq!(Quantity::hours(i64::MIN), Quantity::Hours(Duration::MIN));
+    assert!(Quantity::from_count(UnitType::Hours, u64::MAX as i64).count() < 0);
+}
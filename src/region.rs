@@ -0,0 +1,359 @@
+//! Statistics over every member of a region taking part in a Challenge, for Municipal Liaisons.
+//!
+//! Regions can have thousands of members, so a [`RegionScan`] fetches them a page at a time, and
+//! keeps what it has fetched in a [`RegionCheckpoint`]. The checkpoint can be saved between pages
+//! and the scan resumed from it, so a failure part way through doesn't mean starting over.
+//!
+//! ```no_run
+//! # async fn example(client: &nanowrimo::NanoClient) -> Result<(), Box<dyn std::error::Error>> {
+//! use nanowrimo::region::RegionScan;
+//!
+//! let mut scan = RegionScan::new(403, 56);
+//! while !scan.is_done() {
+//!     let res = scan.next_page(client).await;
+//!     std::fs::write("checkpoint.json", serde_json::to_vec(scan.checkpoint())?)?;
+//!     res?;
+//! }
+//!
+//! let stats = scan.stats(chrono::Utc::now().date_naive());
+//! println!("{}", serde_json::to_string_pretty(&stats)?);
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::analytics::{DailySeries, Progress};
+use crate::client::{fetch_each, NanoClient};
+use crate::data::*;
+use crate::enums::{GroupType, InvitationStatus, UnitType, WritingType};
+use crate::error::Error;
+use crate::kind::NanoKind;
+use crate::leaderboard::{fetch_daily, fetch_project_challenge, projects_denied, projects_visible};
+use crate::quantity::Quantity;
+
+use std::collections::HashSet;
+
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+
+#[cfg(test)]
+mod tests;
+
+/// How many members are fetched in a page, unless set otherwise
+const DEFAULT_PAGE_SIZE: u64 = 50;
+
+/// How many members of a page are fetched at once
+const CONCURRENCY: usize = 4;
+
+/// The percentiles of progress included in the stats
+const PERCENTILES: [u8; 5] = [10, 25, 50, 75, 90];
+
+/// What was fetched for a single member
+#[derive(Serialize, Deserialize, Debug)]
+struct MemberRecord {
+    user_id: u64,
+    /// Whether the member lets the region see their projects. Nothing else is fetched for
+    /// members who don't
+    visible: bool,
+    project_challenge: Option<ProjectChallengeData>,
+    daily: Vec<DailyAggregateData>,
+}
+
+/// Everything a [`RegionScan`] has fetched so far. Serialize it to save the progress of a scan,
+/// and pass it to [`RegionScan::resume`] to carry on
+#[derive(Serialize, Deserialize, Debug)]
+pub struct RegionCheckpoint {
+    group_id: u64,
+    challenge_id: u64,
+    /// The next page of members to fetch, or None once there are no more
+    next_page: Option<u64>,
+    members: Vec<MemberRecord>,
+}
+
+impl RegionCheckpoint {
+    /// The ID of the region being scanned
+    pub fn group_id(&self) -> u64 {
+        self.group_id
+    }
+
+    /// The ID of the Challenge being scanned
+    pub fn challenge_id(&self) -> u64 {
+        self.challenge_id
+    }
+
+    /// The next page of members to fetch, or None once there are no more
+    pub fn next_page(&self) -> Option<u64> {
+        self.next_page
+    }
+
+    /// How many members have been fetched so far
+    pub fn members_fetched(&self) -> usize {
+        self.members.len()
+    }
+}
+
+/// A percentile of progress towards the goal
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq)]
+pub struct Percentile {
+    pub percentile: u8,
+    /// The percentage of the goal reached at this percentile
+    pub percent: f64,
+}
+
+/// The total words written in the region on a day
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq, Eq)]
+pub struct DailyTotal {
+    pub day: NaiveDate,
+    pub words: u64,
+}
+
+/// The participants writing one type of project
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq, Eq)]
+pub struct WritingTypeStats {
+    /// The type of writing, or None for projects that don't say
+    pub writing_type: Option<WritingType>,
+    pub participants: u64,
+    pub words: u64,
+}
+
+/// Statistics for a region in a Challenge, as of a given day. Word totals only include
+/// participants counting words, but progress includes everyone, as a percentage of their goal
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct RegionStats {
+    pub group_id: u64,
+    pub challenge_id: u64,
+    pub as_of: NaiveDate,
+    /// Members whose projects the region can see
+    pub members: u64,
+    /// Members whose projects the region can't see, who aren't in any other stat
+    pub hidden_members: u64,
+    /// Members taking part in the Challenge
+    pub participants: u64,
+    /// Participants who have reached their goal
+    pub winners: u64,
+    pub total_words: u64,
+    /// The median percentage of their goal participants have reached, or None without any
+    pub median_percent: Option<f64>,
+    /// Percentiles of the percentage of their goal participants have reached, from lowest
+    pub percentiles: Vec<Percentile>,
+    /// The share of participants, from 0 to 1, who are at or ahead of par
+    pub on_par: f64,
+    /// The words written on every day of the Challenge, in order
+    pub daily: Vec<DailyTotal>,
+    /// Participants and words for each type of writing, in order of most participants
+    pub by_writing_type: Vec<WritingTypeStats>,
+}
+
+/// Get a percentile of sorted values, interpolating between the values either side of it
+fn percentile(sorted: &[f64], percentile: u8) -> Option<f64> {
+    if sorted.is_empty() {
+        return None;
+    }
+
+    let rank = f64::from(percentile) / 100.0 * (sorted.len() - 1) as f64;
+    let (low, high) = (rank.floor() as usize, rank.ceil() as usize);
+    Some(sorted[low] + (sorted[high] - sorted[low]) * (rank - low as f64))
+}
+
+/// A scan over the members of a region, fetching the ProjectChallenge and daily counts of each
+/// for a single Challenge
+#[derive(Debug)]
+pub struct RegionScan {
+    checkpoint: RegionCheckpoint,
+    page_size: u64,
+}
+
+impl RegionScan {
+    /// Create a scan of a region in a Challenge, starting from the first page of members
+    pub fn new(group_id: u64, challenge_id: u64) -> RegionScan {
+        RegionScan::resume(RegionCheckpoint { group_id, challenge_id, next_page: Some(1), members: Vec::new() })
+    }
+
+    /// Carry on a scan from a checkpoint. The page size should be the same as it was before,
+    /// but members fetched twice are only counted once
+    pub fn resume(checkpoint: RegionCheckpoint) -> RegionScan {
+        RegionScan { checkpoint, page_size: DEFAULT_PAGE_SIZE }
+    }
+
+    /// Set how many members are fetched in each page
+    pub fn page_size(mut self, page_size: u64) -> RegionScan {
+        self.page_size = page_size.max(1);
+        self
+    }
+
+    /// What the scan has fetched so far
+    pub fn checkpoint(&self) -> &RegionCheckpoint {
+        &self.checkpoint
+    }
+
+    /// Get what the scan has fetched so far, to save it
+    pub fn into_checkpoint(self) -> RegionCheckpoint {
+        self.checkpoint
+    }
+
+    /// Whether every page of members has been fetched
+    pub fn is_done(&self) -> bool {
+        self.checkpoint.next_page.is_none()
+    }
+
+    async fn fetch_member(client: &NanoClient, user_id: u64, challenge_id: u64) -> Result<MemberRecord, Error> {
+        let user = client.get_typed::<UserObject>(NanoKind::User, user_id).await?.data;
+        if !projects_visible(&user.data, GroupType::Region) {
+            return Ok(MemberRecord { user_id, visible: false, project_challenge: None, daily: Vec::new() })
+        }
+
+        let (project_challenge, daily) = match fetch_project_challenge(client, user_id, challenge_id).await {
+            Ok(Some(pc)) => {
+                let daily = fetch_daily(client, &pc).await?;
+                (Some(pc.data), daily)
+            },
+            Ok(None) => (None, Vec::new()),
+            // Nano doesn't send other users' privacy settings, so this is how hidden projects show
+            Err(err) if projects_denied(&err) => {
+                return Ok(MemberRecord { user_id, visible: false, project_challenge: None, daily: Vec::new() })
+            },
+            Err(err) => return Err(err)
+        };
+
+        Ok(MemberRecord { user_id, visible: true, project_challenge, daily })
+    }
+
+    /// Fetch the next page of members. Members fetched before a failure are kept, so calling
+    /// this again after an error only fetches the rest of the page. Fails if the group isn't a
+    /// region.
+    ///
+    /// Nano pages members by offset, so members leaving the region between pages move everyone
+    /// after them back. Each page after the first reads the page before it again, so members who
+    /// moved back aren't skipped, unless more than a page of members left in between
+    pub async fn next_page(&mut self, client: &NanoClient) -> Result<(), Error> {
+        let page = match self.checkpoint.next_page {
+            Some(page) => page,
+            None => return Ok(())
+        };
+
+        if page == 1 {
+            let group = client.get_typed::<GroupObject>(NanoKind::Group, self.checkpoint.group_id).await?.data;
+            if group.data.group_type != GroupType::Region {
+                return Err(Error::ValidationError(format!(
+                    "Region stats are only for regions, not {:?} groups", group.data.group_type
+                )))
+            }
+        }
+
+        let filter = [("group_id", self.checkpoint.group_id)];
+        let mut group_users = client.get_page_typed::<GroupUserObject>(NanoKind::GroupUser, &filter, page, self.page_size)
+            .await?
+            .data;
+        let last_page = (group_users.len() as u64) < self.page_size;
+
+        if page > 1 {
+            let previous = client.get_page_typed::<GroupUserObject>(NanoKind::GroupUser, &filter, page - 1, self.page_size)
+                .await?
+                .data;
+            group_users.extend(previous);
+        }
+
+        let fetched = self.checkpoint.members.iter().map(|member| member.user_id).collect::<HashSet<_>>();
+        let user_ids = group_users.into_iter()
+            .filter(|member| member.data.invitation_accepted == InvitationStatus::Accepted)
+            .filter(|member| member.data.exit_at.is_none())
+            .map(|member| member.data.user_id)
+            .filter(|user_id| !fetched.contains(user_id))
+            .collect::<HashSet<_>>();
+
+        let challenge_id = self.checkpoint.challenge_id;
+        let results = fetch_each(user_ids.into_iter().collect(), CONCURRENCY, |user_id| {
+            RegionScan::fetch_member(client, user_id, challenge_id)
+        }).await;
+
+        let mut error = None;
+        for res in results {
+            match res {
+                Ok(member) => self.checkpoint.members.push(member),
+                Err(err) => error = error.or(Some(err)),
+            }
+        }
+        if let Some(err) = error {
+            return Err(err)
+        }
+
+        self.checkpoint.next_page = if last_page { None } else { Some(page + 1) };
+        Ok(())
+    }
+
+    /// Fetch every remaining page of members. If this fails, the checkpoint still has everything
+    /// fetched before the failure
+    pub async fn run(&mut self, client: &NanoClient) -> Result<(), Error> {
+        while !self.is_done() {
+            self.next_page(client).await?;
+        }
+        Ok(())
+    }
+
+    /// Work out the stats from the members fetched so far, as of a day. Usually called once the
+    /// scan is done, but can be called part way through for partial stats
+    pub fn stats(&self, today: NaiveDate) -> RegionStats {
+        let members = &self.checkpoint.members;
+        let participants = members.iter()
+            .filter_map(|member| member.project_challenge.as_ref().map(|pc| (pc, &member.daily)))
+            .collect::<Vec<_>>();
+
+        let mut percents = participants.iter()
+            .map(|(pc, _)| if pc.goal == 0 { 0.0 } else { pc.current_count as f64 * 100.0 / pc.goal as f64 })
+            .collect::<Vec<_>>();
+        percents.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+
+        let on_par = participants.iter()
+            .filter(|(pc, daily)| Progress::new(pc, daily.iter(), today).ahead_by() >= Quantity::zero(pc.unit_type))
+            .count();
+
+        let words = participants.iter()
+            .filter(|(pc, _)| pc.unit_type == UnitType::Words)
+            .collect::<Vec<_>>();
+        let series = words.iter()
+            .map(|(pc, daily)| DailySeries::new(pc, daily.iter()))
+            .collect::<Vec<_>>();
+        let daily = DailySeries::merged(&series)
+            .ok()
+            .flatten()
+            .map(|series| series.iter().map(|(day, words)| DailyTotal { day, words }).collect())
+            .unwrap_or_default();
+
+        let mut by_writing_type: Vec<WritingTypeStats> = Vec::new();
+        for (pc, _) in &participants {
+            let pos = match by_writing_type.iter().position(|stats| stats.writing_type == pc.writing_type) {
+                Some(pos) => pos,
+                None => {
+                    by_writing_type.push(WritingTypeStats { writing_type: pc.writing_type, participants: 0, words: 0 });
+                    by_writing_type.len() - 1
+                }
+            };
+
+            by_writing_type[pos].participants += 1;
+            if pc.unit_type == UnitType::Words {
+                by_writing_type[pos].words += pc.current_count;
+            }
+        }
+        by_writing_type.sort_by(|a, b| {
+            b.participants.cmp(&a.participants)
+                .then_with(|| a.writing_type.map(u8::from).cmp(&b.writing_type.map(u8::from)))
+        });
+
+        RegionStats {
+            group_id: self.checkpoint.group_id,
+            challenge_id: self.checkpoint.challenge_id,
+            as_of: today,
+            members: members.iter().filter(|member| member.visible).count() as u64,
+            hidden_members: members.iter().filter(|member| !member.visible).count() as u64,
+            participants: participants.len() as u64,
+            winners: participants.iter().filter(|(pc, _)| pc.current_count >= pc.goal).count() as u64,
+            total_words: words.iter().map(|(pc, _)| pc.current_count).sum(),
+            median_percent: percentile(&percents, 50),
+            percentiles: PERCENTILES.iter()
+                .filter_map(|p| percentile(&percents, *p).map(|percent| Percentile { percentile: *p, percent }))
+                .collect(),
+            on_par: if participants.is_empty() { 0.0 } else { on_par as f64 / participants.len() as f64 },
+            daily,
+            by_writing_type,
+        }
+    }
+}
@@ -0,0 +1,164 @@
+use super::*;
+use crate::mock::MockServer;
+use crate::mock::fixtures::*;
+
+use serde_json::{json, Value};
+
+/// Enroll a user with a count written evenly over the first `days` days
+fn enroll_evenly(server: &MockServer, user_id: u64, unit_type: u8, goal: u64, count: u64, days: u32, writing_type: Value) {
+    let daily = (1..=days).map(|day| (day, count / u64::from(days))).collect::<Vec<_>>();
+    enroll_with(server, user_id, json!({
+        "current-count": count,
+        "goal": goal,
+        "unit-type": unit_type,
+        "writing-type": writing_type
+    }), &daily);
+}
+
+/// A region with a mix of members. Every member has the password `pass`
+fn region_server() -> (MockServer, u64, Vec<u64>) {
+    let server = MockServer::new();
+    let users = ["ahead", "behind", "winner", "hours", "hidden", "idle", "left"].iter()
+        .map(|name| server.add_user(name, "pass"))
+        .collect::<Vec<_>>();
+
+    let region = group(&server, "region");
+    for user in &users[..6] {
+        join(&server, region, *user, 1, None);
+    }
+    join(&server, region, users[6], 1, Some("2020-11-02T00:00:00Z"));
+
+    enroll_evenly(&server, users[0], 0, 50000, 20000, 10, json!(0));
+    enroll_evenly(&server, users[1], 0, 50000, 10000, 5, json!(0));
+    enroll_evenly(&server, users[2], 0, 50000, 50000, 10, json!(1));
+    enroll_evenly(&server, users[3], 1, 30, 12, 4, Value::Null);
+    enroll_evenly(&server, users[4], 0, 50000, 40000, 10, json!(0));
+    enroll_evenly(&server, users[6], 0, 50000, 40000, 10, json!(0));
+    server.update(NanoKind::User, users[4], privacy(0));
+
+    (server, region, users)
+}
+
+/// A region with a mix of members, and a client logged in as one of them
+async fn region() -> (MockServer, NanoClient, u64, Vec<u64>) {
+    let (server, region, users) = region_server();
+    let client = server.user_client("ahead", "pass").await.unwrap();
+    (server, client, region, users)
+}
+
+#[tokio::test]
+async fn test_region_stats() {
+    let (server, client, region, users) = region().await;
+
+    let mut scan = RegionScan::new(region, CHALLENGE).page_size(3);
+    scan.run(&client).await.expect("Couldn't scan a region");
+    assert!(scan.is_done());
+    assert_eq!(scan.checkpoint().members_fetched(), 6);
+
+    let stats = scan.stats(nov(10));
+    assert_eq!((stats.members, stats.hidden_members, stats.participants, stats.winners), (5, 1, 4, 1));
+    assert_eq!(stats.total_words, 80000);
+    assert_eq!(stats.median_percent, Some(40.0));
+    let percentiles = stats.percentiles.iter().map(|p| (p.percentile, p.percent.round())).collect::<Vec<_>>();
+    assert_eq!(percentiles, vec![(10, 26.0), (25, 35.0), (50, 40.0), (75, 55.0), (90, 82.0)]);
+    assert_eq!(stats.on_par, 0.75);
+
+    assert_eq!(stats.daily.len(), 30);
+    assert_eq!(stats.daily[0], DailyTotal { day: nov(1), words: 9000 });
+    assert_eq!(stats.daily[5], DailyTotal { day: nov(6), words: 7000 });
+    assert_eq!(stats.daily[10], DailyTotal { day: nov(11), words: 0 });
+
+    assert_eq!(stats.by_writing_type, vec![
+        WritingTypeStats { writing_type: Some(WritingType::Novel), participants: 2, words: 30000 },
+        WritingTypeStats { writing_type: None, participants: 1, words: 0 },
+        WritingTypeStats { writing_type: Some(WritingType::ShortStories), participants: 1, words: 50000 },
+    ]);
+
+    let json = serde_json::to_value(&stats).unwrap();
+    assert_eq!(json["daily"][0], json!({ "day": "2020-11-01", "words": 9000 }));
+    assert_eq!(serde_json::from_value::<RegionStats>(json).unwrap(), stats);
+
+    // Nano doesn't send other members' privacy settings, so hidden projects only show when it
+    // refuses to list them
+    server.hide_projects(users[1]);
+    let mut scan = RegionScan::new(region, CHALLENGE);
+    scan.run(&client).await.unwrap();
+    let stats = scan.stats(nov(10));
+    assert_eq!((stats.members, stats.hidden_members, stats.participants, stats.total_words), (4, 2, 3, 70000));
+}
+
+#[tokio::test]
+async fn test_region_resume() {
+    let (server, client, region, users) = region().await;
+
+    // One member can't be read, so the first page fails part way through
+    server.update(NanoKind::User, users[1], json!({ "time-zone": 5 }));
+    let mut scan = RegionScan::new(region, CHALLENGE).page_size(3);
+    assert!(matches!(scan.run(&client).await, Err(Error::SerdeError(_))));
+    assert_eq!(scan.checkpoint().next_page(), Some(1));
+    assert_eq!(scan.checkpoint().members_fetched(), 2);
+
+    let saved = serde_json::to_string(&scan.into_checkpoint()).unwrap();
+    server.update(NanoKind::User, users[1], json!({ "time-zone": "UTC" }));
+
+    let before = server.requests().len();
+    let mut scan = RegionScan::resume(serde_json::from_str(&saved).unwrap()).page_size(3);
+    scan.run(&client).await.expect("Couldn't resume a region scan");
+    let user_requests = server.requests()[before..].iter()
+        .filter(|(_, path)| path.starts_with("users/"))
+        .count();
+    assert_eq!(user_requests, 4);
+
+    let stats = scan.stats(nov(10));
+    assert_eq!((stats.members, stats.participants, stats.total_words), (5, 4, 80000));
+
+    let event = group(&server, "event");
+    let res = RegionScan::new(event, CHALLENGE).run(&client).await;
+    assert!(matches!(res, Err(Error::ValidationError(_))));
+}
+
+#[tokio::test]
+async fn test_region_member_leaves() {
+    let server = MockServer::new();
+    let users = (0..6).map(|num| server.add_user(&format!("user{}", num), "pass")).collect::<Vec<_>>();
+    let client = server.user_client("user0", "pass").await.unwrap();
+
+    let region = group(&server, "region");
+    let joined = users.iter().map(|user| join(&server, region, *user, 1, None)).collect::<Vec<_>>();
+    for user in &users {
+        enroll(&server, *user, 1000, &[]);
+    }
+
+    let mut scan = RegionScan::new(region, CHALLENGE).page_size(3);
+    scan.next_page(&client).await.unwrap();
+    assert_eq!(scan.checkpoint().members_fetched(), 3);
+
+    // Someone on the first page leaves, so the first member of the second page moves back a page
+    client.delete(NanoKind::GroupUser, joined[0]).await.unwrap();
+    scan.run(&client).await.unwrap();
+    assert_eq!(scan.checkpoint().members_fetched(), 6);
+    assert_eq!(scan.stats(nov(10)).total_words, 6000);
+}
+
+#[cfg(feature = "blocking")]
+#[test]
+fn test_blocking_region() {
+    let (server, region, users) = region_server();
+    let client = crate::blocking::NanoClient::builder()
+        .base_url(&server.url())
+        .transport(server.transport())
+        .user("ahead", "pass")
+        .build_blocking()
+        .unwrap();
+    client.login().unwrap();
+
+    let mut scan = RegionScan::new(region, CHALLENGE).page_size(3);
+    client.next_region_page(&mut scan).unwrap();
+    assert_eq!(scan.checkpoint().next_page(), Some(2));
+    client.run_region_scan(&mut scan).unwrap();
+    assert_eq!(scan.stats(nov(10)).total_words, 80000);
+
+    let mut board = crate::leaderboard::Leaderboard::new(region, CHALLENGE);
+    client.refresh_leaderboard(&mut board, nov(10)).unwrap();
+    assert_eq!(board.rank_of(users[2]), Some(1));
+}
@@ -0,0 +1,751 @@
+//! Detecting drift between the attributes Nano actually sends and what the `*Data` types expect.
+//!
+//! Nano's API is private, so fields appear, disappear and change type without warning. A
+//! [`DriftDetector`] is fed raw JSON:API documents, either directly or from a recorded
+//! [`Cassette`], and compares the attributes of every object in them against the expected schema
+//! of its kind. The resulting [`DriftReport`] serializes to JSON, for use in scripts and CI.
+//!
+//! Some parts of the schema get extra attention:
+//! - `UserData` flattens several groups of settings. A group that's only partly present parses as
+//!   `None` without an error, so partial groups are reported on their own
+//! - Fields whose meaning isn't known yet, such as `speed` and `when` on project challenges, are
+//!   always reported, with the types and some example values seen for them
+//!
+//! The `schema_drift` example records a live session and prints the report for it.
+
+use crate::cassette::Cassette;
+use crate::kind::NanoKind;
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use serde::{Serialize, Deserialize};
+use serde_json::{Map, Value};
+
+/// How many distinct example values are kept for a field
+const MAX_EXAMPLES: usize = 3;
+
+/// The type of a JSON value, as far as the schema cares
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum JsonType {
+    Null,
+    Boolean,
+    Integer,
+    Float,
+    String,
+    Array,
+    Object,
+}
+
+impl JsonType {
+    /// Get the type of a JSON value
+    pub fn of(val: &Value) -> JsonType {
+        match val {
+            Value::Null => JsonType::Null,
+            Value::Bool(_) => JsonType::Boolean,
+            Value::Number(num) if num.is_f64() => JsonType::Float,
+            Value::Number(_) => JsonType::Integer,
+            Value::String(_) => JsonType::String,
+            Value::Array(_) => JsonType::Array,
+            Value::Object(_) => JsonType::Object,
+        }
+    }
+}
+
+const BOOL: &[JsonType] = &[JsonType::Boolean];
+const INT: &[JsonType] = &[JsonType::Integer];
+const NUM: &[JsonType] = &[JsonType::Integer, JsonType::Float];
+const STR: &[JsonType] = &[JsonType::String];
+
+/// The expected shape of a single attribute
+#[derive(Debug, Clone)]
+pub struct FieldSchema {
+    /// The name of the attribute, as Nano sends it
+    pub name: &'static str,
+    /// The JSON types the attribute may have, not counting null
+    pub types: &'static [JsonType],
+    /// Whether the attribute may be null
+    pub nullable: bool,
+    /// Whether the attribute may be missing entirely
+    pub optional: bool,
+    /// The flattened group the attribute belongs to, if any
+    pub group: Option<&'static str>,
+    /// Whether the meaning of the attribute isn't known yet
+    pub uncertain: bool,
+}
+
+impl FieldSchema {
+    fn new(name: &'static str, types: &'static [JsonType]) -> FieldSchema {
+        FieldSchema { name, types, nullable: false, optional: false, group: None, uncertain: false }
+    }
+
+    fn nullable(mut self) -> FieldSchema {
+        self.nullable = true;
+        self.optional = true;
+        self
+    }
+
+    fn required(mut self) -> FieldSchema {
+        self.optional = false;
+        self
+    }
+
+    fn in_group(mut self, group: &'static str) -> FieldSchema {
+        self.group = Some(group);
+        self
+    }
+
+    fn uncertain(mut self) -> FieldSchema {
+        self.uncertain = true;
+        self
+    }
+
+    /// Whether a value of a given type is allowed
+    pub fn allows(&self, ty: JsonType) -> bool {
+        if ty == JsonType::Null {
+            self.nullable
+        } else {
+            self.types.contains(&ty)
+        }
+    }
+}
+
+fn field(name: &'static str, types: &'static [JsonType]) -> FieldSchema {
+    FieldSchema::new(name, types)
+}
+
+fn nullable(name: &'static str, types: &'static [JsonType]) -> FieldSchema {
+    FieldSchema::new(name, types).nullable()
+}
+
+/// A group of attributes flattened into one struct field
+#[derive(Debug, Clone)]
+pub struct GroupSchema {
+    /// The name of the group, shared by the start of every attribute in it
+    pub name: &'static str,
+    /// Whether the whole group may be missing. If it is, a partial group is silently dropped
+    pub optional: bool,
+}
+
+const USER_GROUPS: &[GroupSchema] = &[
+    GroupSchema { name: "email", optional: true },
+    GroupSchema { name: "notification", optional: true },
+    GroupSchema { name: "privacy", optional: true },
+    GroupSchema { name: "stats", optional: false },
+];
+
+/// The expected attributes of one kind of object
+#[derive(Debug, Clone)]
+pub struct KindSchema {
+    /// The kind this schema is for
+    pub kind: NanoKind,
+    /// The expected attributes
+    pub fields: Vec<FieldSchema>,
+    /// The flattened groups the attributes may belong to
+    pub groups: &'static [GroupSchema],
+}
+
+impl KindSchema {
+    /// Get the expected schema for a kind, or None if objects of that kind can't be parsed yet
+    pub fn of(kind: NanoKind) -> Option<KindSchema> {
+        let mut groups: &'static [GroupSchema] = &[];
+
+        let fields = match kind {
+            NanoKind::Badge => vec![
+                field("active", BOOL),
+                field("adheres-to", STR),
+                field("awarded", STR),
+                field("awarded-description", STR),
+                field("badge-type", STR),
+                field("description", STR),
+                field("generic-description", STR),
+                field("list-order", INT),
+                nullable("suborder", INT),
+                field("title", STR),
+                field("unawarded", STR),
+                field("winner", BOOL),
+            ],
+            NanoKind::Challenge => vec![
+                field("default-goal", INT),
+                field("ends-at", STR),
+                nullable("event-type", INT),
+                nullable("flexible-goal", BOOL),
+                field("name", STR),
+                nullable("prep-starts-at", STR),
+                field("starts-at", STR),
+                field("unit-type", INT),
+                field("user-id", INT),
+                nullable("win-allowed-at", STR),
+                field("writing-type", INT),
+            ],
+            NanoKind::DailyAggregate => vec![
+                field("count", INT),
+                field("day", STR),
+                field("project-id", INT),
+                field("unit-type", INT),
+                nullable("user-id", INT),
+            ],
+            NanoKind::FavoriteAuthor => vec![
+                field("name", STR),
+                field("user-id", INT),
+            ],
+            NanoKind::FavoriteBook => vec![
+                field("title", STR),
+                field("user-id", INT),
+            ],
+            NanoKind::Genre => vec![
+                field("name", STR),
+                field("user-id", INT),
+            ],
+            NanoKind::Group => vec![
+                field("approved-by-id", INT),
+                nullable("avatar", STR),
+                field("cancelled-by-id", INT),
+                field("created-at", STR),
+                nullable("description", STR),
+                nullable("end-dt", STR),
+                nullable("forum-link", STR),
+                nullable("group-id", INT),
+                field("group-type", STR),
+                nullable("joining-rule", INT),
+                nullable("latitude", NUM),
+                nullable("longitude", NUM),
+                nullable("max-member-count", INT),
+                nullable("member-count", INT),
+                field("name", STR),
+                nullable("plate", STR),
+                field("slug", STR),
+                nullable("start-dt", STR),
+                nullable("time-zone", STR),
+                field("updated-at", STR),
+                nullable("url", STR),
+                nullable("user-id", INT),
+            ],
+            NanoKind::GroupExternalLink => vec![
+                field("group-id", INT),
+                nullable("label", STR),
+                field("url", STR),
+            ],
+            NanoKind::Location => vec![
+                field("city", STR),
+                field("country", STR),
+                nullable("county", STR),
+                nullable("formatted-address", STR),
+                field("latitude", NUM),
+                field("longitude", NUM),
+                nullable("map-url", STR),
+                nullable("municipality", STR),
+                field("name", STR),
+                nullable("neighborhood", STR),
+                nullable("postal-code", STR).required(),
+                field("state", STR),
+                nullable("street1", STR),
+                nullable("street2", STR),
+                nullable("utc-offset", INT),
+            ],
+            NanoKind::NanoMessage => vec![
+                field("content", STR),
+                field("created-at", STR),
+                field("group-id", INT),
+                field("official", BOOL),
+                nullable("send-email", BOOL),
+                nullable("sender-avatar-url", STR),
+                nullable("sender-name", STR),
+                nullable("sender-slug", STR),
+                field("updated-at", STR),
+                field("user-id", INT),
+            ],
+            NanoKind::Notification => vec![
+                nullable("action-id", INT),
+                field("action-type", STR),
+                field("content", STR),
+                field("created-at", STR),
+                nullable("data-count", INT),
+                field("display-at", STR),
+                field("display-status", INT),
+                field("headline", STR),
+                nullable("image-url", STR),
+                nullable("last-viewed-at", STR),
+                nullable("redirect-url", STR),
+                field("updated-at", STR),
+                field("user-id", INT),
+            ],
+            NanoKind::Page => vec![
+                field("body", STR),
+                field("url", STR),
+                field("headline", STR),
+                field("content-type", STR),
+                nullable("show-after", STR),
+                nullable("promotional-card-image", STR),
+            ],
+            NanoKind::Post => vec![
+                nullable("api-code", STR).uncertain(),
+                field("body", STR),
+                nullable("card-image", STR),
+                field("content-type", STR),
+                nullable("expires-at", STR),
+                nullable("external-link", STR),
+                field("headline", STR),
+                nullable("offer-code", STR),
+                nullable("order", INT),
+                field("published", BOOL),
+                nullable("subhead", STR).uncertain(),
+            ],
+            NanoKind::Project => vec![
+                nullable("cover", STR),
+                field("created-at", STR),
+                nullable("excerpt", STR),
+                nullable("pinterest-url", STR),
+                nullable("playlist-url", STR),
+                nullable("primary", INT).uncertain(),
+                field("privacy", INT),
+                field("slug", STR),
+                field("status", STR),
+                nullable("summary", STR),
+                field("title", STR),
+                nullable("unit-count", INT),
+                field("unit-type", INT),
+                field("user-id", INT),
+                field("writing-type", INT),
+            ],
+            NanoKind::ProjectSession => vec![
+                field("count", INT),
+                field("created-at", STR),
+                field("end", STR),
+                nullable("feeling", INT),
+                nullable("how", INT),
+                field("project-challenge-id", INT),
+                field("project-id", INT),
+                field("session-date", STR),
+                nullable("start", STR),
+                field("unit-type", INT),
+                nullable("where", INT),
+            ],
+            NanoKind::StopWatch => vec![
+                field("start", STR),
+                nullable("stop", STR),
+            ],
+            NanoKind::Timer => vec![
+                field("cancelled", BOOL),
+                field("duration", INT),
+                field("start", STR),
+            ],
+            NanoKind::User => {
+                groups = USER_GROUPS;
+
+                vec![
+                    field("admin-level", INT),
+                    nullable("avatar", STR),
+                    nullable("bio", STR),
+                    field("confirmed-at", STR),
+                    field("created-at", STR),
+                    nullable("discourse-username", STR),
+                    nullable("email", STR),
+                    field("halo", BOOL),
+                    field("laurels", INT),
+                    nullable("location", STR),
+                    field("name", STR),
+                    field("notifications-viewed-at", STR),
+                    nullable("plate", STR),
+                    nullable("postal-code", STR).required(),
+                    field("registration-path", STR),
+                    field("setting-session-count-by-session", INT).uncertain(),
+                    field("setting-session-more-info", BOOL).uncertain(),
+                    field("slug", STR),
+                    field("time-zone", STR),
+                    field("email-blog-posts", BOOL).in_group("email"),
+                    field("email-buddy-requests", BOOL).in_group("email"),
+                    field("email-events-in-home-region", BOOL).in_group("email"),
+                    field("email-nanomessages-buddies", BOOL).in_group("email"),
+                    field("email-nanomessages-hq", BOOL).in_group("email"),
+                    field("email-nanomessages-mls", BOOL).in_group("email"),
+                    field("email-nanowrimo-updates", BOOL).in_group("email"),
+                    field("email-newsletter", BOOL).in_group("email"),
+                    field("email-writing-reminders", BOOL).in_group("email"),
+                    field("notification-buddy-activities", BOOL).in_group("notification"),
+                    field("notification-buddy-requests", BOOL).in_group("notification"),
+                    field("notification-events-in-home-region", BOOL).in_group("notification"),
+                    field("notification-goal-milestones", BOOL).in_group("notification"),
+                    field("notification-nanomessages-buddies", BOOL).in_group("notification"),
+                    field("notification-nanomessages-hq", BOOL).in_group("notification"),
+                    field("notification-nanomessages-mls", BOOL).in_group("notification"),
+                    field("notification-new-badges", BOOL).in_group("notification"),
+                    field("notification-sprint-invitation", BOOL).in_group("notification"),
+                    field("notification-sprint-start", BOOL).in_group("notification"),
+                    field("notification-writing-reminders", BOOL).in_group("notification"),
+                    field("privacy-send-nanomessages", INT).in_group("privacy"),
+                    field("privacy-view-buddies", INT).in_group("privacy"),
+                    field("privacy-view-profile", INT).in_group("privacy"),
+                    field("privacy-view-projects", INT).in_group("privacy"),
+                    field("privacy-view-search", INT).in_group("privacy"),
+                    field("privacy-visibility-activity-logs", BOOL).in_group("privacy"),
+                    field("privacy-visibility-buddy-lists", BOOL).in_group("privacy"),
+                    field("privacy-visibility-regions", BOOL).in_group("privacy"),
+                    field("stats-projects", INT).in_group("stats"),
+                    field("stats-projects-enabled", BOOL).in_group("stats"),
+                    field("stats-streak", INT).in_group("stats"),
+                    field("stats-streak-enabled", BOOL).in_group("stats"),
+                    field("stats-word-count", INT).in_group("stats"),
+                    field("stats-word-count-enabled", BOOL).in_group("stats"),
+                    field("stats-wordiest", INT).in_group("stats"),
+                    field("stats-wordiest-enabled", BOOL).in_group("stats"),
+                    nullable("stats-writing-pace", INT).in_group("stats"),
+                    field("stats-writing-pace-enabled", BOOL).in_group("stats"),
+                    nullable("stats-years-done", INT).in_group("stats"),
+                    field("stats-years-enabled", BOOL).in_group("stats"),
+                    nullable("stats-years-won", INT).in_group("stats"),
+                ]
+            },
+            NanoKind::WritingLocation | NanoKind::WritingMethod => vec![
+                field("name", STR),
+            ],
+            NanoKind::GroupUser => vec![
+                field("created-at", STR),
+                nullable("entry-at", STR),
+                field("entry-method", STR),
+                nullable("exit-at", STR),
+                nullable("exit-method", STR),
+                nullable("group-code-id", INT),
+                field("group-id", INT),
+                field("group-type", STR),
+                field("invitation-accepted", INT),
+                nullable("invited-by-id", INT),
+                nullable("is-admin", BOOL),
+                nullable("latest-message", STR),
+                field("num-unread-messages", INT),
+                field("primary", INT),
+                field("updated-at", STR),
+                field("user-id", INT),
+            ],
+            NanoKind::LocationGroup => vec![
+                field("group-id", INT),
+                field("location-id", INT),
+                field("primary", BOOL),
+            ],
+            NanoKind::ProjectChallenge => vec![
+                field("challenge-id", INT),
+                field("current-count", INT),
+                field("ends-at", STR),
+                field("event-type", INT),
+                nullable("feeling", INT),
+                field("goal", INT),
+                nullable("how", INT),
+                nullable("last-recompute", STR),
+                field("name", STR),
+                field("project-id", INT),
+                nullable("speed", INT).uncertain(),
+                nullable("start-count", INT),
+                field("starts-at", STR),
+                nullable("streak", INT),
+                field("unit-type", INT),
+                field("user-id", INT),
+                nullable("when", INT).uncertain(),
+                nullable("writing-location", STR).uncertain(),
+                nullable("writing-type", INT),
+            ],
+            NanoKind::UserBadge => vec![
+                field("badge-id", INT),
+                field("created-at", STR),
+                field("project-challenge-id", INT),
+                field("user-id", INT),
+            ],
+            _ => return None
+        };
+
+        Some(KindSchema { kind, fields, groups })
+    }
+
+    /// Get the expected schema of an attribute
+    pub fn field(&self, name: &str) -> Option<&FieldSchema> {
+        self.fields.iter().find(|field| field.name == name)
+    }
+}
+
+/// A difference between the expected and observed shape of an attribute
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct FieldDrift {
+    /// The name of the attribute
+    pub field: String,
+    /// The types the attribute is expected to have, including null if it's nullable. Empty if
+    /// the attribute isn't expected at all
+    pub expected: Vec<JsonType>,
+    /// The types seen for the attribute, across all objects
+    pub observed: Vec<JsonType>,
+    /// How many objects showed this difference
+    pub objects: usize,
+    /// Whether objects with this difference fail to parse
+    pub breaking: bool,
+    /// Some of the values seen, for attributes that are new or not understood yet
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub examples: Vec<Value>,
+}
+
+/// A flattened group of attributes that was only partly present in some objects
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct GroupDrift {
+    /// The name of the group
+    pub group: String,
+    /// The attributes of the group that were missing from at least one object
+    pub missing: Vec<String>,
+    /// How many objects had only part of the group
+    pub objects: usize,
+    /// Whether objects with a partial group fail to parse. If not, the whole group is dropped
+    pub breaking: bool,
+}
+
+/// The drift found in the objects of one kind
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct KindDrift {
+    /// How many objects of this kind were seen
+    pub objects: usize,
+    /// Attributes that were seen, but aren't expected
+    pub added: Vec<FieldDrift>,
+    /// Attributes that are expected, but were missing from some objects
+    pub removed: Vec<FieldDrift>,
+    /// Attributes that were seen with a type that isn't expected
+    pub changed: Vec<FieldDrift>,
+    /// Flattened groups that were only partly present
+    pub partial_groups: Vec<GroupDrift>,
+    /// Attributes whose meaning isn't known, with what was seen for them. These aren't drift,
+    /// but are reported every time to help work out what they are
+    pub uncertain: Vec<FieldDrift>,
+}
+
+impl KindDrift {
+    /// Whether anything differs from the expected schema
+    pub fn has_drift(&self) -> bool {
+        !(self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty() && self.partial_groups.is_empty())
+    }
+
+    /// Whether any difference makes objects fail to parse
+    pub fn is_breaking(&self) -> bool {
+        self.removed.iter().chain(&self.changed).any(|drift| drift.breaking)
+            || self.partial_groups.iter().any(|drift| drift.breaking)
+    }
+}
+
+/// A machine-readable report of how observed objects differ from the expected schemas
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct DriftReport {
+    /// The drift in each kind of object seen, by its API name
+    pub kinds: BTreeMap<String, KindDrift>,
+    /// Object types that were seen, but that have no known schema
+    pub unknown_kinds: BTreeSet<String>,
+}
+
+impl DriftReport {
+    /// Whether any kind differs from its expected schema, or any unknown kind was seen
+    pub fn has_drift(&self) -> bool {
+        !self.unknown_kinds.is_empty() || self.kinds.values().any(KindDrift::has_drift)
+    }
+
+    /// Whether any difference makes objects fail to parse
+    pub fn is_breaking(&self) -> bool {
+        self.kinds.values().any(KindDrift::is_breaking)
+    }
+}
+
+/// Collects the attributes of objects from raw responses, to compare them against the expected
+/// schemas
+#[derive(Debug, Clone, Default)]
+pub struct DriftDetector {
+    observed: BTreeMap<String, Vec<Map<String, Value>>>,
+}
+
+impl DriftDetector {
+    /// Create a new detector, which hasn't seen any objects
+    pub fn new() -> DriftDetector {
+        DriftDetector::default()
+    }
+
+    /// Observe every object in a JSON:API document, both the primary data and anything included.
+    /// Documents that aren't JSON:API, such as errors or store items, are ignored
+    pub fn observe_document(&mut self, doc: &Value) {
+        let data = match doc.get("data") {
+            Some(Value::Array(objs)) => objs.iter().collect(),
+            Some(obj) => vec![obj],
+            None => Vec::new()
+        };
+        let included = doc.get("included")
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten();
+
+        for obj in data.into_iter().chain(included) {
+            self.observe_object(obj);
+        }
+    }
+
+    /// Observe a single object, with a `type` and `attributes`
+    pub fn observe_object(&mut self, obj: &Value) {
+        let kind = obj.get("type").and_then(Value::as_str);
+        let attributes = obj.get("attributes").and_then(Value::as_object);
+
+        if let (Some(kind), Some(attributes)) = (kind, attributes) {
+            self.observed.entry(kind.to_string()).or_default().push(attributes.clone());
+        }
+    }
+
+    /// Observe every successful response in a recorded cassette. Responses can be arrays of
+    /// documents, as with offers
+    pub fn observe_cassette(&mut self, cassette: &Cassette) {
+        let bodies = cassette.interactions
+            .iter()
+            .filter(|int| (200..300).contains(&int.response.status))
+            .filter_map(|int| int.response.body.as_ref());
+
+        for body in bodies {
+            match body {
+                Value::Array(docs) => docs.iter().for_each(|doc| self.observe_document(doc)),
+                doc => self.observe_document(doc)
+            }
+        }
+    }
+
+    /// Compare everything observed so far against the expected schemas
+    pub fn report(&self) -> DriftReport {
+        let mut report = DriftReport::default();
+
+        for (name, objects) in &self.observed {
+            let schema = match NanoKind::from_name(name).ok().and_then(KindSchema::of) {
+                Some(schema) => schema,
+                None => {
+                    report.unknown_kinds.insert(name.clone());
+                    continue;
+                }
+            };
+
+            report.kinds.insert(name.clone(), compare(&schema, objects));
+        }
+
+        report
+    }
+}
+
+/// Everything seen for one attribute
+#[derive(Default)]
+struct Seen {
+    types: BTreeSet<JsonType>,
+    objects: usize,
+    wrong_type: usize,
+    examples: Vec<Value>,
+}
+
+impl Seen {
+    fn add(&mut self, val: &Value) {
+        self.types.insert(JsonType::of(val));
+        self.objects += 1;
+        if self.examples.len() < MAX_EXAMPLES && !val.is_null() && !self.examples.contains(val) {
+            self.examples.push(val.clone());
+        }
+    }
+
+    fn drift(&self, name: &str, expected: Vec<JsonType>, objects: usize, breaking: bool) -> FieldDrift {
+        FieldDrift {
+            field: name.to_string(),
+            expected,
+            observed: self.types.iter().copied().collect(),
+            objects,
+            breaking,
+            examples: Vec::new(),
+        }
+    }
+}
+
+fn expected_types(field: &FieldSchema) -> Vec<JsonType> {
+    let mut types = field.types.to_vec();
+    if field.nullable {
+        types.insert(0, JsonType::Null);
+    }
+    types
+}
+
+fn compare(schema: &KindSchema, objects: &[Map<String, Value>]) -> KindDrift {
+    let mut seen = BTreeMap::<&str, Seen>::new();
+    let mut partial = BTreeMap::<&str, (BTreeSet<&str>, usize)>::new();
+
+    for attributes in objects {
+        for (key, val) in attributes {
+            let entry = seen.entry(key.as_str()).or_default();
+            entry.add(val);
+            if let Some(field) = schema.field(key) {
+                if !field.allows(JsonType::of(val)) {
+                    entry.wrong_type += 1;
+                }
+            }
+        }
+
+        for group in schema.groups {
+            let missing = schema.fields
+                .iter()
+                .filter(|field| field.group == Some(group.name) && !attributes.contains_key(field.name))
+                .map(|field| field.name)
+                .collect::<Vec<_>>();
+            let total = schema.fields.iter().filter(|field| field.group == Some(group.name)).count();
+
+            if !missing.is_empty() && missing.len() < total {
+                let entry = partial.entry(group.name).or_default();
+                entry.0.extend(missing);
+                entry.1 += 1;
+            }
+        }
+    }
+
+    let mut drift = KindDrift { objects: objects.len(), ..KindDrift::default() };
+
+    for (name, seen) in &seen {
+        let field = match schema.field(name) {
+            Some(field) => field,
+            None => {
+                drift.added.push(FieldDrift {
+                    examples: seen.examples.clone(),
+                    ..seen.drift(name, Vec::new(), seen.objects, false)
+                });
+                continue;
+            }
+        };
+
+        if seen.wrong_type > 0 {
+            drift.changed.push(seen.drift(name, expected_types(field), seen.wrong_type, true));
+        }
+        if field.uncertain {
+            drift.uncertain.push(FieldDrift {
+                examples: seen.examples.clone(),
+                ..seen.drift(name, expected_types(field), seen.objects, false)
+            });
+        }
+    }
+
+    let optional_group = |field: &FieldSchema| schema.groups
+        .iter()
+        .any(|group| Some(group.name) == field.group && group.optional);
+
+    for field in &schema.fields {
+        let present = seen.get(field.name).map_or(0, |seen| seen.objects);
+
+        // Missing members of an optional group are reported with the group instead
+        if present < objects.len() && !optional_group(field) {
+            drift.removed.push(FieldDrift {
+                field: field.name.to_string(),
+                expected: expected_types(field),
+                observed: Vec::new(),
+                objects: objects.len() - present,
+                breaking: !field.optional,
+                examples: Vec::new(),
+            });
+        }
+    }
+
+    for group in schema.groups {
+        if let Some((missing, count)) = partial.get(group.name) {
+            drift.partial_groups.push(GroupDrift {
+                group: group.name.to_string(),
+                missing: missing.iter().map(|name| name.to_string()).collect(),
+                objects: *count,
+                breaking: !group.optional,
+            });
+        }
+    }
+
+    drift
+}
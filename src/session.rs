@@ -0,0 +1,163 @@
+//! Saving and restoring the log-in session of a [`NanoClient`](crate::NanoClient), so that a client
+//! can authenticate with a previous token instead of logging in again.
+
+use crate::error::Error;
+use crate::credentials::Secret;
+
+use std::fmt;
+use std::fs;
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::process;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use chrono::{DateTime, Utc};
+use serde::{Serialize, Deserialize};
+
+/// A saved log-in session, which can be used to authenticate without a username or password.
+/// The token is redacted from `Debug` output
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct Session {
+    /// The authorization token for this session, as returned when logging in
+    pub auth_token: String,
+}
+
+impl fmt::Debug for Session {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Session")
+            .field("auth_token", &"<redacted>")
+            .finish()
+    }
+}
+
+/// The log-in state of a client
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SessionState {
+    /// Not logged in, acting as the 'anonymous' or 'guest' user
+    Anonymous,
+    /// Logged in, or restored from a saved session
+    Authenticated {
+        /// When the client logged in or restored the session
+        since: DateTime<Utc>,
+        /// The authorization token sent with each request
+        token: Secret,
+    },
+    /// Was logged in, but Nano has since rejected the token. Requests are sent as the guest user
+    /// until the client logs in again
+    Expired,
+}
+
+impl SessionState {
+    pub(crate) fn authenticated(token: String) -> SessionState {
+        SessionState::Authenticated { since: Utc::now(), token: Secret::new(token) }
+    }
+
+    /// Get the authorization token, if authenticated
+    pub fn token(&self) -> Option<&Secret> {
+        match self {
+            SessionState::Authenticated { token, .. } => Some(token),
+            _ => None
+        }
+    }
+}
+
+/// A place to save and restore a [`Session`]. The client saves its session whenever it logs in,
+/// and clears it whenever it logs out
+pub trait SessionStore: fmt::Debug {
+    /// Load the saved session, if there is one
+    fn load(&self) -> Result<Option<Session>, Error>;
+    /// Save a session, replacing any existing one
+    fn save(&self, session: &Session) -> Result<(), Error>;
+    /// Remove the saved session, if there is one
+    fn clear(&self) -> Result<(), Error>;
+}
+
+/// Counts temporary files, so saves from the same process never share one
+static TMP_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// A [`SessionStore`] that saves the session as JSON in a file. On unix, the file is only
+/// readable by the current user
+#[derive(Debug, Clone)]
+pub struct FileSessionStore {
+    path: PathBuf,
+}
+
+impl FileSessionStore {
+    /// Create a new store using the file at a given path. The file doesn't need to exist yet
+    pub fn new<P: Into<PathBuf>>(path: P) -> FileSessionStore {
+        FileSessionStore { path: path.into() }
+    }
+}
+
+impl SessionStore for FileSessionStore {
+    fn load(&self) -> Result<Option<Session>, Error> {
+        match fs::read(&self.path) {
+            Ok(data) => Ok(Some(serde_json::from_slice(&data)?)),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(err.into())
+        }
+    }
+
+    fn save(&self, session: &Session) -> Result<(), Error> {
+        // The session is written to a new file and moved over the old one, so an existing file
+        // that others can read never holds the token. Every save gets its own temporary file, so
+        // clients saving to the same store at once don't write over each other's
+        let mut tmp_name = self.path.file_name().unwrap_or_default().to_os_string();
+        tmp_name.push(format!(".{}.{}.tmp", process::id(), TMP_COUNTER.fetch_add(1, Ordering::Relaxed)));
+        let tmp_path = self.path.with_file_name(tmp_name);
+
+        let mut options = fs::OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+
+        let mut file = options.open(&tmp_path)?;
+        // The mode is only applied when the file is created, so a leftover temporary file is fixed
+        #[cfg(unix)]
+        file.set_permissions(std::os::unix::fs::PermissionsExt::from_mode(0o600))?;
+        file.write_all(&serde_json::to_vec(session)?)?;
+        drop(file);
+
+        fs::rename(&tmp_path, &self.path)?;
+        Ok(())
+    }
+
+    fn clear(&self) -> Result<(), Error> {
+        match fs::remove_file(&self.path) {
+            Err(err) if err.kind() != io::ErrorKind::NotFound => Err(err.into()),
+            _ => Ok(())
+        }
+    }
+}
+
+/// A [`SessionStore`] that keeps the session in memory. Clones share the same session, so a
+/// clone can be kept to inspect what the client saved
+#[derive(Debug, Clone, Default)]
+pub struct MemorySessionStore {
+    session: Arc<Mutex<Option<Session>>>,
+}
+
+impl MemorySessionStore {
+    /// Create a new, empty, store
+    pub fn new() -> MemorySessionStore {
+        MemorySessionStore::default()
+    }
+}
+
+impl SessionStore for MemorySessionStore {
+    fn load(&self) -> Result<Option<Session>, Error> {
+        Ok(self.session.lock().unwrap().clone())
+    }
+
+    fn save(&self, session: &Session) -> Result<(), Error> {
+        self.session.lock().unwrap().replace(session.clone());
+        Ok(())
+    }
+
+    fn clear(&self) -> Result<(), Error> {
+        self.session.lock().unwrap().take();
+        Ok(())
+    }
+}
@@ -0,0 +1,98 @@
+//! The layer that actually sends requests to Nano. By default, a [`NanoClient`](crate::NanoClient)
+//! sends them with [`reqwest`], through a [`ReqwestTransport`], but any [`HttpTransport`] can be
+//! set with [`NanoClientBuilder::transport`](crate::NanoClientBuilder::transport), for example to
+//! use a different HTTP library or async runtime, or to answer requests in memory.
+//!
+//! The transport only sees requests after all middleware has run, and isn't asked to send
+//! requests that a middleware answered. Waiting between retries, and for rate limits, also goes
+//! through the transport, so a client doesn't need a tokio timer if its transport waits some
+//! other way.
+
+use crate::error::Error;
+use crate::middleware::{RawResponse, RequestInfo};
+
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::sync::Arc;
+use std::time::Duration;
+
+use reqwest::Client;
+
+/// A boxed future returned by an [`HttpTransport`]. It doesn't need to be `Send`, since clients
+/// aren't shared between threads
+pub type TransportFuture<'a, T> = Pin<Box<dyn Future<Output = T> + 'a>>;
+
+/// A way of sending requests and waiting, which a client is built on
+pub trait HttpTransport: fmt::Debug {
+    /// Send a request, and get the complete response to it. Responses with error statuses
+    /// should be returned as responses, errors are only for requests that got no response
+    fn send<'a>(&'a self, req: &'a RequestInfo) -> TransportFuture<'a, Result<RawResponse, Error>>;
+
+    /// Wait for a duration, before retrying a request or sending one over the rate limit. Uses
+    /// the tokio timer by default
+    fn delay(&self, duration: Duration) -> TransportFuture<'static, ()> {
+        Box::pin(tokio::time::delay_for(duration))
+    }
+}
+
+impl<T: HttpTransport + ?Sized> HttpTransport for Rc<T> {
+    fn send<'a>(&'a self, req: &'a RequestInfo) -> TransportFuture<'a, Result<RawResponse, Error>> {
+        (**self).send(req)
+    }
+
+    fn delay(&self, duration: Duration) -> TransportFuture<'static, ()> {
+        (**self).delay(duration)
+    }
+}
+
+impl<T: HttpTransport + ?Sized> HttpTransport for Arc<T> {
+    fn send<'a>(&'a self, req: &'a RequestInfo) -> TransportFuture<'a, Result<RawResponse, Error>> {
+        (**self).send(req)
+    }
+
+    fn delay(&self, duration: Duration) -> TransportFuture<'static, ()> {
+        (**self).delay(duration)
+    }
+}
+
+/// An [`HttpTransport`] that sends requests with [`reqwest`], over the network. This is the
+/// transport clients use unless they're given another. It needs to be used within a tokio runtime
+#[derive(Debug, Clone, Default)]
+pub struct ReqwestTransport {
+    client: Client,
+}
+
+impl ReqwestTransport {
+    /// Create a new transport, with a default reqwest client
+    pub fn new() -> ReqwestTransport {
+        ReqwestTransport::default()
+    }
+
+    /// Create a new transport from a configured reqwest client, for example one with a proxy or
+    /// timeouts
+    pub fn from_client(client: Client) -> ReqwestTransport {
+        ReqwestTransport { client }
+    }
+}
+
+impl HttpTransport for ReqwestTransport {
+    fn send<'a>(&'a self, req: &'a RequestInfo) -> TransportFuture<'a, Result<RawResponse, Error>> {
+        Box::pin(async move {
+            let mut builder = self.client
+                .request(req.method.clone(), req.url.clone())
+                .headers(req.headers.clone());
+
+            if let Some(body) = &req.body {
+                builder = builder.body(body.clone());
+            }
+
+            let resp = builder.send().await?;
+            let status = resp.status();
+            let headers = resp.headers().clone();
+
+            Ok(RawResponse { status, headers, body: resp.bytes().await?.to_vec() })
+        })
+    }
+}
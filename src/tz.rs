@@ -0,0 +1,89 @@
+//! IANA time zones for users and groups, and conversions of Nano's UTC times into them.
+//! Requires the `tz` feature.
+//!
+//! Nano stores times in UTC, but writing days in the writer's own time zone. A session that ends
+//! in the evening in California has already ended the next day in UTC, so dates should always be
+//! taken from local times, such as with [`UserData::today`] when setting a `session_date`.
+//!
+//! ```no_run
+//! # async fn example(client: &nanowrimo::NanoClient) -> Result<(), nanowrimo::Error> {
+//! let user = client.current_user().await?;
+//! println!("It's {} for {}", user.data.data.today(), user.data.data.name);
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::data::{GroupData, ProjectSessionData, UserData};
+use crate::error::Error;
+
+use chrono::{DateTime, NaiveDate, Utc};
+
+pub use chrono_tz::Tz;
+
+/// Parse an IANA time zone name, such as `America/Los_Angeles`, as Nano sends them
+pub fn parse(name: &str) -> Result<Tz, Error> {
+    name.parse().map_err(|_| Error::UnknownTimeZone(name.to_string()))
+}
+
+/// Get the current day in a time zone
+pub fn today(tz: Tz) -> NaiveDate {
+    Utc::now().with_timezone(&tz).date_naive()
+}
+
+impl UserData {
+    /// Get the time zone this user has set
+    pub fn tz(&self) -> Tz {
+        self.time_zone
+    }
+
+    /// Convert a time into this user's time zone
+    pub fn local(&self, time: DateTime<Utc>) -> DateTime<Tz> {
+        time.with_timezone(&self.tz())
+    }
+
+    /// Get the current day for this user, which is the day a session written now belongs to
+    pub fn today(&self) -> NaiveDate {
+        today(self.tz())
+    }
+}
+
+impl GroupData {
+    /// Get the time zone this group has set, if it has one. Events often don't, and take place in
+    /// the time zone of their region
+    pub fn tz(&self) -> Option<Tz> {
+        self.time_zone
+    }
+
+    fn local(&self, time: Option<DateTime<Utc>>, default: Tz) -> Option<DateTime<Tz>> {
+        let tz = self.tz().unwrap_or(default);
+        time.map(|time| time.with_timezone(&tz))
+    }
+
+    /// Get the start of this group or event in its time zone, or in `default` if it has none
+    pub fn local_start(&self, default: Tz) -> Option<DateTime<Tz>> {
+        self.local(self.start_dt, default)
+    }
+
+    /// Get the end of this group or event in its time zone, or in `default` if it has none
+    pub fn local_end(&self, default: Tz) -> Option<DateTime<Tz>> {
+        self.local(self.end_dt, default)
+    }
+}
+
+impl ProjectSessionData {
+    /// Get the start of this session in a time zone, if it has one
+    pub fn local_start(&self, tz: Tz) -> Option<DateTime<Tz>> {
+        self.start.map(|start| start.with_timezone(&tz))
+    }
+
+    /// Get the end of this session in a time zone
+    pub fn local_end(&self, tz: Tz) -> DateTime<Tz> {
+        self.end.with_timezone(&tz)
+    }
+
+    /// Get the day this session was written on in a time zone, by when it ended. For sessions
+    /// logged by the writer, this matches `session_date` in their time zone
+    pub fn local_date(&self, tz: Tz) -> NaiveDate {
+        self.local_end(tz).date_naive()
+    }
+}
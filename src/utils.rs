@@ -21,13 +21,37 @@ pub(crate) fn de_str_num<'de, T, D>(des: D) -> Result<T, D::Error>
         .map_err(serde::de::Error::custom)
 }
 
+pub(crate) fn se_str_num<T, S>(val: &T, ser: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+        T: ToString
+{
+    val.to_string()
+        .serialize(ser)
+}
+
 pub(crate) fn de_opt_str_num<'de, T, D>(des: D) -> Result<Option<T>, D::Error>
     where
         D: Deserializer<'de>,
-        T: Deserialize<'de> + FromStr,
-        <T as FromStr>::Erro: fmt::Display
+        T: FromStr,
+        <T as FromStr>::Err: fmt::Display
 {
-    Ok(de_str_num(des).ok())
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum StrOrNum {
+        Null(()),
+        Num(serde_json::Number),
+        Str(String),
+        Other(serde::de::IgnoredAny),
+    }
+
+    // Only null is missing, so a value Nano changed the type of fails rather than disappearing
+    match StrOrNum::deserialize(des)? {
+        StrOrNum::Null(()) => Ok(None),
+        StrOrNum::Num(val) => val.to_string().parse().map(Some).map_err(serde::de::Error::custom),
+        StrOrNum::Str(val) => val.parse().map(Some).map_err(serde::de::Error::custom),
+        StrOrNum::Other(_) => Err(serde::de::Error::custom("expected a number, a string of one, or null")),
+    }
 }
 
 pub(crate) fn de_duration_mins<'de, D>(des: D) -> Result<Duration, D::Error>
@@ -105,18 +129,17 @@ pub(crate) fn de_rel_includes<'de, D>(des: D) -> Result<HashMap<NanoKind, Vec<Ob
         data: Option<Vec<ObjectRef>>
     }
 
-    HashMap::<String, DataWrap>::deserialize(des)
-        .map(
-            |table| table.into_iter()
-                .filter(
-                    |(_, val)| val.data.is_some()
-                )
-                .map(
-                    |(key, val)| (NanoKind::from_name(&key).unwrap(), val.data.unwrap())
-                )
-                .collect()
-        )
-        .map_err(serde::de::Error::custom)
+    // Relations to kinds this crate doesn't know are skipped, rather than failing the whole object
+    let mut out = HashMap::new();
+    for (key, val) in HashMap::<String, serde_json::Value>::deserialize(des)? {
+        if let Ok(kind) = NanoKind::from_name(&key) {
+            if let Some(data) = DataWrap::deserialize(val).map_err(serde::de::Error::custom)?.data {
+                out.insert(kind, data);
+            }
+        }
+    }
+
+    Ok(out)
 }
 
 pub(crate) fn se_rel_includes<S>(val: &HashMap<NanoKind, Vec<ObjectRef>>, ser: S) -> Result<S::Ok, S::Error>
@@ -138,14 +161,14 @@ pub(crate) fn de_relation<'de, D>(des: D) -> Result<HashMap<NanoKind, RelationLi
         links: RelationLink
     }
 
-    HashMap::<String, LinkWrap>::deserialize(des)
-        .map(
-            |table| table.into_iter()
-                .map(
-                    |(key, val)| (NanoKind::from_name(&key).unwrap(), val.links)
-                ).collect()
-        )
-        .map_err(serde::de::Error::custom)
+    let mut out = HashMap::new();
+    for (key, val) in HashMap::<String, serde_json::Value>::deserialize(des)? {
+        if let Ok(kind) = NanoKind::from_name(&key) {
+            out.insert(kind, LinkWrap::deserialize(val).map_err(serde::de::Error::custom)?.links);
+        }
+    }
+
+    Ok(out)
 }
 
 pub(crate) fn se_relation<S>(val: &HashMap<NanoKind, RelationLink>, ser: S) -> Result<S::Ok, S::Error>